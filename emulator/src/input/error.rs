@@ -0,0 +1,25 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug)]
+pub enum KeyMapError {
+    UnknownJoypadButton(String),
+    #[cfg(feature = "config")]
+    InvalidConfig(String),
+}
+
+impl Display for KeyMapError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyMapError::UnknownJoypadButton(name) => {
+                write!(f, "Unknown joypad button - {}", name)
+            }
+            #[cfg(feature = "config")]
+            KeyMapError::InvalidConfig(reason) => {
+                write!(f, "Invalid key map config - {}", reason)
+            }
+        }
+    }
+}
+
+impl Error for KeyMapError {}
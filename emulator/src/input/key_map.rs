@@ -0,0 +1,187 @@
+use crate::input::error::KeyMapError;
+use nes::controller::register::JoypadRegister;
+use std::collections::HashMap;
+
+// Keyboard keys a frontend can bind, independent of the windowing library.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "config", derive(serde::Serialize, serde::Deserialize))]
+pub enum Button {
+    Up,
+    Down,
+    Left,
+    Right,
+    Return,
+    Space,
+    Backspace,
+    Tab,
+    LShift,
+    RShift,
+    LCtrl,
+    RCtrl,
+    A,
+    B,
+    C,
+    D,
+    E,
+    F,
+    G,
+    H,
+    I,
+    J,
+    K,
+    L,
+    M,
+    N,
+    O,
+    P,
+    Q,
+    R,
+    S,
+    T,
+    U,
+    V,
+    W,
+    X,
+    Y,
+    Z,
+}
+
+pub struct KeyMap {
+    bindings: HashMap<Button, JoypadRegister>,
+}
+
+impl KeyMap {
+    pub fn new() -> Self {
+        KeyMap {
+            bindings: HashMap::new(),
+        }
+    }
+
+    pub fn set(&mut self, button: Button, register: JoypadRegister) {
+        self.bindings.insert(button, register);
+    }
+
+    pub fn remove(&mut self, button: Button) {
+        self.bindings.remove(&button);
+    }
+
+    pub fn get(&self, button: Button) -> Option<JoypadRegister> {
+        self.bindings.get(&button).copied()
+    }
+
+    // Bindings are stored by joypad button name, e.g. `Return = "START"`.
+    pub fn from_names<'a>(
+        names: impl IntoIterator<Item = (Button, &'a str)>,
+    ) -> Result<KeyMap, KeyMapError> {
+        let mut key_map = KeyMap::new();
+        for (button, name) in names {
+            let register = JoypadRegister::from_name(name)
+                .ok_or_else(|| KeyMapError::UnknownJoypadButton(name.to_string()))?;
+            key_map.set(button, register);
+        }
+        Ok(key_map)
+    }
+
+    pub fn to_names(&self) -> Vec<(Button, &'static str)> {
+        let mut names: Vec<(Button, &'static str)> = self
+            .bindings
+            .iter()
+            .filter_map(|(button, register)| {
+                register
+                    .iter_names()
+                    .next()
+                    .map(|(name, _)| (*button, name))
+            })
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[cfg(feature = "config")]
+    pub fn from_toml(config: &str) -> Result<KeyMap, KeyMapError> {
+        let names: std::collections::BTreeMap<Button, String> = toml::from_str(config)
+            .map_err(|error| KeyMapError::InvalidConfig(error.to_string()))?;
+        KeyMap::from_names(names.iter().map(|(button, name)| (*button, name.as_str())))
+    }
+
+    #[cfg(feature = "config")]
+    pub fn to_toml(&self) -> Result<String, KeyMapError> {
+        let names: std::collections::BTreeMap<Button, &str> = self.to_names().into_iter().collect();
+        toml::to_string(&names).map_err(|error| KeyMapError::InvalidConfig(error.to_string()))
+    }
+}
+
+impl Default for KeyMap {
+    fn default() -> Self {
+        let mut key_map = KeyMap::new();
+        key_map.set(Button::Down, JoypadRegister::DOWN);
+        key_map.set(Button::Up, JoypadRegister::UP);
+        key_map.set(Button::Right, JoypadRegister::RIGHT);
+        key_map.set(Button::Left, JoypadRegister::LEFT);
+        key_map.set(Button::E, JoypadRegister::SELECT);
+        key_map.set(Button::Return, JoypadRegister::START);
+        key_map.set(Button::A, JoypadRegister::BUTTON_A);
+        key_map.set(Button::B, JoypadRegister::BUTTON_B);
+        key_map
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_binds_every_joypad_button() {
+        let key_map = KeyMap::default();
+
+        assert_eq!(key_map.get(Button::Return), Some(JoypadRegister::START));
+        assert_eq!(key_map.get(Button::E), Some(JoypadRegister::SELECT));
+        assert_eq!(key_map.get(Button::A), Some(JoypadRegister::BUTTON_A));
+        assert_eq!(key_map.get(Button::Z), None);
+        assert_eq!(key_map.to_names().len(), 8);
+    }
+
+    #[test]
+    fn test_set_rebinds_and_remove_unbinds() {
+        let mut key_map = KeyMap::default();
+
+        key_map.set(Button::Z, JoypadRegister::BUTTON_A);
+        key_map.set(Button::A, JoypadRegister::BUTTON_B);
+        key_map.remove(Button::B);
+
+        assert_eq!(key_map.get(Button::Z), Some(JoypadRegister::BUTTON_A));
+        assert_eq!(key_map.get(Button::A), Some(JoypadRegister::BUTTON_B));
+        assert_eq!(key_map.get(Button::B), None);
+    }
+
+    #[test]
+    fn test_unknown_joypad_button_name_is_rejected() {
+        let result = KeyMap::from_names([(Button::A, "BUTTON_A"), (Button::B, "TURBO")]);
+
+        assert!(matches!(
+            result,
+            Err(KeyMapError::UnknownJoypadButton(name)) if name == "TURBO"
+        ));
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_toml_round_trip() {
+        let mut key_map = KeyMap::default();
+        key_map.set(Button::Space, JoypadRegister::SELECT);
+
+        let config = key_map.to_toml().unwrap();
+        let loaded = KeyMap::from_toml(&config).unwrap();
+
+        assert!(config.contains("Space = \"SELECT\""));
+        assert_eq!(loaded.to_names(), key_map.to_names());
+    }
+
+    #[cfg(feature = "config")]
+    #[test]
+    fn test_invalid_toml_is_rejected() {
+        let result = KeyMap::from_toml("Return = 5");
+
+        assert!(matches!(result, Err(KeyMapError::InvalidConfig(_))));
+    }
+}
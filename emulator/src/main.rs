@@ -1,17 +1,30 @@
+use emulator::input::key_map::{Button, KeyMap};
 use emulator::rendering::frame::Frame;
-use emulator::rendering::render::render;
-use nes::bus::Bus;
+use emulator::rendering::palette::Palette;
+use emulator::rendering::render::{RenderOptions, render};
+use nes::bus::{Bus, EmulationFlow};
 use nes::controller::controller::Controller;
-use nes::controller::register::JoypadRegister;
-use nes::cpu::cpu::CPU;
+use nes::cpu::clock::Clock;
+use nes::cpu::cpu::{CPU, ExitReason};
 use nes::ppu::ppu::PPU;
+use nes::ppu::render::RenderLayers;
 use nes::rom::rom::Rom;
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
 use sdl2::pixels::PixelFormatEnum;
-use std::collections::HashMap;
+
+const USAGE: &str =
+    "Usage: emulator [--bg-only] [--sprites-only] [--grid] [--no-sprite-limit] [ROM]";
 
 fn main() {
+    let (rom_path, render_layers, render_options) = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(arg) => {
+            eprintln!("Unknown argument: {arg}\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+
     let sdl_context = sdl2::init().unwrap();
     let video_subsystem = sdl_context.video().unwrap();
     let window = video_subsystem
@@ -19,7 +32,7 @@ fn main() {
         .position_centered()
         .build()
         .unwrap();
-    let mut canvas = window.into_canvas().present_vsync().build().unwrap();
+    let mut canvas = window.into_canvas().build().unwrap();
     let mut event_pump = sdl_context.event_pump().unwrap();
     canvas.set_scale(3.0, 3.0).unwrap();
     let creator = canvas.texture_creator();
@@ -27,21 +40,17 @@ fn main() {
         .create_texture_target(PixelFormatEnum::RGB24, 256, 240)
         .unwrap();
 
-    let mut key_map = HashMap::new();
-    key_map.insert(Keycode::Down, JoypadRegister::DOWN);
-    key_map.insert(Keycode::Up, JoypadRegister::UP);
-    key_map.insert(Keycode::Right, JoypadRegister::RIGHT);
-    key_map.insert(Keycode::Left, JoypadRegister::LEFT);
-    key_map.insert(Keycode::E, JoypadRegister::SELECT);
-    key_map.insert(Keycode::Return, JoypadRegister::START);
-    key_map.insert(Keycode::A, JoypadRegister::BUTTON_A);
-    key_map.insert(Keycode::B, JoypadRegister::BUTTON_B);
+    let key_map = KeyMap::default();
 
-    let bytes: Vec<u8> = std::fs::read("./roms/123.nes").unwrap();
+    let bytes: Vec<u8> = std::fs::read(&rom_path).unwrap();
     let rom = Rom::new(&bytes).unwrap();
+    let mut clock = Clock::for_region(rom.region);
     let mut frame = Frame::new();
-    let bus = Bus::new(rom, |ppu: &PPU, contoller: &mut Controller| {
-        render(ppu, &mut frame);
+    let palette = Palette::default();
+    let mut bus = Bus::new(rom, |ppu: &PPU, contoller: &mut Controller| {
+        if let Err(error) = render(ppu, &mut frame, &palette, &render_options) {
+            eprintln!("Failed to render a frame: {error}");
+        }
         texture.update(None, &frame.data, 256 * 3).unwrap();
 
         canvas.copy(&texture, None, None).unwrap();
@@ -53,31 +62,109 @@ fn main() {
                 | Event::KeyDown {
                     keycode: Some(Keycode::Escape),
                     ..
-                } => std::process::exit(0),
+                } => return EmulationFlow::Break,
                 Event::KeyDown { keycode, .. } => {
-                    if let Some(key) = key_map.get(&keycode.unwrap_or(Keycode::Ampersand)) {
-                        contoller.set_button_status(key.clone(), true);
+                    if let Some(key) = keycode
+                        .and_then(button)
+                        .and_then(|button| key_map.get(button))
+                    {
+                        contoller.set_button_status(key, true);
                     }
                 }
                 Event::KeyUp { keycode, .. } => {
-                    if let Some(key) = key_map.get(&keycode.unwrap_or(Keycode::Ampersand)) {
-                        contoller.set_button_status(key.clone(), false);
+                    if let Some(key) = keycode
+                        .and_then(button)
+                        .and_then(|button| key_map.get(button))
+                    {
+                        contoller.set_button_status(key, false);
                     }
                 }
 
                 _ => { /* do nothing */ }
             }
         }
+        EmulationFlow::Continue
+    })
+    .unwrap_or_else(|error| {
+        eprintln!("Failed to load {rom_path}: {error}");
+        std::process::exit(1);
     });
+    bus.ppu.set_render_layers(render_layers);
     let mut cpu = CPU::new(bus);
-    cpu.reset_interrupt();
-    cpu.run(|_| {}).unwrap();
+    cpu.power_on();
+    if let ExitReason::Jammed { opcode, pc } = cpu.run_throttled(&mut clock, |_| {}).unwrap() {
+        eprintln!("CPU jammed on opcode {opcode:02X} at ${pc:04X}");
+    }
+
+    // let mut file = File::create("log.txt").unwrap();
+    // cpu.run_with_trace(&mut file).unwrap();
+}
+
+// Returns the ROM path, the PPU's render layers and the rendering options, or
+// the argument that isn't recognized
+fn parse_args(
+    args: impl Iterator<Item = String>,
+) -> Result<(String, RenderLayers, RenderOptions), String> {
+    let mut rom_path = String::from("./roms/123.nes");
+    let mut layers = RenderLayers::default();
+    let mut options = RenderOptions::default();
+    for arg in args {
+        match arg.as_str() {
+            "--bg-only" => layers.sprites = false,
+            "--sprites-only" => layers.background = false,
+            "--grid" => options.grid = true,
+            "--no-sprite-limit" => layers.sprite_limit = false,
+            _ if !arg.starts_with("--") => rom_path = arg,
+            _ => return Err(arg),
+        }
+    }
+    Ok((rom_path, layers, options))
+}
+
+const KEYCODES: [(Keycode, Button); 38] = [
+    (Keycode::Up, Button::Up),
+    (Keycode::Down, Button::Down),
+    (Keycode::Left, Button::Left),
+    (Keycode::Right, Button::Right),
+    (Keycode::Return, Button::Return),
+    (Keycode::Space, Button::Space),
+    (Keycode::Backspace, Button::Backspace),
+    (Keycode::Tab, Button::Tab),
+    (Keycode::LShift, Button::LShift),
+    (Keycode::RShift, Button::RShift),
+    (Keycode::LCtrl, Button::LCtrl),
+    (Keycode::RCtrl, Button::RCtrl),
+    (Keycode::A, Button::A),
+    (Keycode::B, Button::B),
+    (Keycode::C, Button::C),
+    (Keycode::D, Button::D),
+    (Keycode::E, Button::E),
+    (Keycode::F, Button::F),
+    (Keycode::G, Button::G),
+    (Keycode::H, Button::H),
+    (Keycode::I, Button::I),
+    (Keycode::J, Button::J),
+    (Keycode::K, Button::K),
+    (Keycode::L, Button::L),
+    (Keycode::M, Button::M),
+    (Keycode::N, Button::N),
+    (Keycode::O, Button::O),
+    (Keycode::P, Button::P),
+    (Keycode::Q, Button::Q),
+    (Keycode::R, Button::R),
+    (Keycode::S, Button::S),
+    (Keycode::T, Button::T),
+    (Keycode::U, Button::U),
+    (Keycode::V, Button::V),
+    (Keycode::W, Button::W),
+    (Keycode::X, Button::X),
+    (Keycode::Y, Button::Y),
+    (Keycode::Z, Button::Z),
+];
 
-    // let mut file = OpenOptions::new()
-    //     .create(true)
-    //     .append(true)
-    //     .open("log.txt")
-    //     .unwrap();
-    // file.set_len(0).unwrap();
-    // cpu.run(|cpu| println!("{}", trace(cpu))).unwrap();
+fn button(keycode: Keycode) -> Option<Button> {
+    KEYCODES
+        .iter()
+        .find(|(key, _)| *key == keycode)
+        .map(|(_, button)| *button)
 }
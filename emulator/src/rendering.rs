@@ -1,5 +1,5 @@
-mod error;
+pub mod error;
+pub mod filter;
 pub mod frame;
 pub mod palette;
 pub mod render;
-pub mod view_port;
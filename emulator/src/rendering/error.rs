@@ -11,3 +11,45 @@ impl Display for InvalidBankNumber {
 }
 
 impl Error for InvalidBankNumber {}
+
+#[derive(Debug)]
+pub enum PaletteError {
+    InvalidSize(usize),
+}
+
+impl Display for PaletteError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PaletteError::InvalidSize(actual) => {
+                write!(f, "Palette file must be 192 bytes, actual {}", actual)
+            }
+        }
+    }
+}
+
+impl Error for PaletteError {}
+
+#[derive(Debug)]
+pub enum RenderError {
+    InvalidColorIndex(u8),
+    BufferTooSmall(usize),
+}
+
+impl Display for RenderError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderError::InvalidColorIndex(index) => {
+                write!(f, "Color index must be in range 0..=63, actual {}", index)
+            }
+            RenderError::BufferTooSmall(actual) => {
+                write!(
+                    f,
+                    "Tile buffer must be at least 192 bytes, actual {}",
+                    actual
+                )
+            }
+        }
+    }
+}
+
+impl Error for RenderError {}
@@ -0,0 +1,90 @@
+use crate::rendering::frame::Frame;
+
+// Post-processing applied to a rendered frame, e.g. CRT-style effects.
+// Filters work on the RGB data only, so they can be chained after `render`.
+pub trait PostFilter {
+    fn apply(&self, frame: &mut Frame);
+}
+
+// Leaves the frame untouched
+pub struct NoFilter;
+
+impl PostFilter for NoFilter {
+    fn apply(&self, _frame: &mut Frame) {}
+}
+
+// Darkens every other scanline, `intensity` is the percent of brightness kept
+pub struct ScanlineFilter {
+    intensity: u8,
+}
+
+impl ScanlineFilter {
+    pub fn new(intensity: u8) -> ScanlineFilter {
+        ScanlineFilter {
+            intensity: intensity.min(100),
+        }
+    }
+}
+
+impl PostFilter for ScanlineFilter {
+    fn apply(&self, frame: &mut Frame) {
+        let row_size = frame.width() * 3;
+        for row in frame.data.chunks_exact_mut(row_size).skip(1).step_by(2) {
+            for channel in row {
+                *channel = (*channel as u16 * self.intensity as u16 / 100) as u8;
+            }
+        }
+    }
+}
+
+// Blends every pixel with its horizontal neighbours, which roughly imitates
+// the color bleeding of a composite NTSC signal.
+pub struct BlurFilter;
+
+impl PostFilter for BlurFilter {
+    fn apply(&self, frame: &mut Frame) {
+        let row_size = frame.width() * 3;
+        for row in frame.data.chunks_exact_mut(row_size) {
+            let source = row.to_vec();
+            for x in 0..row_size / 3 {
+                let left = x.saturating_sub(1);
+                let right = (x + 1).min(row_size / 3 - 1);
+                for channel in 0..3 {
+                    let sum = source[left * 3 + channel] as u16
+                        + source[x * 3 + channel] as u16 * 2
+                        + source[right * 3 + channel] as u16;
+                    row[x * 3 + channel] = (sum / 4) as u8;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_no_filter_leaves_frame_unchanged() {
+        let mut frame = Frame::new();
+        for (i, value) in frame.data.iter_mut().enumerate() {
+            *value = i as u8;
+        }
+        let expected = frame.data.clone();
+
+        NoFilter.apply(&mut frame);
+
+        assert_eq!(frame.data, expected);
+    }
+
+    #[test]
+    fn test_scanline_filter_darkens_odd_rows() {
+        let mut frame = Frame::new();
+        frame.fill((200, 100, 50));
+
+        ScanlineFilter::new(50).apply(&mut frame);
+
+        assert_eq!(frame.get_pixel(0, 0), Some((200, 100, 50)));
+        assert_eq!(frame.get_pixel(0, 1), Some((100, 50, 25)));
+    }
+}
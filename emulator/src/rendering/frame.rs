@@ -1,10 +1,15 @@
+#[cfg(feature = "image")]
+use std::io;
+#[cfg(feature = "image")]
+use std::path::Path;
+
 pub struct Frame {
     pub data: Vec<u8>,
 }
 
 impl Frame {
-    const WIDTH: usize = 256;
-    const HEIGHT: usize = 240;
+    pub const WIDTH: usize = 256;
+    pub const HEIGHT: usize = 240;
 
     pub fn new() -> Frame {
         Frame {
@@ -12,6 +17,20 @@ impl Frame {
         }
     }
 
+    pub fn width(&self) -> usize {
+        Self::WIDTH
+    }
+
+    pub fn height(&self) -> usize {
+        Self::HEIGHT
+    }
+
+    pub fn fill(&mut self, rgb: (u8, u8, u8)) {
+        for pixel in self.data.chunks_exact_mut(3) {
+            pixel.copy_from_slice(&[rgb.0, rgb.1, rgb.2]);
+        }
+    }
+
     pub fn set_pixel(&mut self, x: usize, y: usize, rgb: (u8, u8, u8)) {
         let pixel_index = y * 3 * Frame::WIDTH + x * 3;
         if pixel_index + 2 < self.data.len() {
@@ -20,4 +39,35 @@ impl Frame {
             self.data[pixel_index + 2] = rgb.2;
         }
     }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> Option<(u8, u8, u8)> {
+        if x >= Frame::WIDTH || y >= Frame::HEIGHT {
+            return None;
+        }
+        let pixel_index = y * 3 * Frame::WIDTH + x * 3;
+        Some((
+            self.data[pixel_index],
+            self.data[pixel_index + 1],
+            self.data[pixel_index + 2],
+        ))
+    }
+
+    pub fn to_rgba(&self) -> Vec<u8> {
+        self.data
+            .chunks_exact(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], 0xFF])
+            .collect()
+    }
+
+    #[cfg(feature = "image")]
+    pub fn save_png(&self, path: &Path) -> io::Result<()> {
+        image::save_buffer(
+            path,
+            &self.data,
+            Self::WIDTH as u32,
+            Self::HEIGHT as u32,
+            image::ExtendedColorType::Rgb8,
+        )
+        .map_err(io::Error::other)
+    }
 }
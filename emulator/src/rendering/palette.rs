@@ -1,66 +1,36 @@
-pub const SYSTEM_PALETTE: [(u8, u8, u8); 64] = [
-    (0x80, 0x80, 0x80),
-    (0x00, 0x3D, 0xA6),
-    (0x00, 0x12, 0xB0),
-    (0x44, 0x00, 0x96),
-    (0xA1, 0x00, 0x5E),
-    (0xC7, 0x00, 0x28),
-    (0xBA, 0x06, 0x00),
-    (0x8C, 0x17, 0x00),
-    (0x5C, 0x2F, 0x00),
-    (0x10, 0x45, 0x00),
-    (0x05, 0x4A, 0x00),
-    (0x00, 0x47, 0x2E),
-    (0x00, 0x41, 0x66),
-    (0x00, 0x00, 0x00),
-    (0x05, 0x05, 0x05),
-    (0x05, 0x05, 0x05),
-    (0xC7, 0xC7, 0xC7),
-    (0x00, 0x77, 0xFF),
-    (0x21, 0x55, 0xFF),
-    (0x82, 0x37, 0xFA),
-    (0xEB, 0x2F, 0xB5),
-    (0xFF, 0x29, 0x50),
-    (0xFF, 0x22, 0x00),
-    (0xD6, 0x32, 0x00),
-    (0xC4, 0x62, 0x00),
-    (0x35, 0x80, 0x00),
-    (0x05, 0x8F, 0x00),
-    (0x00, 0x8A, 0x55),
-    (0x00, 0x99, 0xCC),
-    (0x21, 0x21, 0x21),
-    (0x09, 0x09, 0x09),
-    (0x09, 0x09, 0x09),
-    (0xFF, 0xFF, 0xFF),
-    (0x0F, 0xD7, 0xFF),
-    (0x69, 0xA2, 0xFF),
-    (0xD4, 0x80, 0xFF),
-    (0xFF, 0x45, 0xF3),
-    (0xFF, 0x61, 0x8B),
-    (0xFF, 0x88, 0x33),
-    (0xFF, 0x9C, 0x12),
-    (0xFA, 0xBC, 0x20),
-    (0x9F, 0xE3, 0x0E),
-    (0x2B, 0xF0, 0x35),
-    (0x0C, 0xF0, 0xA4),
-    (0x05, 0xFB, 0xFF),
-    (0x5E, 0x5E, 0x5E),
-    (0x0D, 0x0D, 0x0D),
-    (0x0D, 0x0D, 0x0D),
-    (0xFF, 0xFF, 0xFF),
-    (0xA6, 0xFC, 0xFF),
-    (0xB3, 0xEC, 0xFF),
-    (0xDA, 0xAB, 0xEB),
-    (0xFF, 0xA8, 0xF9),
-    (0xFF, 0xAB, 0xB3),
-    (0xFF, 0xD2, 0xB0),
-    (0xFF, 0xEF, 0xA6),
-    (0xFF, 0xF7, 0x9C),
-    (0xD7, 0xE8, 0x95),
-    (0xA6, 0xED, 0xAF),
-    (0xA2, 0xF2, 0xDA),
-    (0x99, 0xFF, 0xFC),
-    (0xDD, 0xDD, 0xDD),
-    (0x11, 0x11, 0x11),
-    (0x11, 0x11, 0x11),
-];
+use crate::rendering::error::PaletteError;
+
+pub use nes::ppu::palette::SYSTEM_PALETTE;
+
+pub struct Palette {
+    colors: [(u8, u8, u8); 64],
+}
+
+impl Palette {
+    const PAL_FILE_SIZE: usize = 192;
+
+    // A .pal file is 64 consecutive RGB triplets, one per NES color index.
+    pub fn from_pal_bytes(bytes: &[u8]) -> Result<Palette, PaletteError> {
+        if bytes.len() != Palette::PAL_FILE_SIZE {
+            return Err(PaletteError::InvalidSize(bytes.len()));
+        }
+
+        let mut colors = [(0, 0, 0); 64];
+        for (color, rgb) in colors.iter_mut().zip(bytes.chunks_exact(3)) {
+            *color = (rgb[0], rgb[1], rgb[2]);
+        }
+        Ok(Palette { colors })
+    }
+
+    pub fn color(&self, index: u8) -> (u8, u8, u8) {
+        self.colors[(index & 0b0011_1111) as usize]
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            colors: SYSTEM_PALETTE,
+        }
+    }
+}
@@ -1,125 +1,449 @@
+use crate::rendering::error::RenderError;
 use crate::rendering::frame::Frame;
-use crate::rendering::palette::SYSTEM_PALETTE;
-use crate::rendering::view_port::ViewPort;
-use nes::ppu::palette::{get_bg_palette, sprite_palette};
+use crate::rendering::palette::Palette;
 use nes::ppu::ppu::PPU;
-use std::ops::Range;
-
-pub fn render(ppu: &PPU, frame: &mut Frame) {
-    let (main_name_table, second_name_table) = ppu.get_name_table_ranges();
-    let scroll_x = ppu.get_x_scroll() as usize;
-    let scroll_y = ppu.get_y_scroll() as usize;
-
-    render_name_table(
-        ppu,
-        frame,
-        main_name_table,
-        ViewPort::new(scroll_x, scroll_y, 256, 240),
-        -(scroll_x as isize),
-        -(scroll_y as isize),
-    );
-    if scroll_x > 0 {
-        render_name_table(
-            ppu,
-            frame,
-            second_name_table,
-            ViewPort::new(0, 0, scroll_x, 240),
-            (256 - scroll_x) as isize,
-            0,
+use nes::ppu::register::ppumask::PPUMASK;
+use nes::ppu::render::{greyscale, tile_pixel};
+
+// Emphasis bits darken the channels that are not emphasized. The NTSC PPU
+// attenuates them roughly to 75-85% of their value, 0.816 is a commonly used
+// approximation.
+// https://www.nesdev.org/wiki/NTSC_video#Color_Tint_Bits
+const EMPHASIS_ATTENUATION: f32 = 0.816;
+
+// RGB bytes `render_tile` writes
+pub const TILE_RGB_SIZE: usize = 8 * 8 * 3;
+
+// System palettes have 64 colors
+const MAX_COLOR_INDEX: u8 = 0x3F;
+
+// Color of the tile grid overlay
+const GRID_COLOR: (u8, u8, u8) = (0xFF, 0x00, 0xFF);
+
+#[derive(Default)]
+pub struct RenderOptions {
+    // Outline background tiles, it follows the scroll.
+    pub grid: bool,
+}
+
+// Colors the PPU's last frame with the system palette and PPUMASK emphasis.
+// Pixels with an index past the 64 palette colors are left as they were, the
+// rest of the frame is still drawn and the first such index is returned.
+pub fn render(
+    ppu: &PPU,
+    frame: &mut Frame,
+    palette: &Palette,
+    options: &RenderOptions,
+) -> Result<(), RenderError> {
+    let mask = ppu.mask_flags();
+    let mut invalid_index = None;
+    for (rgb, &color_idx) in frame.data.chunks_exact_mut(3).zip(ppu.index_buffer()) {
+        if color_idx > MAX_COLOR_INDEX {
+            invalid_index.get_or_insert(color_idx);
+            continue;
+        }
+        let (red, green, blue) = masked_color(palette, color_idx, mask);
+        rgb.copy_from_slice(&[red, green, blue]);
+    }
+    if options.grid && mask.contains(PPUMASK::ENABLE_BG_RENDERING) && ppu.render_layers().background
+    {
+        draw_grid(ppu, frame);
+    }
+    match invalid_index {
+        Some(color_idx) => Err(RenderError::InvalidColorIndex(color_idx)),
+        None => Ok(()),
+    }
+}
+
+// Outlines background tiles, every scanline with the scroll it was drawn with
+fn draw_grid(ppu: &PPU, frame: &mut Frame) {
+    for y in 0..Frame::HEIGHT {
+        let (scroll_x, scroll_y) = ppu.scroll_at_scanline(y);
+        let tile_edge = (y + scroll_y as usize).is_multiple_of(8);
+        for x in 0..Frame::WIDTH {
+            if tile_edge || (x + scroll_x as usize).is_multiple_of(8) {
+                frame.set_pixel(x, y, GRID_COLOR);
+            }
+        }
+    }
+}
+
+// Draws one CHR tile as 8x8 RGB pixels, row by row, e.g. for a tile picker.
+// `palette` holds the 4 color indices pixel values 0-3 map to, colors come
+// from the default system palette.
+pub fn render_tile(
+    chr_tile: &[u8; 16],
+    palette: [u8; 4],
+    out: &mut [u8],
+) -> Result<(), RenderError> {
+    if out.len() < TILE_RGB_SIZE {
+        return Err(RenderError::BufferTooSmall(out.len()));
+    }
+    let system_palette = Palette::default();
+    for (pixel, rgb) in out[..TILE_RGB_SIZE].chunks_exact_mut(3).enumerate() {
+        let value = tile_pixel(chr_tile, pixel % 8, pixel / 8);
+        let (red, green, blue) = system_palette.color(palette[value as usize]);
+        rgb.copy_from_slice(&[red, green, blue]);
+    }
+    Ok(())
+}
+
+fn masked_color(palette: &Palette, color_idx: u8, mask: PPUMASK) -> (u8, u8, u8) {
+    let color_idx = greyscale(color_idx, mask);
+    let (mut red, mut green, mut blue) = palette.color(color_idx);
+
+    let attenuate = |channel: u8| (channel as f32 * EMPHASIS_ATTENUATION) as u8;
+    if mask.contains(PPUMASK::EMPHASIZE_RED) {
+        green = attenuate(green);
+        blue = attenuate(blue);
+    }
+    if mask.contains(PPUMASK::EMPHASIZE_GREEN) {
+        red = attenuate(red);
+        blue = attenuate(blue);
+    }
+    if mask.contains(PPUMASK::EMPHASIZE_BLUE) {
+        red = attenuate(red);
+        green = attenuate(green);
+    }
+
+    (red, green, blue)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rendering::palette::SYSTEM_PALETTE;
+    use nes::mapper::mapper::create_mapper;
+    use nes::ppu::mirroring::Mirroring;
+    use nes::ppu::render::RenderLayers;
+    use nes::region::Region;
+
+    #[test]
+    fn test_disabled_rendering_draws_backdrop() {
+        let mapper = create_mapper(0, 0x4000, 0x2000, Mirroring::Horizontal).unwrap();
+        let mut ppu = PPU::new(
+            vec![0xFF; 0x2000],
+            Mirroring::Horizontal,
+            false,
+            Region::Ntsc,
+            mapper,
         );
-    } else if scroll_y > 0 {
-        render_name_table(
-            ppu,
-            frame,
-            second_name_table,
-            ViewPort::new(0, 0, 256, scroll_y),
-            0,
-            (240 - scroll_y) as isize,
+        ppu.write_ppuaddr(0x3F);
+        ppu.write_ppuaddr(0x00);
+        ppu.write_ppudata(0x21);
+        ppu.write_ppumask(0);
+        while !ppu.tick(1) {}
+
+        let mut frame = Frame::new();
+        frame.set_pixel(10, 10, (1, 2, 3));
+        render(
+            &ppu,
+            &mut frame,
+            &Palette::default(),
+            &RenderOptions::default(),
+        )
+        .unwrap();
+
+        let (red, green, blue) = SYSTEM_PALETTE[0x21];
+        assert!(
+            frame
+                .data
+                .chunks_exact(3)
+                .all(|pixel| pixel == [red, green, blue])
         );
     }
 
-    for i in (0..256).step_by(4).rev() {
-        let tile_idx = ppu.read_oamdata(i + 1) as usize;
-        let tile_x = ppu.read_oamdata(i + 3) as usize;
-        let tile_y = ppu.read_oamdata(i) as usize;
-
-        let flip_vertical = ppu.read_oamdata(i + 2) >> 7 & 1 == 1;
-        let flip_horizontal = ppu.read_oamdata(i + 2) >> 6 & 1 == 1;
-        let palette_idx = ppu.read_oamdata(i + 2) & 0b11;
-        let sprite_palette = sprite_palette(ppu, palette_idx);
-
-        let sprite_tile = ppu.read_sprite_tile(tile_idx);
-
-        for y in 0..=7 {
-            let mut upper = sprite_tile[y];
-            let mut lower = sprite_tile[y + 8];
-
-            'c: for x in (0..=7).rev() {
-                let value = (1 & lower) << 1 | (1 & upper);
-                upper >>= 1;
-                lower >>= 1;
-                let rgb = match value {
-                    0 => continue 'c,
-                    1 => SYSTEM_PALETTE[sprite_palette[1] as usize],
-                    2 => SYSTEM_PALETTE[sprite_palette[2] as usize],
-                    3 => SYSTEM_PALETTE[sprite_palette[3] as usize],
-                    _ => panic!("Impossible value for tile pixel."),
-                };
-                match (flip_horizontal, flip_vertical) {
-                    (false, false) => frame.set_pixel(tile_x + x, tile_y + y, rgb),
-                    (true, false) => frame.set_pixel(tile_x + 7 - x, tile_y + y, rgb),
-                    (false, true) => frame.set_pixel(tile_x + x, tile_y + 7 - y, rgb),
-                    (true, true) => frame.set_pixel(tile_x + 7 - x, tile_y + 7 - y, rgb),
-                };
+    #[test]
+    fn test_invalid_color_index_is_reported_and_skipped() {
+        let mapper = create_mapper(0, 0x4000, 0x2000, Mirroring::Horizontal).unwrap();
+        let mut ppu = PPU::new(
+            vec![0; 0x2000],
+            Mirroring::Horizontal,
+            false,
+            Region::Ntsc,
+            mapper,
+        );
+        // Palette RAM keeps all 8 bits written to it
+        ppu.write_ppuaddr(0x3F);
+        ppu.write_ppuaddr(0x00);
+        ppu.write_ppudata(0x7F);
+        ppu.write_ppumask(0);
+        while !ppu.tick(1) {}
+
+        let mut frame = Frame::new();
+        frame.fill((1, 2, 3));
+        let result = render(
+            &ppu,
+            &mut frame,
+            &Palette::default(),
+            &RenderOptions::default(),
+        );
+
+        assert!(matches!(result, Err(RenderError::InvalidColorIndex(0x7F))));
+        assert_eq!(frame.get_pixel(10, 10), Some((1, 2, 3)));
+    }
+
+    #[test]
+    fn test_behind_background_sprite_shows_only_through_transparent_pixels() {
+        // Tile 0 is solid, tile 1 transparent
+        let mut chr = vec![0; 0x2000];
+        chr[..16].fill(0xFF);
+        let mapper = create_mapper(0, 0x4000, 0x2000, Mirroring::Horizontal).unwrap();
+        let mut ppu = PPU::new(chr, Mirroring::Horizontal, false, Region::Ntsc, mapper);
+        // Transparent background tile at column 5, row 10
+        ppu.write_ppuaddr(0x21);
+        ppu.write_ppuaddr(0x45);
+        ppu.write_ppudata(1);
+        ppu.write_ppuaddr(0x3F);
+        ppu.write_ppuaddr(0x00);
+        for color in [0x0F, 0x00, 0x00, 0x16] {
+            ppu.write_ppudata(color);
+        }
+        ppu.write_ppuaddr(0x3F);
+        ppu.write_ppuaddr(0x13);
+        ppu.write_ppudata(0x21);
+        ppu.write_oamaddr(0);
+        let sprites = [
+            // Behind an opaque background
+            [50, 0, 0b0010_0000, 16],
+            // In front, but sprite 0 owns these pixels
+            [50, 0, 0, 16],
+            // Behind a transparent background tile
+            [80, 0, 0b0010_0000, 40],
+            // In front of an opaque background
+            [100, 0, 0, 100],
+        ];
+        for i in 0..64 {
+            for value in sprites.get(i).copied().unwrap_or([0xF0, 0, 0, 0]) {
+                ppu.write_oamdata(value);
             }
         }
+        ppu.write_ppuctrl(0);
+        ppu.write_ppuscroll(0);
+        ppu.write_ppuscroll(0);
+        ppu.write_ppumask(0b0001_1110);
+        while !ppu.tick(1) {}
+        while !ppu.tick(1) {}
+
+        let mut frame = Frame::new();
+        render(
+            &ppu,
+            &mut frame,
+            &Palette::default(),
+            &RenderOptions::default(),
+        )
+        .unwrap();
+
+        let background = Some(SYSTEM_PALETTE[0x16]);
+        let sprite = Some(SYSTEM_PALETTE[0x21]);
+        assert_eq!(frame.get_pixel(20, 53), background);
+        assert_eq!(frame.get_pixel(44, 83), sprite);
+        assert_eq!(frame.get_pixel(104, 103), sprite);
+        assert_eq!(frame.get_pixel(60, 60), background);
     }
-}
 
-pub fn render_name_table(
-    ppu: &PPU,
-    frame: &mut Frame,
-    name_table_range: Range<usize>,
-    view_port: ViewPort,
-    shift_x: isize,
-    shift_y: isize,
-) {
-    for i in 0..0x03C0usize {
-        let tile_x = i % 32;
-        let tile_y = i / 32;
-        let tile = ppu.read_tile(i, &name_table_range);
-        let palette = get_bg_palette(ppu, tile_x, tile_y);
-
-        for y in 0..=7 {
-            let mut upper = tile[y];
-            let mut lower = tile[y + 8];
-
-            for x in (0..=7).rev() {
-                let rgb = match (1 & lower) << 1 | (1 & upper) {
-                    0 => SYSTEM_PALETTE[palette[0] as usize],
-                    1 => SYSTEM_PALETTE[palette[1] as usize],
-                    2 => SYSTEM_PALETTE[palette[2] as usize],
-                    3 => SYSTEM_PALETTE[palette[3] as usize],
-                    _ => panic!("Impossible value for tile pixel."),
-                };
-                upper >>= 1;
-                lower >>= 1;
-
-                let pixel_x = tile_x * 8 + x;
-                let pixel_y = tile_y * 8 + y;
-
-                if pixel_x >= view_port.x1
-                    && pixel_x < view_port.x2
-                    && pixel_y >= view_port.y1
-                    && pixel_y < view_port.y2
-                {
-                    frame.set_pixel(
-                        (shift_x + pixel_x as isize) as usize,
-                        (shift_y + pixel_y as isize) as usize,
-                        rgb,
-                    );
-                }
+    #[test]
+    fn test_sprite_limit_drops_ninth_sprite() {
+        let mapper = create_mapper(0, 0x4000, 0x2000, Mirroring::Horizontal).unwrap();
+        let mut ppu = PPU::new(
+            vec![0xFF; 0x2000],
+            Mirroring::Horizontal,
+            false,
+            Region::Ntsc,
+            mapper,
+        );
+        ppu.write_oamaddr(0);
+        for i in 0..64u8 {
+            // Nine solid sprites side by side on the same row
+            let (y, x) = if i < 9 { (50, 16 + i * 8) } else { (0xF0, 0) };
+            for value in [y, 0, 0, x] {
+                ppu.write_oamdata(value);
             }
         }
+        // Sprite palette 0, color 3
+        ppu.write_ppuaddr(0x3F);
+        ppu.write_ppuaddr(0x13);
+        ppu.write_ppudata(0x21);
+        ppu.write_ppumask(0b0001_0100);
+        let ninth_sprite_x = 16 + 8 * 8;
+        while !ppu.tick(1) {}
+
+        let mut frame = Frame::new();
+        render(
+            &ppu,
+            &mut frame,
+            &Palette::default(),
+            &RenderOptions::default(),
+        )
+        .unwrap();
+        let backdrop = frame.get_pixel(0, 0);
+        assert_eq!(frame.get_pixel(ninth_sprite_x, 50), backdrop);
+        assert_ne!(frame.get_pixel(16, 50), backdrop);
+
+        ppu.set_render_layers(RenderLayers {
+            sprite_limit: false,
+            ..RenderLayers::default()
+        });
+        while !ppu.tick(1) {}
+        render(
+            &ppu,
+            &mut frame,
+            &Palette::default(),
+            &RenderOptions::default(),
+        )
+        .unwrap();
+        assert_ne!(frame.get_pixel(ninth_sprite_x, 50), backdrop);
+    }
+
+    #[test]
+    fn test_render_tile_decodes_bit_planes() {
+        let mut tile = [0; 16];
+        // Row 0: values 3, 2, 0, 0, 0, 0, 0, 1, row 7 is all 1
+        tile[0] = 0b1000_0001;
+        tile[8] = 0b1100_0000;
+        tile[7] = 0xFF;
+        let palette = [0x0F, 0x01, 0x21, 0x30];
+        let mut out = [0xAA; TILE_RGB_SIZE + 3];
+
+        render_tile(&tile, palette, &mut out).unwrap();
+
+        let pixel = |x: usize, y: usize| {
+            let i = (y * 8 + x) * 3;
+            (out[i], out[i + 1], out[i + 2])
+        };
+        assert_eq!(pixel(0, 0), SYSTEM_PALETTE[0x30]);
+        assert_eq!(pixel(1, 0), SYSTEM_PALETTE[0x21]);
+        assert_eq!(pixel(2, 0), SYSTEM_PALETTE[0x0F]);
+        assert_eq!(pixel(7, 0), SYSTEM_PALETTE[0x01]);
+        assert_eq!(pixel(3, 4), SYSTEM_PALETTE[0x0F]);
+        assert!((0..8).all(|x| pixel(x, 7) == SYSTEM_PALETTE[0x01]));
+        // Bytes past the tile are left alone
+        assert_eq!(out[TILE_RGB_SIZE..], [0xAA; 3]);
+
+        let mut short = [0; TILE_RGB_SIZE - 1];
+        assert!(matches!(
+            render_tile(&tile, palette, &mut short),
+            Err(RenderError::BufferTooSmall(191))
+        ));
+    }
+
+    #[test]
+    fn test_layer_toggles_and_grid_overlay() {
+        let mapper = create_mapper(0, 0x4000, 0x2000, Mirroring::Horizontal).unwrap();
+        let mut ppu = PPU::new(
+            vec![0xFF; 0x2000],
+            Mirroring::Horizontal,
+            false,
+            Region::Ntsc,
+            mapper,
+        );
+        ppu.write_ppuaddr(0x3F);
+        ppu.write_ppuaddr(0x00);
+        for color in [0x0F, 0x00, 0x00, 0x16] {
+            ppu.write_ppudata(color);
+        }
+        ppu.write_ppuaddr(0x3F);
+        ppu.write_ppuaddr(0x13);
+        ppu.write_ppudata(0x21);
+        ppu.write_oamaddr(0);
+        for i in 0..64 {
+            let sprite = if i == 0 {
+                [50, 0, 0, 16]
+            } else {
+                [0xF0, 0, 0, 0]
+            };
+            for value in sprite {
+                ppu.write_oamdata(value);
+            }
+        }
+        ppu.write_ppuctrl(0);
+        ppu.write_ppuscroll(0);
+        ppu.write_ppuscroll(0);
+        ppu.write_ppumask(0b0001_1110);
+        while !ppu.tick(1) {}
+        while !ppu.tick(1) {}
+
+        let backdrop = Some(SYSTEM_PALETTE[0x0F]);
+        let background = Some(SYSTEM_PALETTE[0x16]);
+        let sprite = Some(SYSTEM_PALETTE[0x21]);
+        let mut frame = Frame::new();
+        let mut render_with = |layers: RenderLayers, options: RenderOptions| {
+            ppu.set_render_layers(layers);
+            while !ppu.tick(1) {}
+            render(&ppu, &mut frame, &Palette::default(), &options).unwrap();
+            (
+                frame.get_pixel(20, 53),
+                frame.get_pixel(60, 60),
+                frame.get_pixel(64, 64),
+            )
+        };
+
+        let sprites_only = RenderLayers {
+            background: false,
+            ..RenderLayers::default()
+        };
+        assert_eq!(
+            render_with(sprites_only, RenderOptions::default()),
+            (sprite, backdrop, backdrop)
+        );
+
+        let background_only = RenderLayers {
+            sprites: false,
+            ..RenderLayers::default()
+        };
+        assert_eq!(
+            render_with(background_only, RenderOptions::default()),
+            (background, background, background)
+        );
+
+        let grid = RenderOptions { grid: true };
+        assert_eq!(
+            render_with(RenderLayers::default(), grid),
+            (sprite, background, Some(GRID_COLOR))
+        );
+    }
+
+    #[test]
+    fn test_mirroring_switch_mid_frame_splits_the_screen() {
+        // Tile 0 is empty, tile 1 solid
+        let mut chr = vec![0; 0x2000];
+        chr[16..32].fill(0xFF);
+        let mapper = create_mapper(0, 0x4000, 0x2000, Mirroring::Horizontal).unwrap();
+        let mut ppu = PPU::new(chr, Mirroring::Horizontal, false, Region::Ntsc, mapper);
+        // Only the second VRAM bank ($2800 with horizontal mirroring) has solid tiles
+        ppu.write_ppuaddr(0x28);
+        ppu.write_ppuaddr(0x00);
+        for _ in 0..0x3C0 {
+            ppu.write_ppudata(1);
+        }
+        ppu.write_ppuaddr(0x3F);
+        ppu.write_ppuaddr(0x00);
+        for color in [0x0F, 0x00, 0x00, 0x16] {
+            ppu.write_ppudata(color);
+        }
+        ppu.write_ppuctrl(0);
+        ppu.write_ppuscroll(0);
+        ppu.write_ppuscroll(0);
+        ppu.write_ppumask(0b0000_1010);
+        while !ppu.tick(1) {}
+
+        // From vblank to about scanline 120 of the next frame
+        for _ in 0..(262 - 241 + 120) * 341 {
+            ppu.tick(1);
+        }
+        ppu.set_mirroring(Mirroring::SingleScreenB);
+        while !ppu.tick(1) {}
+
+        let mut frame = Frame::new();
+        render(
+            &ppu,
+            &mut frame,
+            &Palette::default(),
+            &RenderOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(frame.get_pixel(50, 50), Some(SYSTEM_PALETTE[0x0F]));
+        assert_eq!(frame.get_pixel(50, 200), Some(SYSTEM_PALETTE[0x16]));
     }
 }
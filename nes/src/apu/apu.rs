@@ -0,0 +1,145 @@
+use crate::region::Region;
+use alloc::collections::VecDeque;
+use core::f32::consts::PI;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Channels {
+    Mono,
+    // Both channels get the same sample, interleaved as left, right
+    Stereo,
+}
+
+impl Channels {
+    fn count(&self) -> usize {
+        match self {
+            Channels::Mono => 1,
+            Channels::Stereo => 2,
+        }
+    }
+}
+
+// Output stage of the APU. The mixer produces one sample per CPU cycle
+// (~1.79 MHz on NTSC), it is low-pass filtered and decimated down to the
+// rate the audio backend asked for.
+// https://www.nesdev.org/wiki/APU_Mixer
+pub struct Apu {
+    sample_rate: u32,
+    channels: Channels,
+    cpu_frequency: u32,
+    // Progress towards the next output sample, in units of 1 / sample_rate CPU cycles
+    phase: u64,
+    filtered: f32,
+    alpha: f32,
+    samples: VecDeque<f32>,
+}
+
+impl Apu {
+    // Filter cutoff relative to the output rate, just below the Nyquist frequency
+    const CUTOFF_RATIO: f32 = 0.45;
+    // Samples past one second of audio are dropped when nobody drains them
+    const BUFFERED_SECONDS: usize = 1;
+
+    pub fn new(sample_rate: u32, channels: Channels) -> Apu {
+        let mut apu = Apu {
+            sample_rate,
+            channels,
+            cpu_frequency: 0,
+            phase: 0,
+            filtered: 0.0,
+            alpha: 0.0,
+            samples: VecDeque::new(),
+        };
+        apu.set_region(Region::Ntsc);
+        apu
+    }
+
+    pub fn set_region(&mut self, region: Region) {
+        self.cpu_frequency = region.cpu_frequency();
+        // One-pole RC filter, alpha = dt / (RC + dt)
+        let cutoff = 2.0 * PI * self.sample_rate as f32 * Self::CUTOFF_RATIO;
+        self.alpha = cutoff / (self.cpu_frequency as f32 + cutoff);
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn channels(&self) -> Channels {
+        self.channels
+    }
+
+    // Takes the mixer output for a single CPU cycle, in the 0.0..=1.0 range
+    pub fn push_sample(&mut self, amplitude: f32) {
+        self.filtered += self.alpha * (amplitude - self.filtered);
+        self.phase += self.sample_rate as u64;
+        if self.phase < self.cpu_frequency as u64 {
+            return;
+        }
+        self.phase -= self.cpu_frequency as u64;
+
+        let limit = self.sample_rate as usize * Self::BUFFERED_SECONDS * self.channels.count();
+        for _ in 0..self.channels.count() {
+            if self.samples.len() == limit {
+                self.samples.pop_front();
+            }
+            self.samples.push_back(self.filtered);
+        }
+    }
+
+    // Fills the buffer with the oldest samples and returns how many were
+    // written. Stereo samples are only written in left/right pairs.
+    pub fn drain_samples(&mut self, out: &mut [f32]) -> usize {
+        let channels = self.channels.count();
+        let count = self.samples.len().min(out.len() / channels * channels);
+        for (slot, sample) in out.iter_mut().zip(self.samples.drain(..count)) {
+            *slot = sample;
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    // Square wave at the given frequency, one sample per CPU cycle
+    fn pulse_tone(apu: &mut Apu, frequency: u32, cycles: u32) {
+        let period = Region::Ntsc.cpu_frequency() / frequency;
+        for cycle in 0..cycles {
+            let high = cycle % period < period / 2;
+            apu.push_sample(if high { 0.5 } else { 0.0 });
+        }
+    }
+
+    #[test]
+    fn test_resamples_tone_to_requested_rate() {
+        let mut apu = Apu::new(44_100, Channels::Mono);
+        // One second
+        pulse_tone(&mut apu, 440, Region::Ntsc.cpu_frequency());
+
+        let mut out = vec![0.0; 50_000];
+        let written = apu.drain_samples(&mut out);
+        assert_eq!(written, 44_100);
+
+        let samples = &out[..written];
+        let max = samples.iter().copied().fold(f32::MIN, f32::max);
+        let min = samples.iter().copied().fold(f32::MAX, f32::min);
+        assert!(max > 0.4 && max <= 0.5, "max {max}");
+        assert!((0.0..0.1).contains(&min), "min {min}");
+        assert_eq!(apu.drain_samples(&mut out), 0);
+    }
+
+    #[test]
+    fn test_stereo_writes_whole_frames() {
+        let mut apu = Apu::new(48_000, Channels::Stereo);
+        pulse_tone(&mut apu, 440, Region::Ntsc.cpu_frequency());
+
+        let mut out = vec![0.0; 15];
+        assert_eq!(apu.drain_samples(&mut out), 14);
+        assert_eq!(out[0], out[1]);
+
+        let mut rest = vec![0.0; 100_000];
+        assert_eq!(apu.drain_samples(&mut rest), 2 * 48_000 - 14);
+    }
+}
@@ -0,0 +1,29 @@
+// APU and I/O registers ($4000-$4013, $4015 and $4017) are routed here until
+// a real APU implements the same trait. The $4018-$401F test registers are
+// disabled and never reach it.
+// https://www.nesdev.org/wiki/2A03
+pub trait ApuIo {
+    // None leaves the open bus value on the data bus
+    fn read(&mut self, address: u16) -> Option<u8>;
+
+    fn write(&mut self, address: u16, value: u8);
+}
+
+// Ignores writes and reports every channel as idle, so games polling $4015
+// for a length counter to expire don't wait forever.
+pub struct StubApuIo;
+
+impl StubApuIo {
+    const STATUS_ADDR: u16 = 0x4015;
+}
+
+impl ApuIo for StubApuIo {
+    fn read(&mut self, address: u16) -> Option<u8> {
+        match address {
+            StubApuIo::STATUS_ADDR => Some(0),
+            _ => None,
+        }
+    }
+
+    fn write(&mut self, _address: u16, _value: u8) {}
+}
@@ -1,15 +1,88 @@
+pub mod access;
+pub mod error;
+pub mod state;
+pub mod timing;
+
+use crate::apu::io::{ApuIo, StubApuIo};
+use crate::bus::access::{Access, AccessKind};
+use crate::bus::error::{BusError, SramError};
+use crate::bus::timing::FrameTiming;
 use crate::controller::controller::Controller;
+use crate::controller::input_device::InputDevice;
+use crate::event::{Event, EventKind, EventLog, SharedEventLog};
+use crate::mapper::error::UnsupportedMapper;
+use crate::mapper::mapper::SharedMapper;
+use crate::mapper::registry::MapperRegistry;
 use crate::ppu::ppu::PPU;
 use crate::rom::rom::Rom;
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
 
 pub struct Bus<'call> {
     cpu_ram: [u8; 2048],
+    ram_fill: RamFill,
     prg_rom: Vec<u8>,
+    prg_ram: [u8; 8192],
+    mapper: SharedMapper,
+    mappers: MapperRegistry,
     controller_1: Controller,
-    controller_2: Controller,
+    port_2: InputDevice,
+    apu_io: Box<dyn ApuIo>,
     pub ppu: PPU,
     pub cycles: usize,
-    nmi_callback: Box<dyn FnMut(&PPU, &mut Controller) + 'call>,
+    ppu_dots_remainder: usize,
+    access_cycles: u8,
+    access_ticking: bool,
+    last_bus_value: u8,
+    frame_ready: bool,
+    frame_count: usize,
+    frame_skip: u8,
+    frames_until_callback: u8,
+    break_requested: bool,
+    nmi_callback: Box<dyn FnMut(&PPU, &mut Controller) -> EmulationFlow + 'call>,
+    access_log: Option<Box<dyn FnMut(Access) + 'call>>,
+    frame_timing: Option<Box<dyn FnMut(FrameTiming) + 'call>>,
+    frame_start_cycles: usize,
+    event_log: Option<SharedEventLog>,
+}
+
+// Counters for measuring emulation speed, e.g. FPS and effective CPU MHz.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BusStats {
+    pub cycles: usize,
+    pub frames: usize,
+}
+
+// Real RAM powers up with indeterminate contents, some games seed their RNG
+// from it. A pattern repeats through the whole RAM, e.g. FCEUX fills it with
+// `&[0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF]`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RamFill {
+    Zeroed,
+    AllOnes,
+    Pattern(&'static [u8]),
+}
+
+impl RamFill {
+    fn ram(&self) -> [u8; 2048] {
+        match self {
+            RamFill::Zeroed | RamFill::Pattern([]) => [0; 2048],
+            RamFill::AllOnes => [0xFF; 2048],
+            RamFill::Pattern(pattern) => {
+                core::array::from_fn(|address| pattern[address % pattern.len()])
+            }
+        }
+    }
+}
+
+// Returned by the frame callback, `Break` makes `CPU::run` return after the
+// current instruction so the frontend can shut down cleanly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EmulationFlow {
+    Continue,
+    Break,
 }
 
 pub trait BusOperation<T> {
@@ -37,42 +110,416 @@ impl Bus<'_> {
     const CONTROLLER_1_ADDR: u16 = 0x4016;
     const CONTROLLER_2_ADDR: u16 = 0x4017;
 
+    const APU_IO_START: u16 = 0x4000;
+    const APU_IO_END: u16 = 0x4017;
+    // CPU test mode registers, disabled on retail consoles
+    // https://www.nesdev.org/wiki/CPU_Test_Mode
+    const CPU_TEST_START: u16 = 0x4018;
+    const CPU_TEST_END: u16 = 0x401F;
+
+    const PRG_RAM_START: u16 = 0x6000;
+    const TRAINER_START: u16 = 0x7000;
+    const PRG_RAM_END: u16 = 0x7FFF;
+
     const PRG_ROM_START: u16 = 0x8000;
     const PRG_ROM_END: u16 = 0xFFFF;
 
     const CPU_MIRRORING: u16 = 0b0000_0111_1111_1111;
     const PPU_MIRRORING: u16 = 0b0010_0000_0000_0111;
 
-    pub fn new<'call, F>(rom: Rom, nmi_callback: F) -> Bus<'call>
+    // Creates the mapper with the built-in mappers or ones added by
+    // `register_mapper`, fails when the cartridge needs neither.
+    pub fn new<'call, F>(rom: Rom, nmi_callback: F) -> Result<Bus<'call>, UnsupportedMapper>
+    where
+        F: FnMut(&PPU, &mut Controller) -> EmulationFlow + 'call,
+    {
+        Bus::with_registry(rom, MapperRegistry::global(), nmi_callback)
+    }
+
+    // Like `new`, but custom mappers come from the given registry, which
+    // `load_rom` uses as well.
+    pub fn with_registry<'call, F>(
+        rom: Rom,
+        mappers: MapperRegistry,
+        nmi_callback: F,
+    ) -> Result<Bus<'call>, UnsupportedMapper>
+    where
+        F: FnMut(&PPU, &mut Controller) -> EmulationFlow + 'call,
+    {
+        let mapper = mappers.create(&rom)?;
+        let mut bus = Bus::with_mapper(rom, mapper, nmi_callback);
+        bus.mappers = mappers;
+        Ok(bus)
+    }
+
+    // Uses the given mapper instead of creating one for the cartridge
+    pub fn with_mapper<'call, F>(rom: Rom, mapper: SharedMapper, nmi_callback: F) -> Bus<'call>
     where
-        F: FnMut(&PPU, &mut Controller) + 'call,
+        F: FnMut(&PPU, &mut Controller) -> EmulationFlow + 'call,
     {
+        let chr_ram = rom.uses_chr_ram();
+        let prg_ram = Bus::initial_prg_ram(&rom);
         Bus {
             cpu_ram: [0; 2048],
+            ram_fill: RamFill::Zeroed,
             prg_rom: rom.prg_rom,
-            ppu: PPU::new(rom.chr_rom, rom.mirroring),
+            prg_ram,
+            ppu: PPU::new(
+                rom.chr_rom,
+                rom.mirroring,
+                chr_ram,
+                rom.region,
+                mapper.clone(),
+            ),
+            mapper,
+            mappers: MapperRegistry::global(),
             controller_1: Controller::new(),
-            controller_2: Controller::new(),
+            port_2: InputDevice::Controller(Controller::new()),
+            apu_io: Box::new(StubApuIo),
             cycles: 0,
+            ppu_dots_remainder: 0,
+            access_cycles: 0,
+            access_ticking: true,
+            last_bus_value: 0,
+            frame_ready: false,
+            frame_count: 0,
+            frame_skip: 0,
+            frames_until_callback: 0,
+            break_requested: false,
             nmi_callback: Box::new(nmi_callback),
+            access_log: None,
+            frame_timing: None,
+            frame_start_cycles: 0,
+            event_log: None,
+        }
+    }
+
+    // Swaps the cartridge, e.g. for a "load ROM" menu. CPU RAM is cleared and
+    // the PPU starts over, the frame callback, controllers, logs and render
+    // layers are kept.
+    // Use `CPU::load_rom` to also restart execution at the new reset vector.
+    // The current cartridge is kept when the new one has an unsupported mapper.
+    pub fn load_rom(&mut self, rom: Rom) -> Result<(), UnsupportedMapper> {
+        let mapper = self.mappers.create(&rom)?;
+        let chr_ram = rom.uses_chr_ram();
+        let render_layers = self.ppu.render_layers();
+        self.prg_ram = Bus::initial_prg_ram(&rom);
+        self.ppu = PPU::new(
+            rom.chr_rom,
+            rom.mirroring,
+            chr_ram,
+            rom.region,
+            mapper.clone(),
+        );
+        self.ppu.set_event_log(self.event_log.clone());
+        self.ppu.set_render_layers(render_layers);
+        self.ppu.set_skip_rendering(self.frames_until_callback > 0);
+        self.prg_rom = rom.prg_rom;
+        self.mapper = mapper;
+        self.cpu_ram = self.ram_fill.ram();
+        self.ppu_dots_remainder = 0;
+        self.last_bus_value = 0;
+        self.frame_ready = false;
+        Ok(())
+    }
+
+    // The trainer is loaded at $7000 before the game starts
+    fn initial_prg_ram(rom: &Rom) -> [u8; 8192] {
+        let mut prg_ram = [0; 8192];
+        if let Some(trainer) = &rom.trainer {
+            let trainer_start = (Bus::TRAINER_START - Bus::PRG_RAM_START) as usize;
+            prg_ram[trainer_start..trainer_start + trainer.len()].copy_from_slice(trainer);
+        }
+        prg_ram
+    }
+
+    // Creates a bus with PRG RAM restored from a previously saved `.sav` file.
+    pub fn with_sram<'call, F>(
+        rom: Rom,
+        sram: &[u8],
+        nmi_callback: F,
+    ) -> Result<Bus<'call>, BusError>
+    where
+        F: FnMut(&PPU, &mut Controller) -> EmulationFlow + 'call,
+    {
+        let mut bus = Bus::new(rom, nmi_callback)?;
+        bus.load_sram(sram)?;
+        Ok(bus)
+    }
+
+    pub fn load_sram(&mut self, sram: &[u8]) -> Result<(), SramError> {
+        if sram.len() != self.prg_ram.len() {
+            return Err(SramError::InvalidSize(sram.len()));
         }
+        self.prg_ram.copy_from_slice(sram);
+        Ok(())
+    }
+
+    pub fn dump_sram(&self) -> Vec<u8> {
+        self.prg_ram.to_vec()
+    }
+
+    // PRG RAM ($6000-$7FFF) without going through the bus
+    pub fn sram(&self) -> &[u8] {
+        &self.prg_ram
+    }
+
+    // Contents of CPU RAM after power on, applied right away and on every
+    // `power_on`. Zeroed by default.
+    pub fn set_ram_fill(&mut self, fill: RamFill) {
+        self.ram_fill = fill;
+        self.cpu_ram = fill.ram();
+    }
+
+    pub fn power_on(&mut self) {
+        self.cpu_ram = self.ram_fill.ram();
+        self.ppu.reset();
+    }
+
+    pub fn reset(&mut self) {
+        self.ppu.reset();
     }
 
     pub fn tick(&mut self, cycles: u8) {
         self.cycles += cycles as usize;
-        if self.ppu.tick(cycles * 3) {
-            (self.nmi_callback)(&self.ppu, &mut self.controller_1);
+
+        let (dots, cpu_cycles) = self.ppu.region().ppu_dots_per_cpu_cycle();
+        let total_dots = cycles as usize * dots + self.ppu_dots_remainder;
+        self.ppu_dots_remainder = total_dots % cpu_cycles;
+        if self.ppu.tick((total_dots / cpu_cycles) as u8) {
+            self.frame_ready = true;
+            self.frame_count += 1;
+            self.controller_1.advance_frame();
+            self.report_frame_timing();
+            let skipped = self.frames_until_callback > 0;
+            self.frames_until_callback = if skipped {
+                self.frames_until_callback - 1
+            } else {
+                self.frame_skip
+            };
+            self.ppu.set_skip_rendering(self.frames_until_callback > 0);
+            if !skipped
+                && (self.nmi_callback)(&self.ppu, &mut self.controller_1) == EmulationFlow::Break
+            {
+                self.break_requested = true;
+            }
+        }
+    }
+
+    // Calls the frame callback only on every (n + 1)th frame, e.g. to render
+    // less often on slow hardware. The PPU doesn't draw the skipped frames
+    // either. Emulation, `on_frame` and turbo buttons still advance every frame.
+    pub fn set_frame_skip(&mut self, n: u8) {
+        self.frame_skip = n;
+        self.frames_until_callback = self.frames_until_callback.min(n);
+        self.ppu.set_skip_rendering(self.frames_until_callback > 0);
+    }
+
+    fn report_frame_timing(&mut self) {
+        let frame_cycles = self.cycles - self.frame_start_cycles;
+        self.frame_start_cycles = self.cycles;
+        if let Some(on_frame) = &mut self.frame_timing {
+            on_frame(FrameTiming {
+                frame: self.frame_count,
+                cycles: self.cycles,
+                frame_cycles,
+                #[cfg(feature = "std")]
+                timestamp: std::time::Instant::now(),
+            });
         }
     }
 
+    // Starts recording the last `capacity` interrupts, vblank and sprite 0 hit
+    // changes and mapper writes, dropping whatever was recorded before.
+    pub fn enable_event_log(&mut self, capacity: usize) {
+        self.event_log = Some(Rc::new(RefCell::new(EventLog::new(capacity))));
+        self.ppu.set_event_log(self.event_log.clone());
+    }
+
+    pub fn disable_event_log(&mut self) {
+        self.event_log = None;
+        self.ppu.set_event_log(None);
+    }
+
+    // Recorded events, oldest first. The log is empty afterwards.
+    pub fn drain_events(&mut self) -> Vec<Event> {
+        self.event_log
+            .as_ref()
+            .map(|event_log| event_log.borrow_mut().drain())
+            .unwrap_or_default()
+    }
+
+    // Makes `run` return `ExitReason::Break` before the next instruction
+    #[cfg(feature = "std")]
+    pub(crate) fn request_break(&mut self) {
+        self.break_requested = true;
+    }
+
+    // Returns true once after the frame callback asked to stop the emulation.
+    pub fn take_break_request(&mut self) -> bool {
+        core::mem::take(&mut self.break_requested)
+    }
+
+    pub fn stats(&self) -> BusStats {
+        BusStats {
+            cycles: self.cycles,
+            frames: self.frame_count,
+        }
+    }
+
+    // Set every time the PPU finishes a frame, whether NMI is enabled or not.
+    // Returns true once per finished frame.
+    pub fn take_frame_ready(&mut self) -> bool {
+        core::mem::take(&mut self.frame_ready)
+    }
+
+    // Every memory access takes one CPU cycle, so the PPU is advanced right
+    // after each byte is read or written. Cycles an instruction spends without
+    // touching memory are ticked by the CPU once the instruction is done.
+    fn tick_access(&mut self, accesses: u8) {
+        if self.access_ticking {
+            self.access_cycles = self.access_cycles.saturating_add(accesses);
+            self.tick(accesses);
+        }
+    }
+
+    // The CPU is halted while OAM DMA copies 256 bytes: one halt cycle, one more
+    // to align when the write ends on an odd cycle, then 256 read/write pairs.
+    // https://www.nesdev.org/wiki/DMA#OAM_DMA
+    fn oam_dma_stall(&mut self) {
+        if !self.access_ticking {
+            return;
+        }
+        // The $4014 write cycle itself is ticked right after this call
+        let write_cycle_end = self.cycles + 1;
+        let stall = 513 + write_cycle_end % 2;
+        for _ in 0..stall {
+            self.tick(1);
+        }
+    }
+
+    // Returns the number of cycles ticked by memory accesses since the previous call.
+    pub fn take_access_cycles(&mut self) -> u8 {
+        core::mem::take(&mut self.access_cycles)
+    }
+
+    pub fn clear_access_log(&mut self) {
+        self.access_log = None;
+    }
+
+    fn log_access(&mut self, addr: u16, value: u8, is_write: bool, kind: AccessKind) {
+        if let Some(log) = &mut self.access_log
+            && self.access_ticking
+        {
+            log(Access {
+                addr,
+                value,
+                is_write,
+                kind,
+            });
+        }
+    }
+
+    // Allows inspecting memory (e.g. tracing) without advancing the PPU.
+    pub fn set_access_ticking(&mut self, enabled: bool) {
+        self.access_ticking = enabled;
+    }
+
     pub fn poll_nmi_interrupt(&mut self) -> bool {
         self.ppu.poll_nmi_interrupt()
     }
+
+    // IRQ line is level triggered, it stays asserted until the mapper acknowledges it.
+    pub fn poll_irq(&self) -> bool {
+        self.mapper.borrow().poll_irq()
+    }
+
+    fn read_prg_rom(&self, address: u16) -> u8 {
+        self.prg_rom[self.mapper.borrow().map_prg(address)]
+    }
+
+    pub fn set_port2(&mut self, device: InputDevice) {
+        self.port_2 = device;
+    }
+
+    pub fn port2(&mut self) -> &mut InputDevice {
+        &mut self.port_2
+    }
+
+    pub fn set_apu_io(&mut self, apu_io: Box<dyn ApuIo>) {
+        self.apu_io = apu_io;
+    }
 }
 
-impl BusOperation<u8> for Bus<'_> {
-    fn read(&mut self, mut address: u16) -> u8 {
+impl<'call> Bus<'call> {
+    // Reports every byte read or written over the bus in order, including the
+    // reads OAM DMA performs. Inspection with access ticking disabled isn't logged.
+    pub fn set_access_log(&mut self, log: Box<dyn FnMut(Access) + 'call>) {
+        self.access_log = Some(log);
+    }
+
+    // Called at the end of every frame, before the frame callback
+    pub fn on_frame(&mut self, on_frame: Box<dyn FnMut(FrameTiming) + 'call>) {
+        self.frame_timing = Some(on_frame);
+    }
+}
+
+impl Bus<'_> {
+    // Side effect free access for test setup and debuggers: no cycles, no
+    // open bus updates, no PPU latches or increments. PPU registers backed by
+    // memory ($2004, $2007) access that memory, $2002 returns the status flags.
+    pub fn peek(&self, address: u16) -> u8 {
         match address {
+            Bus::CPU_RAM_START..=Bus::CPU_RAM_END => {
+                self.cpu_ram[(address & Bus::CPU_MIRRORING) as usize]
+            }
+            Bus::PPUSTATUS_REGISTER_ADDR => self.ppu.status_flags().bits(),
+            Bus::OAMDATA_REGISTER_ADDR => self.ppu.read_oamdata(self.ppu.read_oamaddr() as usize),
+            Bus::PPUDATA_REGISTER_ADDR => self.ppu.peek_ppudata(),
+            Bus::PPU_IO_REGISTERS_START..=Bus::PPU_IO_REGISTERS_END => {
+                self.peek(address & Bus::PPU_MIRRORING)
+            }
+            Bus::PRG_RAM_START..=Bus::PRG_RAM_END => {
+                self.prg_ram[(address - Bus::PRG_RAM_START) as usize]
+            }
+            Bus::PRG_ROM_START..=Bus::PRG_ROM_END => self.read_prg_rom(address),
+            _ => self.last_bus_value,
+        }
+    }
+
+    // Little-endian pointer at the address, $FFFF wraps around to $0000 like
+    // the CPU's 16-bit reads.
+    pub fn peek_u16(&self, address: u16) -> u16 {
+        u16::from_le_bytes([self.peek(address), self.peek(address.wrapping_add(1))])
+    }
+
+    // Writes to other registers are ignored, PRG ROM is patched in place.
+    pub fn poke(&mut self, address: u16, value: u8) {
+        match address {
+            Bus::CPU_RAM_START..=Bus::CPU_RAM_END => {
+                self.cpu_ram[(address & Bus::CPU_MIRRORING) as usize] = value
+            }
+            Bus::OAMDATA_REGISTER_ADDR => self.ppu.poke_oamdata(value),
+            Bus::PPUDATA_REGISTER_ADDR => self.ppu.poke_ppudata(value),
+            Bus::PPU_IO_REGISTERS_START..=Bus::PPU_IO_REGISTERS_END => {
+                self.poke(address & Bus::PPU_MIRRORING, value)
+            }
+            Bus::PRG_RAM_START..=Bus::PRG_RAM_END => {
+                self.prg_ram[(address - Bus::PRG_RAM_START) as usize] = value
+            }
+            Bus::PRG_ROM_START..=Bus::PRG_ROM_END => {
+                let index = self.mapper.borrow().map_prg(address);
+                self.prg_rom[index] = value
+            }
+            _ => {}
+        }
+    }
+
+    // Reads of unmapped addresses and write-only registers return the value
+    // that was last on the data bus (open bus).
+    // https://www.nesdev.org/wiki/Open_bus_behavior
+    fn read_byte(&mut self, address: u16) -> u8 {
+        let value = match address {
             Bus::CPU_RAM_START..=Bus::CPU_RAM_END => {
                 self.cpu_ram[(address & Bus::CPU_MIRRORING) as usize]
             }
@@ -82,28 +529,41 @@ impl BusOperation<u8> for Bus<'_> {
             | Bus::PPUSCROLL_REGISTER_ADDR
             | Bus::PPUADDR_REGISTER_ADDR
             | Bus::OAMDMA_REGISTER_ADDR => {
-                panic!("Unable to read from writable PPU IO register - ${address:04x}")
+                #[cfg(feature = "bus-log")]
+                eprintln!("Read from writable PPU IO register - ${address:04x}");
+                self.last_bus_value
             }
             Bus::PPUSTATUS_REGISTER_ADDR => self.ppu.read_ppustatus(),
             Bus::OAMDATA_REGISTER_ADDR => self.ppu.read_oamdata(self.ppu.read_oamaddr() as usize),
             Bus::PPUDATA_REGISTER_ADDR => self.ppu.read_ppudata(),
             Bus::PPU_IO_REGISTERS_START..=Bus::PPU_IO_REGISTERS_END => {
-                self.read(address & Bus::PPU_MIRRORING)
+                self.read_byte(address & Bus::PPU_MIRRORING)
             }
             Bus::CONTROLLER_1_ADDR => self.controller_1.read(),
-            Bus::CONTROLLER_2_ADDR => self.controller_2.read(),
-            Bus::PRG_ROM_START..=Bus::PRG_ROM_END => {
-                address -= 0x8000;
-                if self.prg_rom.len() == 0x4000 && address >= 0x4000 {
-                    address &= 0x3FFF;
+            Bus::CONTROLLER_2_ADDR => match &mut self.port_2 {
+                InputDevice::Controller(controller) => controller.read(),
+                InputDevice::Zapper(zapper) => {
+                    let (x, y) = zapper.aim_position();
+                    zapper.sense_brightness(self.ppu.pixel_brightness(x, y));
+                    zapper.read(self.ppu.scanline)
                 }
-                self.prg_rom[address as usize]
+            },
+            Bus::APU_IO_START..=Bus::APU_IO_END => {
+                self.apu_io.read(address).unwrap_or(self.last_bus_value)
             }
-            _ => 0,
-        }
+            // Nothing drives the data bus while test mode is disabled
+            Bus::CPU_TEST_START..=Bus::CPU_TEST_END => self.last_bus_value,
+            Bus::PRG_RAM_START..=Bus::PRG_RAM_END => {
+                self.prg_ram[(address - Bus::PRG_RAM_START) as usize]
+            }
+            Bus::PRG_ROM_START..=Bus::PRG_ROM_END => self.read_prg_rom(address),
+            _ => self.last_bus_value,
+        };
+        self.last_bus_value = value;
+        value
     }
 
-    fn write(&mut self, address: u16, value: u8) {
+    fn write_byte(&mut self, address: u16, value: u8) {
         match address {
             Bus::CPU_RAM_START..=Bus::CPU_RAM_END => {
                 self.cpu_ram[(address & Bus::CPU_MIRRORING) as usize] = value
@@ -119,60 +579,514 @@ impl BusOperation<u8> for Bus<'_> {
                 let hi = (value as usize) << 8;
                 let buffer: [u8; 256] = (0..256)
                     .enumerate()
-                    .map(|(i, _)| BusOperation::<u8>::read(self, (hi + i) as u16))
+                    .map(|(i, _)| {
+                        let address = (hi + i) as u16;
+                        let value = self.read_byte(address);
+                        self.log_access(address, value, false, AccessKind::OamDma);
+                        value
+                    })
                     .collect::<Vec<u8>>()
                     .try_into()
                     .unwrap();
                 self.ppu.write_oamdma(&buffer);
+                self.oam_dma_stall();
             }
             Bus::PPUSTATUS_REGISTER_ADDR => {
-                panic!("Unable to write to only-readable PPU IO register - ${address:04x}")
+                #[cfg(feature = "bus-log")]
+                eprintln!("Write to only-readable PPU IO register - ${address:04x}");
             }
             Bus::PPU_IO_REGISTERS_START..=Bus::PPU_IO_REGISTERS_END => {
-                self.write(address & Bus::PPU_MIRRORING, value)
+                self.write_byte(address & Bus::PPU_MIRRORING, value)
             }
             Bus::CONTROLLER_1_ADDR => self.controller_1.write(value),
-            Bus::CONTROLLER_2_ADDR => self.controller_2.write(value),
-            Bus::PRG_ROM_START..=Bus::PRG_ROM_END => panic!("Write to PRG ROM is restricted"),
+            // $4017 is also the APU frame counter
+            Bus::CONTROLLER_2_ADDR => {
+                if let InputDevice::Controller(controller) = &mut self.port_2 {
+                    controller.write(value)
+                }
+                self.apu_io.write(address, value);
+            }
+            Bus::APU_IO_START..=Bus::APU_IO_END => self.apu_io.write(address, value),
+            Bus::CPU_TEST_START..=Bus::CPU_TEST_END => {}
+            Bus::PRG_RAM_START..=Bus::PRG_RAM_END => {
+                self.prg_ram[(address - Bus::PRG_RAM_START) as usize] = value
+            }
+            Bus::PRG_ROM_START..=Bus::PRG_ROM_END => {
+                self.ppu
+                    .record_event(EventKind::MapperWrite { address, value });
+                self.mapper.borrow_mut().write(address, value)
+            }
             _ => {}
         }
     }
 }
 
+impl BusOperation<u8> for Bus<'_> {
+    fn read(&mut self, address: u16) -> u8 {
+        let value = self.read_byte(address);
+        self.log_access(address, value, false, AccessKind::Cpu);
+        self.tick_access(1);
+        value
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.log_access(address, value, true, AccessKind::Cpu);
+        self.write_byte(address, value);
+        self.tick_access(1);
+    }
+}
+
+// 16-bit accesses are two byte accesses at adjacent addresses, each going
+// through the address decoder like the 6502 does, so they respect mirroring
+// and region boundaries and wrap from $FFFF to $0000.
 impl BusOperation<u16> for Bus<'_> {
-    fn read(&mut self, mut address: u16) -> u16 {
-        match address {
-            Bus::CPU_RAM_START..=Bus::CPU_RAM_END => {
-                address &= Bus::CPU_MIRRORING;
-                u16::from_le_bytes([
-                    self.cpu_ram[address as usize],
-                    self.cpu_ram[address.wrapping_add(1) as usize],
-                ])
+    fn read(&mut self, address: u16) -> u16 {
+        let low = BusOperation::<u8>::read(self, address);
+        let high = BusOperation::<u8>::read(self, address.wrapping_add(1));
+        u16::from_le_bytes([low, high])
+    }
+
+    fn write(&mut self, address: u16, value: u16) {
+        let [low, high] = value.to_le_bytes();
+        BusOperation::<u8>::write(self, address, low);
+        BusOperation::<u8>::write(self, address.wrapping_add(1), high);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::zapper::Zapper;
+    use crate::ppu::mirroring::Mirroring;
+    use crate::ppu::register::ppustatus::PPUSTATUS;
+    use crate::rom::rom::ines_image;
+    use alloc::vec;
+
+    #[test]
+    fn test_mmc3_irq_fires_on_latched_scanline() {
+        let mut bus = setup_bus_with_mapper(4);
+        // Sprites at $1000, background at $0000 so A12 rises once per scanline
+        bus.write(0x2000, 0b0000_1000u8);
+        bus.write(0x2001, 0b0001_1000u8);
+        bus.write(0xC000, 5u8);
+        bus.write(0xC001, 0u8);
+        bus.write(0xE001, 0u8);
+
+        while !bus.poll_irq() {
+            bus.tick(1);
+        }
+        assert_eq!(bus.ppu.scanline, 6);
+
+        bus.write(0xE000, 0u8);
+        assert!(!bus.poll_irq());
+    }
+
+    #[test]
+    fn test_nrom_ignores_writes_to_prg_rom() {
+        let mut bus = setup_bus_with_mapper(0);
+        let before = bus.peek(0x8000);
+
+        BusOperation::<u8>::write(&mut bus, 0x8000, before.wrapping_add(1));
+
+        assert_eq!(bus.peek(0x8000), before);
+    }
+
+    #[test]
+    fn test_unsupported_mapper_is_an_error() {
+        let content = ines_image(1, 2, 1);
+        let rom = || Rom::new(&content).unwrap();
+
+        let result = Bus::new(rom(), |_, _| EmulationFlow::Continue);
+        assert!(matches!(result, Err(UnsupportedMapper(1))));
+
+        let mut bus = setup_bus_with_mapper(0);
+        BusOperation::<u8>::write(&mut bus, 0x0000, 0x42);
+        assert_eq!(bus.load_rom(rom()), Err(UnsupportedMapper(1)));
+        assert_eq!(bus.peek(0x0000), 0x42);
+    }
+
+    #[test]
+    fn test_trainer_is_copied_to_prg_ram() {
+        let mut content = ines_image(0, 1, 1);
+        // Trainer flag, 512 bytes between the header and PRG ROM
+        content[6] |= 0b0000_0100;
+        content.splice(16..16, (0..512).map(|i| i as u8));
+        let mut bus =
+            Bus::new(Rom::new(&content).unwrap(), |_, _| EmulationFlow::Continue).unwrap();
+
+        let first: u8 = bus.read(0x7000);
+        let last: u8 = bus.read(0x71FF);
+        let after: u8 = bus.read(0x7200);
+        assert_eq!((first, last, after), (0x00, 0xFF, 0x00));
+    }
+
+    #[test]
+    fn test_frame_ready_without_nmi() {
+        let mut bus = setup_bus_with_mapper(0);
+        assert!(!bus.take_frame_ready());
+
+        let mut cycles = 0;
+        while !bus.take_frame_ready() {
+            bus.tick(1);
+            cycles += 1;
+        }
+        assert!(!bus.poll_nmi_interrupt());
+        assert_eq!(cycles, 341 * 262 / 3 + 1);
+        assert!(!bus.take_frame_ready());
+        assert_eq!(
+            bus.stats(),
+            BusStats {
+                cycles: 341 * 262 / 3 + 1,
+                frames: 1,
             }
-            Bus::PRG_ROM_START..=Bus::PRG_ROM_END => {
-                address -= 0x8000;
-                if self.prg_rom.len() == 0x4000 && address >= 0x4000 {
-                    address &= 0x3FFF;
-                }
-                u16::from_le_bytes([
-                    self.prg_rom[address as usize],
-                    self.prg_rom[address.wrapping_add(1) as usize],
-                ])
+        );
+    }
+
+    #[test]
+    fn test_sram_round_trip() {
+        let rom = Rom::new(&nrom_image()).unwrap();
+        let sram: Vec<u8> = (0..8192).map(|i| i as u8).collect();
+        let mut bus = Bus::with_sram(rom, &sram, |_, _| EmulationFlow::Continue).unwrap();
+
+        let value: u8 = bus.read(0x6001);
+        assert_eq!(value, 0x01);
+        bus.write(0x7FFF, 0x42u8);
+
+        let dump = bus.dump_sram();
+        assert_eq!(dump.len(), 8192);
+        assert_eq!(dump[0x1FFF], 0x42);
+        assert_eq!(dump[..0x1FFF], sram[..0x1FFF]);
+    }
+
+    #[test]
+    fn test_sram_with_wrong_size_is_rejected() {
+        let rom = Rom::new(&nrom_image()).unwrap();
+        let result = Bus::with_sram(rom, &[0; 100], |_, _| EmulationFlow::Continue);
+        assert!(matches!(
+            result,
+            Err(BusError::Sram(SramError::InvalidSize(100)))
+        ));
+    }
+
+    fn nrom_image() -> Vec<u8> {
+        let mut content = ines_image(0, 1, 1);
+        // Battery
+        content[6] |= 0b0000_0010;
+        content
+    }
+
+    #[test]
+    fn test_access_log_reports_cpu_and_oam_dma_accesses() {
+        let accesses = Rc::new(RefCell::new(Vec::new()));
+        let mut bus = setup_bus_with_mapper(0);
+        let log = accesses.clone();
+        bus.set_access_log(Box::new(move |access| log.borrow_mut().push(access)));
+
+        BusOperation::<u8>::write(&mut bus, 0x0200, 0x42);
+        BusOperation::<u8>::write(&mut bus, 0x4014, 0x02);
+
+        let accesses = accesses.borrow();
+        assert_eq!(accesses.len(), 2 + 256);
+        assert_eq!(
+            accesses[0],
+            Access {
+                addr: 0x0200,
+                value: 0x42,
+                is_write: true,
+                kind: AccessKind::Cpu,
+            }
+        );
+        assert!(accesses[1].is_write);
+        assert_eq!(
+            accesses[2],
+            Access {
+                addr: 0x0200,
+                value: 0x42,
+                is_write: false,
+                kind: AccessKind::OamDma,
+            }
+        );
+        assert_eq!(accesses[257].addr, 0x02FF);
+    }
+
+    #[test]
+    fn test_apu_io_region_is_routed_to_apu_io() {
+        struct Recorder(Rc<RefCell<Vec<(u16, u8)>>>);
+
+        impl ApuIo for Recorder {
+            fn read(&mut self, address: u16) -> Option<u8> {
+                (address == 0x4015).then_some(0x1F)
+            }
+
+            fn write(&mut self, address: u16, value: u8) {
+                self.0.borrow_mut().push((address, value));
             }
-            _ => 0,
         }
+
+        let mut bus = setup_bus_with_mapper(0);
+        let value: u8 = bus.read(0x4015);
+        assert_eq!(value, 0x00);
+
+        let writes = Rc::new(RefCell::new(Vec::new()));
+        bus.set_apu_io(Box::new(Recorder(writes.clone())));
+        BusOperation::<u8>::write(&mut bus, 0x4000, 0x30);
+        BusOperation::<u8>::write(&mut bus, 0x4017, 0x40);
+        let status: u8 = bus.read(0x4015);
+        // Write-only registers leave the open bus value
+        let pulse: u8 = bus.read(0x4000);
+
+        assert_eq!(*writes.borrow(), vec![(0x4000, 0x30), (0x4017, 0x40)]);
+        assert_eq!(status, 0x1F);
+        assert_eq!(pulse, 0x1F);
     }
 
-    fn write(&mut self, mut address: u16, value: u16) {
-        let value_le_bytes: [u8; 2] = value.to_le_bytes();
-        match address {
-            Bus::CPU_RAM_START..=Bus::CPU_RAM_END => {
-                address &= Bus::CPU_MIRRORING;
-                self.cpu_ram[address as usize] = value_le_bytes[0];
-                self.cpu_ram[address.wrapping_add(1) as usize] = value_le_bytes[1];
+    #[test]
+    fn test_cpu_test_registers_are_open_bus() {
+        struct Recorder(Rc<RefCell<Vec<u16>>>);
+
+        impl ApuIo for Recorder {
+            fn read(&mut self, address: u16) -> Option<u8> {
+                self.0.borrow_mut().push(address);
+                Some(0xFF)
             }
-            Bus::PRG_ROM_START..=Bus::PRG_ROM_END => panic!("Write to PRG ROM is restricted"),
-            _ => {}
+
+            fn write(&mut self, address: u16, _value: u8) {
+                self.0.borrow_mut().push(address);
+            }
+        }
+
+        let mut bus = setup_bus_with_mapper(0);
+        let accesses = Rc::new(RefCell::new(Vec::new()));
+        bus.set_apu_io(Box::new(Recorder(accesses.clone())));
+        BusOperation::<u8>::write(&mut bus, 0x0000, 0x5A);
+        BusOperation::<u8>::write(&mut bus, 0x4018, 0x12);
+        BusOperation::<u8>::write(&mut bus, 0x401F, 0x34);
+
+        let ram: u8 = bus.read(0x0000);
+        let first: u8 = bus.read(0x4018);
+        let last: u8 = bus.read(0x401F);
+
+        assert_eq!((ram, first, last), (0x5A, 0x5A, 0x5A));
+        assert!(accesses.borrow().is_empty());
+    }
+
+    #[test]
+    fn test_u16_read_straddling_ram_end_wraps_to_mirror() {
+        let mut bus = setup_bus_with_mapper(0);
+        BusOperation::<u8>::write(&mut bus, 0x07FF, 0x34);
+        BusOperation::<u8>::write(&mut bus, 0x0000, 0x12);
+
+        let value: u16 = bus.read(0x07FF);
+
+        assert_eq!(value, 0x1234);
+    }
+
+    #[test]
+    fn test_u16_read_at_end_of_address_space_wraps_to_zero() {
+        let mut prg = vec![0; 0x4000];
+        prg[0x3FFE..].copy_from_slice(&[0xCD, 0xAB]);
+        let rom = Rom::from_prg(&prg, &[], Mirroring::Vertical);
+        let mut bus = Bus::new(rom, |_, _| EmulationFlow::Continue).unwrap();
+        BusOperation::<u8>::write(&mut bus, 0x0000, 0x12);
+
+        let vector: u16 = bus.read(0xFFFE);
+        let wrapped: u16 = bus.read(0xFFFF);
+
+        assert_eq!(vector, 0xABCD);
+        assert_eq!(wrapped, 0x12AB);
+        assert_eq!(bus.cycles, 1 + 4);
+    }
+
+    #[test]
+    fn test_peek_and_poke_have_no_side_effects() {
+        let mut bus = setup_bus_with_mapper(0);
+        bus.poke(0x0810, 0x42);
+        bus.poke(0x8000, 0xEA);
+        bus.ppu.write_ppuaddr(0x21);
+        bus.ppu.write_ppuaddr(0x00);
+        bus.poke(0x2007, 0x17);
+
+        assert_eq!(bus.peek(0x0010), 0x42);
+        assert_eq!(bus.peek(0x8000), 0xEA);
+        assert_eq!(bus.peek(0x2007), 0x17);
+        assert_eq!(bus.peek(0x3FFF), 0x17);
+        assert_eq!(bus.cycles, 0);
+        // Neither poke nor peek moved v
+        assert_eq!(bus.ppu.peek_ppudata(), 0x17);
+        let value: u8 = bus.read(0x0010);
+        assert_eq!(value, 0x42);
+    }
+
+    #[test]
+    fn test_peek_u16_reads_little_endian_pointers() {
+        let mut bus = setup_bus_with_mapper(0);
+        bus.poke(0x07FF, 0x34);
+        bus.poke(0x0000, 0x12);
+        bus.poke(0xFFFC, 0x00);
+        bus.poke(0xFFFD, 0x80);
+        bus.poke(0xFFFF, 0xC0);
+
+        assert_eq!(bus.peek_u16(0xFFFC), 0x8000);
+        // Crosses the RAM mirror and the end of the address space
+        assert_eq!(bus.peek_u16(0x07FF), 0x1234);
+        assert_eq!(bus.peek_u16(0xFFFF), 0x12C0);
+        assert_eq!(bus.cycles, 0);
+    }
+
+    #[test]
+    fn test_on_frame_reports_cycles_per_frame() {
+        let timings = Rc::new(RefCell::new(Vec::new()));
+        let mut bus = setup_bus_with_mapper(0);
+        let log = timings.clone();
+        bus.on_frame(Box::new(move |timing| log.borrow_mut().push(timing)));
+
+        while bus.stats().frames < 3 {
+            bus.tick(1);
         }
+
+        let timings = timings.borrow();
+        assert_eq!(timings.len(), 3);
+        assert_eq!(
+            timings.iter().map(|t| t.frame).collect::<Vec<_>>(),
+            [1, 2, 3]
+        );
+        assert_eq!(
+            timings.iter().map(|t| t.frame_cycles).sum::<usize>(),
+            timings[2].cycles
+        );
+        // NTSC frames are 29780 or 29781 CPU cycles
+        assert!((29780..=29781).contains(&timings[2].frame_cycles));
+        #[cfg(feature = "std")]
+        assert!(timings[1].timestamp <= timings[2].timestamp);
+    }
+
+    #[test]
+    fn test_ram_fill_is_visible_in_uninitialized_ram() {
+        let mut bus = setup_bus_with_mapper(0);
+        assert_eq!(bus.peek(0x0000), 0x00);
+
+        bus.set_ram_fill(RamFill::AllOnes);
+        assert_eq!(bus.peek(0x07FF), 0xFF);
+
+        bus.set_ram_fill(RamFill::Pattern(&[
+            0x00, 0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF, 0xFF,
+        ]));
+        bus.write(0x0004, 0x42u8);
+        bus.power_on();
+        let ram: Vec<u8> = (0x0000..0x0010).map(|address| bus.read(address)).collect();
+        assert_eq!(ram, [0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF].repeat(2));
+        // Mirrored through $0800-$1FFF
+        assert_eq!(bus.peek(0x1FFF), 0xFF);
+    }
+
+    #[test]
+    fn test_frame_skip_calls_frame_callback_every_nth_frame() {
+        let mut callbacks = 0;
+        let mut bus = setup_bus_with_callback(0, |_, _| {
+            callbacks += 1;
+            EmulationFlow::Continue
+        });
+        bus.set_frame_skip(2);
+
+        while bus.stats().frames < 9 {
+            bus.tick(1);
+        }
+        drop(bus);
+
+        assert_eq!(callbacks, 3);
+    }
+
+    #[test]
+    fn test_frame_skip_leaves_index_buffer_of_skipped_frames() {
+        fn run_frame(bus: &mut Bus) {
+            while !bus.take_frame_ready() {
+                bus.tick(1);
+            }
+        }
+        fn set_backdrop(bus: &mut Bus, color: u8) {
+            bus.ppu.write_ppuaddr(0x3F);
+            bus.ppu.write_ppuaddr(0x00);
+            bus.ppu.write_ppudata(color);
+        }
+        let mut bus = setup_bus_with_callback(0, |_, _| EmulationFlow::Continue);
+        bus.set_frame_skip(1);
+
+        set_backdrop(&mut bus, 0x0F);
+        run_frame(&mut bus);
+        assert!(bus.ppu.index_buffer().iter().all(|&index| index == 0x0F));
+
+        set_backdrop(&mut bus, 0x21);
+        run_frame(&mut bus);
+        assert!(bus.ppu.index_buffer().iter().all(|&index| index == 0x0F));
+
+        run_frame(&mut bus);
+        assert!(bus.ppu.index_buffer().iter().all(|&index| index == 0x21));
+    }
+
+    #[test]
+    fn test_event_log_records_vblank_and_mapper_writes() {
+        let mut bus = setup_bus_with_mapper(4);
+        bus.enable_event_log(16);
+
+        while !bus.ppu.status_flags().contains(PPUSTATUS::VBLANK_FLAG) {
+            bus.tick(1);
+        }
+        let _: u8 = bus.read(0x2002);
+        BusOperation::<u8>::write(&mut bus, 0x8001, 0x05);
+
+        let events = bus.drain_events();
+        let kinds: Vec<EventKind> = events.iter().map(|event| event.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                EventKind::VblankSet,
+                EventKind::VblankCleared,
+                EventKind::MapperWrite {
+                    address: 0x8001,
+                    value: 0x05
+                },
+            ]
+        );
+        assert_eq!(events[0].scanline, 241);
+        assert!(bus.drain_events().is_empty());
+    }
+
+    #[test]
+    fn test_zapper_senses_the_rendered_frame() {
+        let mut bus = setup_bus_with_mapper(0);
+        let mut zapper = Zapper::new();
+        zapper.aim(10, 100, false);
+        bus.set_port2(InputDevice::Zapper(zapper));
+        // Light sense bit of $4017, clear when light is detected
+        let light_sensed = |bus: &mut Bus| BusOperation::<u8>::read(bus, 0x4017) & 0b1000 == 0;
+        let show_backdrop = |bus: &mut Bus, color: u8| {
+            for (address, value) in [(0x2006, 0x3F), (0x2006, 0x00), (0x2007, color)] {
+                BusOperation::<u8>::write(bus, address, value);
+            }
+            while !bus.take_frame_ready() {
+                bus.tick(1);
+            }
+            while bus.ppu.scanline != 100 {
+                bus.tick(1);
+            }
+        };
+
+        show_backdrop(&mut bus, 0x30);
+        assert!(light_sensed(&mut bus));
+
+        show_backdrop(&mut bus, 0x0F);
+        assert!(!light_sensed(&mut bus));
+    }
+
+    pub(super) fn setup_bus_with_mapper<'call>(mapper: u8) -> Bus<'call> {
+        setup_bus_with_callback(mapper, |_, _| EmulationFlow::Continue)
+    }
+
+    fn setup_bus_with_callback<'call, F>(mapper: u8, callback: F) -> Bus<'call>
+    where
+        F: FnMut(&PPU, &mut Controller) -> EmulationFlow + 'call,
+    {
+        let rom = Rom::new(&ines_image(mapper, 2, 1)).unwrap();
+        Bus::new(rom, callback).unwrap()
     }
 }
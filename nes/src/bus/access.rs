@@ -0,0 +1,16 @@
+// A single byte moved over the CPU data bus, reported to the access log.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Access {
+    pub addr: u16,
+    pub value: u8,
+    pub is_write: bool,
+    pub kind: AccessKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AccessKind {
+    // Performed by the instruction being executed
+    Cpu,
+    // Performed by the OAM DMA unit while the CPU is halted
+    OamDma,
+}
@@ -0,0 +1,50 @@
+use crate::mapper::error::UnsupportedMapper;
+use core::error::Error;
+use core::fmt::{Display, Formatter};
+
+#[derive(Debug)]
+pub enum SramError {
+    InvalidSize(usize),
+}
+
+impl Display for SramError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            SramError::InvalidSize(actual) => {
+                write!(f, "PRG RAM save data must be 8192 bytes, actual {}", actual)
+            }
+        }
+    }
+}
+
+impl Error for SramError {}
+
+// Reasons a bus can't be created for a cartridge
+#[derive(Debug)]
+pub enum BusError {
+    UnsupportedMapper(UnsupportedMapper),
+    Sram(SramError),
+}
+
+impl Display for BusError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            BusError::UnsupportedMapper(error) => write!(f, "{}", error),
+            BusError::Sram(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl Error for BusError {}
+
+impl From<UnsupportedMapper> for BusError {
+    fn from(error: UnsupportedMapper) -> Self {
+        BusError::UnsupportedMapper(error)
+    }
+}
+
+impl From<SramError> for BusError {
+    fn from(error: SramError) -> Self {
+        BusError::Sram(error)
+    }
+}
@@ -0,0 +1,91 @@
+use crate::bus::Bus;
+use crate::ppu::ppu::PPU;
+use alloc::vec::Vec;
+
+// Everything on the bus that changes while a game runs: RAM, the PPU,
+// mapper registers and the timing counters. PRG ROM and CHR ROM aren't
+// copied, a state can only be loaded into a bus with the same cartridge.
+// Controllers, the APU and the callbacks are left out, they belong to the
+// frontend rather than to the emulated machine.
+pub struct BusState {
+    cpu_ram: [u8; 2048],
+    prg_ram: [u8; 8192],
+    ppu: PPU,
+    mapper: Vec<u8>,
+    cycles: usize,
+    ppu_dots_remainder: usize,
+    access_cycles: u8,
+    last_bus_value: u8,
+    frame_ready: bool,
+    frame_count: usize,
+    frames_until_callback: u8,
+    frame_start_cycles: usize,
+}
+
+impl Bus<'_> {
+    pub(crate) fn save_state(&self) -> BusState {
+        BusState {
+            cpu_ram: self.cpu_ram,
+            prg_ram: self.prg_ram,
+            ppu: self.ppu.save_state(),
+            mapper: self.mapper.borrow().save_state(),
+            cycles: self.cycles,
+            ppu_dots_remainder: self.ppu_dots_remainder,
+            access_cycles: self.access_cycles,
+            last_bus_value: self.last_bus_value,
+            frame_ready: self.frame_ready,
+            frame_count: self.frame_count,
+            frames_until_callback: self.frames_until_callback,
+            frame_start_cycles: self.frame_start_cycles,
+        }
+    }
+
+    pub(crate) fn load_state(&mut self, state: &BusState) {
+        self.cpu_ram = state.cpu_ram;
+        self.prg_ram = state.prg_ram;
+        self.ppu.load_state(&state.ppu);
+        self.mapper.borrow_mut().load_state(&state.mapper);
+        self.cycles = state.cycles;
+        self.ppu_dots_remainder = state.ppu_dots_remainder;
+        self.access_cycles = state.access_cycles;
+        self.last_bus_value = state.last_bus_value;
+        self.frame_ready = state.frame_ready;
+        self.frame_count = state.frame_count;
+        self.frames_until_callback = state.frames_until_callback;
+        self.frame_start_cycles = state.frame_start_cycles;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bus::BusOperation;
+    use crate::bus::tests::setup_bus_with_mapper;
+    use crate::ppu::mirroring::Mirroring;
+
+    #[test]
+    fn test_state_restores_ram_ppu_and_mapper_registers() {
+        let mut bus = setup_bus_with_mapper(4);
+        bus.write(0x0010, 0x11u8);
+        bus.write(0x6000, 0x22u8);
+        // Horizontal mirroring, IRQ latch 5
+        bus.write(0xA000, 1u8);
+        bus.write(0xC000, 5u8);
+        bus.tick(100);
+        let cycles = bus.cycles;
+        let state = bus.save_state();
+
+        bus.write(0x0010, 0x33u8);
+        bus.write(0x6000, 0x44u8);
+        bus.write(0xA000, 0u8);
+        bus.write(0xC000, 9u8);
+        bus.tick(200);
+        bus.load_state(&state);
+        assert_eq!(bus.cycles, cycles);
+
+        let ram: u8 = bus.read(0x0010);
+        let prg_ram: u8 = bus.read(0x6000);
+        assert_eq!((ram, prg_ram), (0x11, 0x22));
+        assert_eq!(bus.ppu.current_mirroring(), Mirroring::Horizontal);
+        assert_eq!(bus.mapper.borrow().save_state()[10], 5);
+    }
+}
@@ -0,0 +1,14 @@
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+// Reported at the end of every frame, so a frontend can compare the wall
+// clock time between frames with the target frame time and skip rendering.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameTiming {
+    pub frame: usize,
+    // CPU cycles since power on and the ones the finished frame took
+    pub cycles: usize,
+    pub frame_cycles: usize,
+    #[cfg(feature = "std")]
+    pub timestamp: Instant,
+}
@@ -1,2 +1,4 @@
 pub mod controller;
+pub mod input_device;
 pub mod register;
+pub mod zapper;
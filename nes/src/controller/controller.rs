@@ -5,6 +5,9 @@ pub struct Controller {
     buttons: JoypadRegister,
     strobe: bool,
     button_index: u8,
+    // Autofire period per button in frames, 0 means no turbo
+    turbo_rates: [u8; 8],
+    frame: u32,
 }
 
 impl Controller {
@@ -13,6 +16,8 @@ impl Controller {
             buttons: JoypadRegister::new(),
             strobe: false,
             button_index: 0,
+            turbo_rates: [0; 8],
+            frame: 0,
         }
     }
 
@@ -20,9 +25,41 @@ impl Controller {
         self.buttons.set(button, status);
     }
 
+    // A held turbo button is reported as pressed for `rate_frames` frames and
+    // released for the next `rate_frames`. A rate of 0 turns turbo off.
+    pub fn set_turbo(&mut self, button: JoypadRegister, rate_frames: u8) {
+        for (index, rate) in self.turbo_rates.iter_mut().enumerate() {
+            if button.bits() & (1 << index) != 0 {
+                *rate = rate_frames;
+            }
+        }
+    }
+
+    // Called once per frame to drive turbo buttons, the bus does it for controller 1
+    pub fn advance_frame(&mut self) {
+        self.frame = self.frame.wrapping_add(1);
+    }
+
+    fn effective_buttons(&self) -> JoypadRegister {
+        let mut buttons = self.buttons;
+        for (index, &rate) in self.turbo_rates.iter().enumerate() {
+            if rate != 0 && (self.frame / rate as u32) % 2 == 1 {
+                buttons.remove(JoypadRegister::from_bits_truncate(1 << index));
+            }
+        }
+        buttons
+    }
+
+    // While strobe is high the shift register keeps reloading, so every read
+    // returns the live state of A. Once it goes low the buttons are shifted
+    // out in order, and reads past the eighth button return 1.
     pub fn read(&mut self) -> u8 {
-        let button_state = self.buttons.get_button_state(self.button_index);
-        if !self.strobe && self.button_index <= 7 {
+        if self.strobe {
+            self.button_index = 0;
+            return self.effective_buttons().get_button_state(0);
+        }
+        let button_state = self.effective_buttons().get_button_state(self.button_index);
+        if self.button_index <= 7 {
             self.button_index += 1;
         }
         button_state
@@ -35,3 +72,66 @@ impl Controller {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_strobe_protocol() {
+        let mut controller = Controller::new();
+
+        // Strobe high: every read reflects A as it is right now
+        controller.write(1);
+        assert_eq!(controller.read(), 0);
+        controller.set_button_status(JoypadRegister::BUTTON_A, true);
+        assert_eq!(controller.read(), 1);
+        controller.set_button_status(JoypadRegister::START, true);
+        assert_eq!(controller.read(), 1);
+        controller.set_button_status(JoypadRegister::BUTTON_A, false);
+        assert_eq!(controller.read(), 0);
+
+        // Strobe low: A, B, Select, Start, Up, Down, Left, Right, then 1s
+        controller.set_button_status(JoypadRegister::BUTTON_A | JoypadRegister::LEFT, true);
+        controller.write(0);
+        let reads: Vec<u8> = (0..10).map(|_| controller.read()).collect();
+        assert_eq!(reads, [1, 0, 0, 1, 0, 0, 1, 0, 1, 1]);
+
+        // Raising strobe again restarts from A
+        controller.write(1);
+        controller.write(0);
+        assert_eq!(controller.read(), 1);
+        assert_eq!(controller.read(), 0);
+    }
+
+    #[test]
+    fn test_turbo_button_alternates_at_configured_rate() {
+        let mut controller = Controller::new();
+        controller.set_turbo(JoypadRegister::BUTTON_A, 2);
+        controller.set_button_status(JoypadRegister::BUTTON_A | JoypadRegister::BUTTON_B, true);
+
+        let mut sequence = Vec::new();
+        for _ in 0..8 {
+            controller.write(1);
+            controller.write(0);
+            sequence.push((controller.read(), controller.read()));
+            controller.advance_frame();
+        }
+
+        let a: Vec<u8> = sequence.iter().map(|&(a, _)| a).collect();
+        assert_eq!(a, [1, 1, 0, 0, 1, 1, 0, 0]);
+        assert!(sequence.iter().all(|&(_, b)| b == 1));
+    }
+
+    #[test]
+    fn test_released_turbo_button_reads_as_released() {
+        let mut controller = Controller::new();
+        controller.set_turbo(JoypadRegister::BUTTON_A, 1);
+        for _ in 0..4 {
+            controller.write(1);
+            assert_eq!(controller.read(), 0);
+            controller.advance_frame();
+        }
+    }
+}
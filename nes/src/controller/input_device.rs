@@ -0,0 +1,7 @@
+use crate::controller::controller::Controller;
+use crate::controller::zapper::Zapper;
+
+pub enum InputDevice {
+    Controller(Controller),
+    Zapper(Zapper),
+}
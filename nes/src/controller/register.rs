@@ -1,7 +1,7 @@
 use bitflags::bitflags;
 
 bitflags! {
-    #[derive(Copy, Clone)]
+    #[derive(Debug, Copy, Clone, PartialEq)]
     pub struct JoypadRegister: u8 {
         const BUTTON_A = 0b0000_0001;
         const BUTTON_B = 0b0000_0010;
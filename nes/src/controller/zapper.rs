@@ -0,0 +1,105 @@
+// https://www.nesdev.org/wiki/Zapper
+//
+// 7  bit  0
+// ---- ----
+// xxxT WxxS
+//    | |  |
+//    | |  +- Serial data (Vs.)
+//    | +---- Light sense (0: detected; 1: not detected)
+//    +------ Trigger (0: released; 1: pulled)
+pub struct Zapper {
+    x: usize,
+    y: usize,
+    trigger: bool,
+    light_sensed: bool,
+}
+
+impl Zapper {
+    const LIGHT_SENSE_BIT: u8 = 0b0000_1000;
+    const TRIGGER_BIT: u8 = 0b0001_0000;
+
+    // The photodiode keeps reporting light for a few scanlines after
+    // the beam passes the aimed point.
+    const LIGHT_SENSE_SCANLINES: usize = 26;
+    const BRIGHTNESS_THRESHOLD: u8 = 0xA0;
+    const FRAME_WIDTH: usize = 256;
+
+    pub fn new() -> Zapper {
+        Zapper {
+            x: 0,
+            y: 0,
+            trigger: false,
+            light_sensed: false,
+        }
+    }
+
+    pub fn aim(&mut self, x: usize, y: usize, trigger: bool) {
+        self.x = x;
+        self.y = y;
+        self.trigger = trigger;
+    }
+
+    // Checks the aimed pixel of the rendered RGB24 frame.
+    pub fn sense_light(&mut self, frame: &[u8]) {
+        let pixel_index = (self.y * Zapper::FRAME_WIDTH + self.x) * 3;
+        match frame.get(pixel_index..pixel_index + 3) {
+            Some(rgb) => self.sense_brightness(Zapper::luma(rgb[0], rgb[1], rgb[2])),
+            None => self.light_sensed = false,
+        }
+    }
+
+    // Pixel the Zapper points at
+    pub fn aim_position(&self) -> (usize, usize) {
+        (self.x, self.y)
+    }
+
+    // Luma (0-255) of the aimed pixel, the bus samples `PPU::pixel_brightness`
+    // on every read of the port
+    pub fn sense_brightness(&mut self, brightness: u8) {
+        self.light_sensed = brightness >= Zapper::BRIGHTNESS_THRESHOLD;
+    }
+
+    // ITU-R BT.601 weights
+    pub fn luma(red: u8, green: u8, blue: u8) -> u8 {
+        ((red as u32 * 299 + green as u32 * 587 + blue as u32 * 114) / 1000) as u8
+    }
+
+    pub fn read(&self, scanline: u16) -> u8 {
+        let scanline = scanline as usize;
+        let beam_near_aim = scanline >= self.y && scanline < self.y + Zapper::LIGHT_SENSE_SCANLINES;
+
+        let mut value = 0;
+        if !(self.light_sensed && beam_near_aim) {
+            value |= Zapper::LIGHT_SENSE_BIT;
+        }
+        if self.trigger {
+            value |= Zapper::TRIGGER_BIT;
+        }
+        value
+    }
+}
+
+impl Default for Zapper {
+    fn default() -> Self {
+        Zapper::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_light_is_sensed_only_on_bright_pixels_near_the_beam() {
+        let mut zapper = Zapper::new();
+        zapper.aim(10, 100, true);
+
+        zapper.sense_brightness(Zapper::luma(0xFF, 0xFF, 0xFF));
+        assert_eq!(zapper.read(100) & Zapper::LIGHT_SENSE_BIT, 0);
+        assert_ne!(zapper.read(99) & Zapper::LIGHT_SENSE_BIT, 0);
+        assert_ne!(zapper.read(100) & Zapper::TRIGGER_BIT, 0);
+
+        zapper.sense_brightness(Zapper::luma(0x00, 0x00, 0xFF));
+        assert_ne!(zapper.read(100) & Zapper::LIGHT_SENSE_BIT, 0);
+    }
+}
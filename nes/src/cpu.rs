@@ -1,5 +1,12 @@
+pub mod builder;
+#[cfg(feature = "std")]
+pub mod clock;
 pub mod cpu;
 pub mod error;
 pub mod opcode;
 pub mod register;
+pub mod rewind;
+pub mod snapshot;
+pub mod state;
+pub mod test_rom;
 pub mod trace;
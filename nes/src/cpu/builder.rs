@@ -0,0 +1,251 @@
+use crate::bus::{Bus, BusOperation, EmulationFlow};
+use crate::cpu::cpu::CPU;
+use crate::cpu::error::HexProgramError;
+use crate::ppu::mirroring::Mirroring;
+use crate::rom::rom::Rom;
+use alloc::vec;
+use alloc::vec::Vec;
+
+// Builds a CPU running a flat program, intended for tests and 6502 harnesses.
+// The program is placed at the start of a single 16 KiB PRG ROM bank mapped to
+// $8000 (mirrored at $C000) and the reset vector points to its first byte.
+pub struct CpuBuilder {
+    program: Vec<u8>,
+    accumulator: Option<u8>,
+    register_x: Option<u8>,
+    register_y: Option<u8>,
+    status: Option<u8>,
+    stack_pointer: Option<u8>,
+    memory: Vec<(u16, u8)>,
+}
+
+impl CpuBuilder {
+    pub const PROGRAM_START: u16 = 0x8000;
+    const PRG_ROM_SIZE: usize = 0x4000;
+    // The last 6 bytes of the bank hold the NMI, reset and IRQ vectors
+    const MAX_PROGRAM_SIZE: usize = CpuBuilder::PRG_ROM_SIZE - 6;
+    // `from_prg` writes the reset and IRQ vectors over the last 4 bytes
+    const VECTORS_SIZE: usize = 4;
+
+    pub fn new(program: &[u8]) -> Self {
+        assert!(
+            program.len() <= CpuBuilder::MAX_PROGRAM_SIZE,
+            "Program must not exceed {} bytes, actual {}",
+            CpuBuilder::MAX_PROGRAM_SIZE,
+            program.len()
+        );
+        CpuBuilder {
+            program: program.to_vec(),
+            accumulator: None,
+            register_x: None,
+            register_y: None,
+            status: None,
+            stack_pointer: None,
+            memory: vec![],
+        }
+    }
+
+    pub fn accumulator(mut self, value: u8) -> Self {
+        self.accumulator = Some(value);
+        self
+    }
+
+    pub fn register_x(mut self, value: u8) -> Self {
+        self.register_x = Some(value);
+        self
+    }
+
+    pub fn register_y(mut self, value: u8) -> Self {
+        self.register_y = Some(value);
+        self
+    }
+
+    pub fn status(mut self, value: u8) -> Self {
+        self.status = Some(value);
+        self
+    }
+
+    pub fn stack_pointer(mut self, value: u8) -> Self {
+        self.stack_pointer = Some(value);
+        self
+    }
+
+    // Values written through the bus after power on, so they have to target
+    // writable memory (CPU RAM, PRG RAM or registers).
+    pub fn memory(mut self, address: u16, value: u8) -> Self {
+        self.memory.push((address, value));
+        self
+    }
+
+    pub fn build<'bus>(self) -> CPU<'bus> {
+        let mut cpu = CPU::from_prg(&self.program, CpuBuilder::PROGRAM_START);
+
+        // Presets don't take CPU time
+        cpu.bus.set_access_ticking(false);
+        for (address, value) in self.memory {
+            cpu.bus.write(address, value);
+        }
+        cpu.bus.set_access_ticking(true);
+
+        if let Some(value) = self.accumulator {
+            cpu.accumulator.set(value);
+        }
+        if let Some(value) = self.register_x {
+            cpu.register_x.set(value);
+        }
+        if let Some(value) = self.register_y {
+            cpu.register_y.set(value);
+        }
+        if let Some(value) = self.status {
+            cpu.status.update(value);
+        }
+        if let Some(value) = self.stack_pointer {
+            cpu.stack.set_pointer(value);
+        }
+        cpu
+    }
+}
+
+impl<'bus> CPU<'bus> {
+    pub fn from_program(program: &[u8]) -> Self {
+        CpuBuilder::new(program).build()
+    }
+
+    // Runs whitespace separated hex bytes, e.g. "a9 05 aa e8 00", from $8000.
+    // Handy for a REPL or doc examples without an assembler.
+    pub fn load_hex(program: &str) -> Result<Self, HexProgramError> {
+        let mut bytes = program
+            .split_whitespace()
+            .enumerate()
+            .map(|(index, token)| {
+                let is_byte = token.len() <= 2 && token.bytes().all(|b| b.is_ascii_hexdigit());
+                is_byte
+                    .then(|| u8::from_str_radix(token, 16).ok())
+                    .flatten()
+                    .ok_or(HexProgramError::InvalidByte(index))
+            })
+            .collect::<Result<Vec<u8>, _>>()?;
+        if bytes.is_empty() {
+            return Err(HexProgramError::Empty);
+        }
+        let max_size = 2 * CpuBuilder::PRG_ROM_SIZE - CpuBuilder::VECTORS_SIZE;
+        if bytes.len() > max_size {
+            return Err(HexProgramError::TooLong(bytes.len()));
+        }
+        // A program reaching into the vectors of the first bank gets a second one
+        if bytes.len() > CpuBuilder::PRG_ROM_SIZE - CpuBuilder::VECTORS_SIZE {
+            bytes.resize(max_size, 0);
+        }
+        Ok(CPU::from_prg(&bytes, CpuBuilder::PROGRAM_START))
+    }
+
+    // Loads a headerless PRG binary at $8000, padded to a full 16 KiB bank
+    // (mirrored at $C000) or 32 KiB if it is longer. The reset and IRQ vectors
+    // are set to `reset_vector`, the CPU is powered on and ready to run.
+    pub fn from_prg(prg: &[u8], reset_vector: u16) -> Self {
+        let bank_size = CpuBuilder::PRG_ROM_SIZE;
+        assert!(
+            prg.len() <= bank_size * 2,
+            "PRG must not exceed {} bytes, actual {}",
+            bank_size * 2,
+            prg.len()
+        );
+
+        let mut prg_rom = prg.to_vec();
+        prg_rom.resize(prg.len().div_ceil(bank_size).max(1) * bank_size, 0);
+        let [low, high] = reset_vector.to_le_bytes();
+        let vectors = prg_rom.len() - 4;
+        prg_rom[vectors..].copy_from_slice(&[low, high, low, high]);
+
+        let rom = Rom::from_prg(&prg_rom, &[], Mirroring::Horizontal);
+        let bus = Bus::new(rom, |_, _| EmulationFlow::Continue).expect("NROM is built in");
+        let mut cpu = CPU::new(bus);
+        cpu.power_on();
+        cpu
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::cpu::ExitReason;
+    use alloc::format;
+
+    #[test]
+    fn test_load_hex_runs_program() {
+        // LDA #$05; TAX; INX; KIL
+        let mut cpu = CPU::load_hex("a9 05\taa\nE8 02").unwrap();
+
+        cpu.run(|_| {}).unwrap();
+
+        assert_eq!(cpu.accumulator.get(), 0x05);
+        assert_eq!(cpu.register_x.get(), 0x06);
+    }
+
+    #[test]
+    fn test_load_hex_rejects_bad_input() {
+        assert_eq!(
+            CPU::load_hex("a9 0g").err(),
+            Some(HexProgramError::InvalidByte(1))
+        );
+        assert_eq!(
+            CPU::load_hex("a9 105").err(),
+            Some(HexProgramError::InvalidByte(1))
+        );
+        assert_eq!(
+            CPU::load_hex("+5").err(),
+            Some(HexProgramError::InvalidByte(0))
+        );
+        assert_eq!(CPU::load_hex("  ").err(), Some(HexProgramError::Empty));
+    }
+
+    #[test]
+    fn test_load_hex_keeps_room_for_the_vectors() {
+        let too_long = "ea ".repeat(0x7FFD);
+        assert_eq!(
+            CPU::load_hex(&too_long).err(),
+            Some(HexProgramError::TooLong(0x7FFD))
+        );
+
+        // NOPs up to the end of the first bank, then KIL at $BFFC
+        let mut cpu = CPU::load_hex(&format!("{}02", "ea ".repeat(0x3FFC))).unwrap();
+        assert_eq!(cpu.pc(), 0x8000);
+        assert_eq!(cpu.bus.peek(0xBFFC), 0x02);
+        assert!(matches!(
+            cpu.run(|_| {}),
+            Ok(ExitReason::Jammed { pc: 0xBFFC, .. })
+        ));
+    }
+
+    #[test]
+    fn test_builder_presets_registers_and_memory() {
+        let mut cpu = CpuBuilder::new(&[0x02])
+            .accumulator(0x01)
+            .register_x(0x02)
+            .register_y(0x03)
+            .stack_pointer(0xF0)
+            .memory(0x0200, 0x42)
+            .build();
+
+        assert_eq!(cpu.program_counter.get(), CpuBuilder::PROGRAM_START);
+        assert_eq!(cpu.accumulator.get(), 0x01);
+        assert_eq!(cpu.register_x.get(), 0x02);
+        assert_eq!(cpu.register_y.get(), 0x03);
+        assert_eq!(cpu.stack.get_pointer(), 0xF0);
+        let value: u8 = cpu.bus.read(0x0200);
+        assert_eq!(value, 0x42);
+    }
+
+    #[test]
+    fn test_from_prg_mirrors_bank_and_sets_reset_vector() {
+        let mut prg = vec![0xEA; 0x10];
+        prg[0] = 0xA9;
+        let mut cpu = CPU::from_prg(&prg, 0xC000);
+
+        assert_eq!(cpu.program_counter.get(), 0xC000);
+        let value: u8 = cpu.bus.read(0xC000);
+        assert_eq!(value, 0xA9);
+        let value: u8 = cpu.bus.read(0x8000);
+        assert_eq!(value, 0xA9);
+    }
+}
@@ -0,0 +1,96 @@
+use crate::region::Region;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Paces emulation to the CPU frequency by sleeping whenever the emulated
+// cycles get ahead of the wall clock.
+pub struct Clock {
+    frequency: u32,
+    speed_multiplier: f64,
+    start: Instant,
+    start_cycles: usize,
+    last_cycles: usize,
+}
+
+impl Clock {
+    // Sleeping for less than this is imprecise on most platforms, so short
+    // leads are accumulated until they are worth a sleep.
+    const MIN_SLEEP: Duration = Duration::from_millis(1);
+
+    pub fn new(frequency: u32) -> Self {
+        Clock {
+            frequency,
+            speed_multiplier: 1.0,
+            start: Instant::now(),
+            start_cycles: 0,
+            last_cycles: 0,
+        }
+    }
+
+    pub fn for_region(region: Region) -> Self {
+        Clock::new(region.cpu_frequency())
+    }
+
+    pub fn frequency(&self) -> u32 {
+        self.frequency
+    }
+
+    pub fn speed_multiplier(&self) -> f64 {
+        self.speed_multiplier
+    }
+
+    // 2.0 runs twice as fast, 0.5 at half speed
+    pub fn set_speed_multiplier(&mut self, multiplier: f64) {
+        self.speed_multiplier = multiplier;
+        self.start = Instant::now();
+        self.start_cycles = self.last_cycles;
+    }
+
+    // Time the given amount of CPU cycles takes at the current speed
+    pub fn cycles_duration(&self, cycles: usize) -> Duration {
+        Duration::from_secs_f64(cycles as f64 / (self.frequency as f64 * self.speed_multiplier))
+    }
+
+    // Takes the total cycles passed since power on, e.g. `Bus::cycles`
+    pub fn pace(&mut self, cycles: usize) {
+        if cycles < self.start_cycles {
+            self.start = Instant::now();
+            self.start_cycles = cycles;
+        }
+        self.last_cycles = cycles;
+
+        let target = self.cycles_duration(cycles - self.start_cycles);
+        let elapsed = self.start.elapsed();
+        if target > elapsed + Clock::MIN_SLEEP {
+            thread::sleep(target - elapsed);
+        }
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock::for_region(Region::Ntsc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cycles_duration_follows_speed_multiplier() {
+        let mut clock = Clock::new(1000);
+        assert_eq!(clock.cycles_duration(500), Duration::from_millis(500));
+
+        clock.set_speed_multiplier(2.0);
+        assert_eq!(clock.cycles_duration(500), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn test_pace_waits_for_wall_clock() {
+        let mut clock = Clock::new(1000);
+        let start = Instant::now();
+        clock.pace(20);
+        assert!(start.elapsed() >= Duration::from_millis(19));
+    }
+}
@@ -1,14 +1,45 @@
 use crate::bus::{Bus, BusOperation};
-use crate::cpu::error::UnknownOpCode;
-use crate::cpu::opcode::OPCODES;
+#[cfg(feature = "std")]
+use crate::cpu::clock::Clock;
+use crate::cpu::error::{CPUError, StackError, UnknownOpCode};
 use crate::cpu::opcode::{AddressingMode, Instruction, OpCode};
+use crate::cpu::opcode::{OPCODES, OpCodeTable};
 use crate::cpu::register::counter::ProgramCounter;
 use crate::cpu::register::register::Register;
 use crate::cpu::register::stack::{Stack, StackOperation};
 use crate::cpu::register::status::ProcessorStatus;
+use crate::cpu::rewind::RewindBuffer;
+#[cfg(feature = "std")]
+use crate::cpu::trace::trace;
+use crate::event::EventKind;
+use crate::mapper::error::UnsupportedMapper;
+use crate::rom::rom::Rom;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::Write;
 
 type PageCrossed = bool;
 
+// Why `run` returned without an error
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExitReason {
+    // The frame callback returned `EmulationFlow::Break`
+    Break,
+    // A KIL opcode locked the CPU up, only a reset recovers it
+    Jammed { opcode: u8, pc: u16 },
+}
+
+// What the CPU does with a byte missing from the opcode table. The table
+// covers all 256 opcodes, so this only matters for a partial table.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnknownOpCodePolicy {
+    // `run` and `step` fail with `CPUError::UnknownOpCode`
+    Error,
+    // The byte is executed as a 2-cycle implied NOP, e.g. to keep a corrupt
+    // ROM running
+    TreatAsNop,
+}
+
 pub struct CPU<'bus> {
     pub accumulator: Register<u8>,
     pub register_x: Register<u8>,
@@ -17,12 +48,27 @@ pub struct CPU<'bus> {
     pub status: ProcessorStatus,
     pub bus: Bus<'bus>,
     pub stack: Stack,
+    xaa_magic: u8,
+    breakpoints: Vec<u16>,
+    pub(crate) instruction_budget: Option<u64>,
+    opcodes: &'static OpCodeTable,
+    unknown_opcode_policy: UnknownOpCodePolicy,
+    pub(crate) rewind: Option<RewindBuffer>,
 }
 
 impl<'bus> CPU<'bus> {
     const NMI_INTERRUPT_VECTOR: u16 = 0xFFFA;
     const RESET_INTERRUPT_VECTOR: u16 = 0xFFFC;
     const IRQ_INTERRUPT_VECTOR: u16 = 0xFFFE;
+    const RESET_CYCLES: u8 = 7;
+    const DEFAULT_XAA_MAGIC: u8 = 0xEE;
+    const JSR_OPCODE: u8 = 0x20;
+    const UNKNOWN_OPCODE_NOP: Instruction = Instruction {
+        opcode: OpCode::NOP,
+        mode: AddressingMode::Implied,
+        cycles: 2,
+        official: false,
+    };
 
     pub fn new(bus: Bus<'bus>) -> Self {
         CPU {
@@ -33,101 +79,257 @@ impl<'bus> CPU<'bus> {
             status: ProcessorStatus::new(),
             stack: Stack::new(),
             bus,
+            xaa_magic: Self::DEFAULT_XAA_MAGIC,
+            breakpoints: Vec::new(),
+            instruction_budget: None,
+            opcodes: &OPCODES,
+            unknown_opcode_policy: UnknownOpCodePolicy::Error,
+            rewind: None,
         }
     }
 
-    pub fn run<F>(&mut self, mut callback: F) -> Result<(), UnknownOpCode>
+    // XAA depends on analog behaviour of the chip, the constant ORed into the
+    // accumulator differs between CPUs (usually 0x00, 0xEE or 0xFF).
+    pub fn set_xaa_magic(&mut self, magic: u8) {
+        self.xaa_magic = magic;
+    }
+
+    // Makes `run` fail after executing that many instructions, so a game stuck
+    // in a loop (e.g. waiting for a sprite 0 hit) doesn't freeze the frontend.
+    // Unlimited by default.
+    pub fn set_instruction_budget(&mut self, max: u64) {
+        self.instruction_budget = Some(max);
+    }
+
+    pub fn clear_instruction_budget(&mut self) {
+        self.instruction_budget = None;
+    }
+
+    pub fn set_unknown_opcode_policy(&mut self, policy: UnknownOpCodePolicy) {
+        self.unknown_opcode_policy = policy;
+    }
+
+    pub fn run<F>(&mut self, mut callback: F) -> Result<ExitReason, CPUError>
     where
         F: FnMut(&mut CPU),
     {
+        let mut executed: u64 = 0;
         loop {
-            if self.bus.poll_nmi_interrupt() {
-                self.nmi_interrupt();
+            if self.bus.take_break_request() {
+                return Ok(ExitReason::Break);
             }
-
+            if let Some(budget) = self.instruction_budget.filter(|budget| executed >= *budget) {
+                return Err(CPUError::BudgetExceeded(budget));
+            }
+            executed += 1;
+            self.record_rewind_state();
+            self.poll_interrupts()?;
             callback(self);
-            let instruction = self.next_instruction()?;
-            let passed_cycles = match instruction.opcode {
-                OpCode::ADC => self.adc(&instruction),
-                OpCode::AND => self.and(&instruction),
-                OpCode::ASL => self.asl(&instruction),
-                OpCode::BCC => self.bcc(&instruction),
-                OpCode::BCS => self.bcs(&instruction),
-                OpCode::BEQ => self.beq(&instruction),
-                OpCode::BIT => self.bit(&instruction),
-                OpCode::BMI => self.bmi(&instruction),
-                OpCode::BNE => self.bne(&instruction),
-                OpCode::BPL => self.bpl(&instruction),
-                OpCode::BRK => self.brk(&instruction),
-                OpCode::BVC => self.bvc(&instruction),
-                OpCode::BVS => self.bvs(&instruction),
-                OpCode::CLC => self.clc(&instruction),
-                OpCode::CLD => self.cld(&instruction),
-                OpCode::CLI => self.cli(&instruction),
-                OpCode::CLV => self.clv(&instruction),
-                OpCode::CMP => self.cmp(&instruction),
-                OpCode::CPX => self.cpx(&instruction),
-                OpCode::CPY => self.cpy(&instruction),
-                OpCode::DEC => self.dec(&instruction),
-                OpCode::DEX => self.dex(&instruction),
-                OpCode::DEY => self.dey(&instruction),
-                OpCode::EOR => self.eor(&instruction),
-                OpCode::INC => self.inc(&instruction),
-                OpCode::INX => self.inx(&instruction),
-                OpCode::INY => self.iny(&instruction),
-                OpCode::JMP => self.jmp(&instruction),
-                OpCode::JSR => self.jsr(&instruction),
-                OpCode::LDA => self.lda(&instruction),
-                OpCode::LDX => self.ldx(&instruction),
-                OpCode::LDY => self.ldy(&instruction),
-                OpCode::LSR => self.lsr(&instruction),
-                OpCode::NOP => self.nop(&instruction),
-                OpCode::ORA => self.ora(&instruction),
-                OpCode::PHA => self.pha(&instruction),
-                OpCode::PHP => self.php(&instruction),
-                OpCode::PLA => self.pla(&instruction),
-                OpCode::PLP => self.plp(&instruction),
-                OpCode::ROL => self.rol(&instruction),
-                OpCode::ROR => self.ror(&instruction),
-                OpCode::RTI => self.rti(&instruction),
-                OpCode::RTS => self.rts(&instruction),
-                OpCode::SBC => self.sbc(&instruction),
-                OpCode::SEC => self.sec(&instruction),
-                OpCode::SED => self.sed(&instruction),
-                OpCode::SEI => self.sei(&instruction),
-                OpCode::STA => self.sta(&instruction),
-                OpCode::STX => self.stx(&instruction),
-                OpCode::STY => self.sty(&instruction),
-                OpCode::TAX => self.tax(&instruction),
-                OpCode::TAY => self.tay(&instruction),
-                OpCode::TSX => self.tsx(&instruction),
-                OpCode::TXA => self.txa(&instruction),
-                OpCode::TXS => self.txs(&instruction),
-                OpCode::TYA => self.tya(&instruction),
-                OpCode::AAC => self.aac(&instruction),
-                OpCode::SAX => self.sax(&instruction),
-                OpCode::ARR => self.arr(&instruction),
-                OpCode::ASR => self.asr(&instruction),
-                OpCode::ATX => self.atx(&instruction),
-                OpCode::AXA => self.axa(&instruction),
-                OpCode::AXS => self.axs(&instruction),
-                OpCode::DCP => self.dcp(&instruction),
-                OpCode::DOP => self.dop(&instruction),
-                OpCode::ISB => self.isb(&instruction),
-                OpCode::KIL => return Ok(()),
-                OpCode::LAR => self.lar(&instruction),
-                OpCode::LAX => self.lax(&instruction),
-                OpCode::RLA => self.rla(&instruction),
-                OpCode::RRA => self.rra(&instruction),
-                OpCode::SLO => self.slo(&instruction),
-                OpCode::SRE => self.sre(&instruction),
-                OpCode::SXA => self.sxa(&instruction),
-                OpCode::SYA => self.sya(&instruction),
-                OpCode::TOP => self.top(&instruction),
-                OpCode::XAA => panic!("XAA encountered. Exact behaviour is unknown."),
-                OpCode::XAS => self.xas(&instruction),
-            };
-            self.bus.tick(passed_cycles);
+            let pc = self.program_counter.get();
+            if !self.execute_instruction()? {
+                let opcode = self.bus.peek(pc);
+                return Ok(ExitReason::Jammed { opcode, pc });
+            }
+        }
+    }
+
+    // Positions execution, e.g. at the fixed entry point of a test ROM.
+    pub fn set_pc(&mut self, address: u16) {
+        self.program_counter.set(address);
+    }
+
+    pub fn pc(&self) -> u16 {
+        self.program_counter.get()
+    }
+
+    // Executes a single instruction, servicing a pending interrupt first.
+    // Returns false once the CPU halted on KIL.
+    pub fn step(&mut self) -> Result<bool, CPUError> {
+        self.record_rewind_state();
+        self.poll_interrupts()?;
+        self.execute_instruction()
+    }
+
+    pub fn step_into(&mut self) -> Result<bool, CPUError> {
+        self.step()
+    }
+
+    // Like `step`, but a JSR is run until the matching RTS returns to the next
+    // instruction. Nested calls are told apart by the stack pointer. Stops
+    // early when the CPU halts or reaches a breakpoint.
+    pub fn step_over(&mut self) -> Result<bool, CPUError> {
+        let program_counter = self.program_counter.get();
+        if self.bus.peek(program_counter) != Self::JSR_OPCODE {
+            return self.step();
+        }
+
+        let return_address = program_counter.wrapping_add(3);
+        let stack_pointer = self.stack.get_pointer();
+        if !self.step()? {
+            return Ok(false);
+        }
+        while self.program_counter.get() != return_address
+            || self.stack.get_pointer() != stack_pointer
+        {
+            if self.breakpoints.contains(&self.program_counter.get()) {
+                break;
+            }
+            if !self.step()? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    pub fn add_breakpoint(&mut self, address: u16) {
+        if !self.breakpoints.contains(&address) {
+            self.breakpoints.push(address);
+        }
+    }
+
+    pub fn remove_breakpoint(&mut self, address: u16) {
+        self.breakpoints.retain(|breakpoint| *breakpoint != address);
+    }
+
+    fn poll_interrupts(&mut self) -> Result<(), StackError> {
+        if self.bus.poll_nmi_interrupt() {
+            self.bus.ppu.record_event(EventKind::Nmi);
+            self.interrupt(Self::NMI_INTERRUPT_VECTOR)?;
+        } else if self.bus.poll_irq() && !self.status.is_interrupt_disable_flag_set() {
+            self.bus.ppu.record_event(EventKind::Irq);
+            self.interrupt(Self::IRQ_INTERRUPT_VECTOR)?;
+        }
+        Ok(())
+    }
+
+    fn execute_instruction(&mut self) -> Result<bool, CPUError> {
+        self.bus.take_access_cycles();
+        let instruction = match self.next_instruction() {
+            Ok(instruction) => instruction,
+            Err(_) if self.unknown_opcode_policy == UnknownOpCodePolicy::TreatAsNop => {
+                &Self::UNKNOWN_OPCODE_NOP
+            }
+            Err(error) => return Err(error.into()),
+        };
+        let passed_cycles = match instruction.opcode {
+            OpCode::ADC => self.adc(&instruction)?,
+            OpCode::AND => self.and(&instruction)?,
+            OpCode::ASL => self.asl(&instruction)?,
+            OpCode::BCC => self.bcc(&instruction)?,
+            OpCode::BCS => self.bcs(&instruction)?,
+            OpCode::BEQ => self.beq(&instruction)?,
+            OpCode::BIT => self.bit(&instruction)?,
+            OpCode::BMI => self.bmi(&instruction)?,
+            OpCode::BNE => self.bne(&instruction)?,
+            OpCode::BPL => self.bpl(&instruction)?,
+            OpCode::BRK => self.brk(&instruction)?,
+            OpCode::BVC => self.bvc(&instruction)?,
+            OpCode::BVS => self.bvs(&instruction)?,
+            OpCode::CLC => self.clc(&instruction),
+            OpCode::CLD => self.cld(&instruction),
+            OpCode::CLI => self.cli(&instruction),
+            OpCode::CLV => self.clv(&instruction),
+            OpCode::CMP => self.cmp(&instruction)?,
+            OpCode::CPX => self.cpx(&instruction)?,
+            OpCode::CPY => self.cpy(&instruction)?,
+            OpCode::DEC => self.dec(&instruction)?,
+            OpCode::DEX => self.dex(&instruction),
+            OpCode::DEY => self.dey(&instruction),
+            OpCode::EOR => self.eor(&instruction)?,
+            OpCode::INC => self.inc(&instruction)?,
+            OpCode::INX => self.inx(&instruction),
+            OpCode::INY => self.iny(&instruction),
+            OpCode::JMP => self.jmp(&instruction)?,
+            OpCode::JSR => self.jsr(&instruction)?,
+            OpCode::LDA => self.lda(&instruction)?,
+            OpCode::LDX => self.ldx(&instruction)?,
+            OpCode::LDY => self.ldy(&instruction)?,
+            OpCode::LSR => self.lsr(&instruction)?,
+            OpCode::NOP => self.nop(&instruction),
+            OpCode::ORA => self.ora(&instruction)?,
+            OpCode::PHA => self.pha(&instruction)?,
+            OpCode::PHP => self.php(&instruction)?,
+            OpCode::PLA => self.pla(&instruction)?,
+            OpCode::PLP => self.plp(&instruction)?,
+            OpCode::ROL => self.rol(&instruction)?,
+            OpCode::ROR => self.ror(&instruction)?,
+            OpCode::RTI => self.rti(&instruction)?,
+            OpCode::RTS => self.rts(&instruction)?,
+            OpCode::SBC => self.sbc(&instruction)?,
+            OpCode::SEC => self.sec(&instruction),
+            OpCode::SED => self.sed(&instruction),
+            OpCode::SEI => self.sei(&instruction),
+            OpCode::STA => self.sta(&instruction)?,
+            OpCode::STX => self.stx(&instruction)?,
+            OpCode::STY => self.sty(&instruction)?,
+            OpCode::TAX => self.tax(&instruction),
+            OpCode::TAY => self.tay(&instruction),
+            OpCode::TSX => self.tsx(&instruction),
+            OpCode::TXA => self.txa(&instruction),
+            OpCode::TXS => self.txs(&instruction),
+            OpCode::TYA => self.tya(&instruction),
+            OpCode::AAC => self.aac(&instruction)?,
+            OpCode::SAX => self.sax(&instruction)?,
+            OpCode::ARR => self.arr(&instruction)?,
+            OpCode::ASR => self.asr(&instruction)?,
+            OpCode::ATX => self.atx(&instruction)?,
+            OpCode::AXA => self.axa(&instruction)?,
+            OpCode::AXS => self.axs(&instruction)?,
+            OpCode::DCP => self.dcp(&instruction)?,
+            OpCode::DOP => self.dop(&instruction)?,
+            OpCode::ISB => self.isb(&instruction)?,
+            OpCode::KIL => return Ok(false),
+            OpCode::LAR => self.lar(&instruction)?,
+            OpCode::LAX => self.lax(&instruction)?,
+            OpCode::RLA => self.rla(&instruction)?,
+            OpCode::RRA => self.rra(&instruction)?,
+            OpCode::SLO => self.slo(&instruction)?,
+            OpCode::SRE => self.sre(&instruction)?,
+            OpCode::SXA => self.sxa(&instruction)?,
+            OpCode::SYA => self.sya(&instruction)?,
+            OpCode::TOP => self.top(&instruction)?,
+            OpCode::XAA => self.xaa(&instruction)?,
+            OpCode::XAS => self.xas(&instruction)?,
+        };
+        let access_cycles = self.bus.take_access_cycles();
+        self.bus.tick(passed_cycles.saturating_sub(access_cycles));
+        Ok(true)
+    }
+
+    // Runs at the pace of the given clock instead of as fast as possible
+    #[cfg(feature = "std")]
+    pub fn run_throttled<F>(
+        &mut self,
+        clock: &mut Clock,
+        mut callback: F,
+    ) -> Result<ExitReason, CPUError>
+    where
+        F: FnMut(&mut CPU),
+    {
+        self.run(|cpu| {
+            clock.pace(cpu.bus.cycles);
+            callback(cpu);
+        })
+    }
+
+    // Like `run`, but writes a nestest-format log line to `out` before every
+    // instruction. The first write error stops the run and is returned as
+    // `CPUError::Io`.
+    #[cfg(feature = "std")]
+    pub fn run_with_trace(&mut self, out: &mut impl Write) -> Result<ExitReason, CPUError> {
+        let mut write_error = None;
+        let exit_reason = self.run(|cpu| {
+            if write_error.is_some() {
+                return;
+            }
+            if let Err(error) = writeln!(out, "{}", trace(cpu)) {
+                write_error = Some(error);
+                cpu.bus.request_break();
+            }
+        })?;
+        match write_error {
+            Some(error) => Err(error.into()),
+            None => Ok(exit_reason),
         }
     }
 
@@ -135,8 +337,8 @@ impl<'bus> CPU<'bus> {
         &mut self,
         addressing_mode: &AddressingMode,
         address: u16,
-    ) -> (PageCrossed, u16) {
-        match addressing_mode {
+    ) -> Result<(PageCrossed, u16), CPUError> {
+        let operand = match addressing_mode {
             AddressingMode::Absolute => (false, self.bus.read(address)),
             AddressingMode::AbsoluteX => {
                 let absolute_address: u16 = self.bus.read(address);
@@ -175,27 +377,7 @@ impl<'bus> CPU<'bus> {
             }
             AddressingMode::Indirect => {
                 let indirect_address = self.bus.read(address);
-                let indirect_address_suffix = indirect_address as u8;
-
-                // TODO: Maybe it is better to move this logic into the bus
-                // Indirect addressing mode is used only in JMP instruction. But an original 6502
-                // has does not correctly fetch the target address if the indirect vector falls on
-                // a page boundary. This code fixes it.
-                // Details: https://www.nesdev.org/obelisk-6502-guide/reference.html#JMP
-                if (indirect_address_suffix & 0xFF) == 0 {
-                    (false, self.bus.read(indirect_address))
-                } else {
-                    (
-                        false,
-                        u16::from_le_bytes([
-                            self.bus.read(indirect_address),
-                            self.bus.read(u16::from_be_bytes([
-                                (indirect_address >> 8) as u8,
-                                indirect_address_suffix.wrapping_add(1),
-                            ])),
-                        ]),
-                    )
-                }
+                (false, resolve_indirect_jmp(&mut self.bus, indirect_address))
             }
             AddressingMode::IndirectIndexedY => {
                 let indirect_address: u8 = self.bus.read(address);
@@ -208,10 +390,8 @@ impl<'bus> CPU<'bus> {
                         self.bus.read(indirect_address.wrapping_add(1) as u16),
                     ])
                 };
-                (
-                    false,
-                    real_address.wrapping_add(self.register_y.get() as u16),
-                )
+                let real_address_y = real_address.wrapping_add(self.register_y.get() as u16);
+                ((real_address >> 8) != (real_address_y >> 8), real_address_y)
             }
             AddressingMode::ZeroPage => (
                 false,
@@ -227,27 +407,71 @@ impl<'bus> CPU<'bus> {
                 BusOperation::<u8>::read(&mut self.bus, address).wrapping_add(self.register_y.get())
                     as u16,
             ),
-            // TODO: Add error instead of panic
             AddressingMode::Accumulator | AddressingMode::Implied => {
-                panic!("Mode {addressing_mode:?} can't have address")
+                return Err(CPUError::NoAddressForMode(*addressing_mode));
             }
-        }
+        };
+        Ok(operand)
     }
 
-    pub fn reset_interrupt(&mut self) {
-        self.program_counter
-            .set(self.bus.read(Self::RESET_INTERRUPT_VECTOR));
+    // Cold start: registers and RAM are cleared before jumping to the reset vector.
+    // https://www.nesdev.org/wiki/CPU_power_up_state
+    pub fn power_on(&mut self) {
         self.accumulator.set(0);
         self.register_x.set(0);
         self.register_y.set(0);
         self.status.reset();
         self.stack.reset();
+        self.bus.power_on();
+        self.reset_interrupt();
+    }
+
+    // Inserts another cartridge and powers the console on again. Rewind
+    // history of the previous cartridge is discarded. A cartridge with an
+    // unsupported mapper leaves the console untouched.
+    pub fn load_rom(&mut self, rom: Rom) -> Result<(), UnsupportedMapper> {
+        self.bus.load_rom(rom)?;
+        if let Some(rewind) = self.rewind.as_mut() {
+            rewind.clear();
+        }
+        self.power_on();
+        Ok(())
+    }
+
+    // Warm reset keeps A, X, Y and RAM untouched. The reset sequence performs
+    // three pushes with writes suppressed, so only SP is decremented by 3.
+    pub fn reset(&mut self) {
+        self.reset_state();
+        self.reset_interrupt();
     }
 
-    fn adc(&mut self, instruction: &Instruction) -> u8 {
-        let (page_crossed, value) = self.get_value(&instruction.mode);
+    // Same as `reset`, but starts at `pc` instead of the $FFFC vector, for
+    // flat test programs without vectors.
+    pub fn reset_to(&mut self, pc: u16) {
+        self.reset_state();
+        self.program_counter.set(pc);
+        self.bus.tick(Self::RESET_CYCLES);
+    }
+
+    fn reset_state(&mut self) {
+        self.status.set_interrupt_disable_flag_to(true);
+        self.stack
+            .set_pointer(self.stack.get_pointer().wrapping_sub(3));
+        self.bus.reset();
+    }
+
+    fn reset_interrupt(&mut self) {
+        self.program_counter
+            .set(self.bus.read(Self::RESET_INTERRUPT_VECTOR));
+        // Reset sequence takes 7 cycles, 2 of them are the vector read
+        let access_cycles = self.bus.take_access_cycles();
+        self.bus.tick(Self::RESET_CYCLES - access_cycles);
+    }
+
+    fn adc(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (page_crossed, value) = self.get_value(&instruction.mode)?;
         self.adc_operation(value);
-        instruction.cycles + page_crossed as u8
+        Ok(instruction.cycles + page_crossed as u8)
     }
 
     // Moved ADC instruction's logic to separate function, because the same logic
@@ -265,16 +489,16 @@ impl<'bus> CPU<'bus> {
         self.accumulator.set(result);
     }
 
-    fn and(&mut self, instruction: &Instruction) -> u8 {
-        let (page_crossed, mut value) = self.get_value(&instruction.mode);
+    fn and(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (page_crossed, mut value) = self.get_value(&instruction.mode)?;
         value &= self.accumulator.get();
         self.accumulator.set(value);
         self.status.set_zero_flag(value);
         self.status.set_negative_flag(value);
-        instruction.cycles + page_crossed as u8
+        Ok(instruction.cycles + page_crossed as u8)
     }
 
-    fn asl(&mut self, instruction: &Instruction) -> u8 {
+    fn asl(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
         let addressing_mode = &instruction.mode;
         let (old_value, shifted_value) = match addressing_mode {
             AddressingMode::Accumulator => {
@@ -284,113 +508,119 @@ impl<'bus> CPU<'bus> {
                 (old_value, shifted_value)
             }
             _ => {
-                let (_, old_value_address) = self.read_operand_address(addressing_mode);
+                let (_, old_value_address) = self.read_operand_address(addressing_mode)?;
                 let old_value: u8 = self.bus.read(old_value_address);
                 let shifted_value = old_value << 1;
-                self.bus.write(old_value_address, shifted_value);
+                self.write_modified(old_value_address, old_value, shifted_value);
                 (old_value, shifted_value)
             }
         };
         self.status.set_carry_flag_to(old_value & 0b1000_0000 != 0);
         self.status.set_negative_flag(shifted_value);
         self.status.set_zero_flag(shifted_value);
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn bcc(&mut self, instruction: &Instruction) -> u8 {
-        let (_, offset) = self.get_value(&instruction.mode);
+    fn bcc(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, offset) = self.get_value(&instruction.mode)?;
         if !self.status.is_carry_flag_set() {
             let page_crossed = self.program_counter.move_with_offset(offset);
-            instruction.cycles + if page_crossed { 2 } else { 1 }
+            Ok(instruction.cycles + if page_crossed { 2 } else { 1 })
         } else {
-            instruction.cycles
+            Ok(instruction.cycles)
         }
     }
 
-    fn bcs(&mut self, instruction: &Instruction) -> u8 {
-        let (_, offset) = self.get_value(&instruction.mode);
+    fn bcs(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, offset) = self.get_value(&instruction.mode)?;
         if self.status.is_carry_flag_set() {
             let page_crossed = self.program_counter.move_with_offset(offset);
-            instruction.cycles + if page_crossed { 2 } else { 1 }
+            Ok(instruction.cycles + if page_crossed { 2 } else { 1 })
         } else {
-            instruction.cycles
+            Ok(instruction.cycles)
         }
     }
 
-    fn beq(&mut self, instruction: &Instruction) -> u8 {
-        let (_, offset) = self.get_value(&instruction.mode);
+    fn beq(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, offset) = self.get_value(&instruction.mode)?;
         if self.status.is_zero_flag_set() {
             let page_crossed = self.program_counter.move_with_offset(offset);
-            instruction.cycles + if page_crossed { 2 } else { 1 }
+            Ok(instruction.cycles + if page_crossed { 2 } else { 1 })
         } else {
-            instruction.cycles
+            Ok(instruction.cycles)
         }
     }
 
-    fn bit(&mut self, instruction: &Instruction) -> u8 {
-        let (_, value) = self.get_value(&instruction.mode);
+    fn bit(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, value) = self.get_value(&instruction.mode)?;
         self.status.set_zero_flag(value & self.accumulator.get());
         self.status.set_negative_flag(value);
         self.status.set_overflow_flag_to(value & 0b0100_0000 != 0);
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn bmi(&mut self, instruction: &Instruction) -> u8 {
-        let (_, offset) = self.get_value(&instruction.mode);
+    fn bmi(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, offset) = self.get_value(&instruction.mode)?;
         if self.status.is_negative_flag_set() {
             let page_crossed = self.program_counter.move_with_offset(offset);
-            instruction.cycles + if page_crossed { 2 } else { 1 }
+            Ok(instruction.cycles + if page_crossed { 2 } else { 1 })
         } else {
-            instruction.cycles
+            Ok(instruction.cycles)
         }
     }
 
-    fn bne(&mut self, instruction: &Instruction) -> u8 {
-        let (_, offset) = self.get_value(&instruction.mode);
+    fn bne(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, offset) = self.get_value(&instruction.mode)?;
         if !self.status.is_zero_flag_set() {
             let page_crossed = self.program_counter.move_with_offset(offset);
-            instruction.cycles + if page_crossed { 2 } else { 1 }
+            Ok(instruction.cycles + if page_crossed { 2 } else { 1 })
         } else {
-            instruction.cycles
+            Ok(instruction.cycles)
         }
     }
 
-    fn bpl(&mut self, instruction: &Instruction) -> u8 {
-        let (_, offset) = self.get_value(&instruction.mode);
+    fn bpl(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, offset) = self.get_value(&instruction.mode)?;
         if !self.status.is_negative_flag_set() {
             let page_crossed = self.program_counter.move_with_offset(offset);
-            instruction.cycles + if page_crossed { 2 } else { 1 }
+            Ok(instruction.cycles + if page_crossed { 2 } else { 1 })
         } else {
-            instruction.cycles
+            Ok(instruction.cycles)
         }
     }
 
-    fn brk(&mut self, instruction: &Instruction) -> u8 {
-        self.stack.push(self.program_counter.get(), &mut self.bus);
-        self.stack.push(self.status.get(), &mut self.bus);
+    // The byte after BRK is skipped, and the pushed status has B set so the
+    // handler can tell it apart from an IRQ
+    fn brk(&mut self, instruction: &Instruction) -> Result<u8, StackError> {
+        let mut status = self.status.clone();
+        status.insert(ProcessorStatus::B_FLAG | ProcessorStatus::B_FLAG_2);
+
+        self.stack
+            .push(self.program_counter.get().wrapping_add(1), &mut self.bus)?;
+        self.stack.push(status.bits(), &mut self.bus)?;
         self.program_counter
             .set(self.bus.read(Self::IRQ_INTERRUPT_VECTOR));
         self.status.set_interrupt_disable_flag_to(true);
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn bvc(&mut self, instruction: &Instruction) -> u8 {
-        let (_, offset) = self.get_value(&instruction.mode);
+    fn bvc(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, offset) = self.get_value(&instruction.mode)?;
         if !self.status.is_overflow_flag_set() {
             let page_crossed = self.program_counter.move_with_offset(offset);
-            instruction.cycles + if page_crossed { 2 } else { 1 }
+            Ok(instruction.cycles + if page_crossed { 2 } else { 1 })
         } else {
-            instruction.cycles
+            Ok(instruction.cycles)
         }
     }
 
-    fn bvs(&mut self, instruction: &Instruction) -> u8 {
-        let (_, offset) = self.get_value(&instruction.mode);
+    fn bvs(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, offset) = self.get_value(&instruction.mode)?;
         if self.status.is_overflow_flag_set() {
             let page_crossed = self.program_counter.move_with_offset(offset);
-            instruction.cycles + if page_crossed { 2 } else { 1 }
+            Ok(instruction.cycles + if page_crossed { 2 } else { 1 })
         } else {
-            instruction.cycles
+            Ok(instruction.cycles)
         }
     }
 
@@ -414,43 +644,44 @@ impl<'bus> CPU<'bus> {
         instruction.cycles
     }
 
-    fn cmp(&mut self, instruction: &Instruction) -> u8 {
-        let (page_crossed, value) = self.get_value(&instruction.mode);
+    fn cmp(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (page_crossed, value) = self.get_value(&instruction.mode)?;
         let result = self.accumulator.sub(value);
         self.status
             .set_carry_flag_to(self.accumulator.get() >= value);
         self.status.set_zero_flag(result);
         self.status.set_negative_flag(result);
-        instruction.cycles + page_crossed as u8
+        Ok(instruction.cycles + page_crossed as u8)
     }
 
-    fn cpx(&mut self, instruction: &Instruction) -> u8 {
-        let (_, value) = self.get_value(&instruction.mode);
+    fn cpx(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, value) = self.get_value(&instruction.mode)?;
         let result = self.register_x.sub(value);
         self.status
             .set_carry_flag_to(self.register_x.get() >= value);
         self.status.set_zero_flag(result);
         self.status.set_negative_flag(result);
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn cpy(&mut self, instruction: &Instruction) -> u8 {
-        let (_, value) = self.get_value(&instruction.mode);
+    fn cpy(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, value) = self.get_value(&instruction.mode)?;
         let result = self.register_y.sub(value);
         self.status
             .set_carry_flag_to(self.register_y.get() >= value);
         self.status.set_zero_flag(result);
         self.status.set_negative_flag(result);
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn dec(&mut self, instruction: &Instruction) -> u8 {
-        let (_, address) = self.read_operand_address(&instruction.mode);
-        let value = BusOperation::<u8>::read(&mut self.bus, address).wrapping_sub(1);
-        self.bus.write(address, value);
+    fn dec(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, address) = self.read_operand_address(&instruction.mode)?;
+        let old_value: u8 = self.bus.read(address);
+        let value = old_value.wrapping_sub(1);
+        self.write_modified(address, old_value, value);
         self.status.set_zero_flag(value);
         self.status.set_negative_flag(value);
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
     fn dex(&mut self, instruction: &Instruction) -> u8 {
@@ -467,22 +698,23 @@ impl<'bus> CPU<'bus> {
         instruction.cycles
     }
 
-    fn eor(&mut self, instruction: &Instruction) -> u8 {
-        let (page_crossed, mut value) = self.get_value(&instruction.mode);
+    fn eor(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (page_crossed, mut value) = self.get_value(&instruction.mode)?;
         value ^= self.accumulator.get();
         self.accumulator.set(value);
         self.status.set_zero_flag(value);
         self.status.set_negative_flag(value);
-        instruction.cycles + page_crossed as u8
+        Ok(instruction.cycles + page_crossed as u8)
     }
 
-    fn inc(&mut self, instruction: &Instruction) -> u8 {
-        let (_, address) = self.read_operand_address(&instruction.mode);
-        let value = BusOperation::<u8>::read(&mut self.bus, address).wrapping_add(1);
-        self.bus.write(address, value);
+    fn inc(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, address) = self.read_operand_address(&instruction.mode)?;
+        let old_value: u8 = self.bus.read(address);
+        let value = old_value.wrapping_add(1);
+        self.write_modified(address, old_value, value);
         self.status.set_zero_flag(value);
         self.status.set_negative_flag(value);
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
     fn inx(&mut self, instruction: &Instruction) -> u8 {
@@ -499,45 +731,45 @@ impl<'bus> CPU<'bus> {
         instruction.cycles
     }
 
-    fn jmp(&mut self, instruction: &Instruction) -> u8 {
-        let (_, address) = self.read_operand_address(&instruction.mode);
+    fn jmp(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, address) = self.read_operand_address(&instruction.mode)?;
         self.program_counter.set(address);
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn jsr(&mut self, instruction: &Instruction) -> u8 {
-        let (_, address) = self.read_operand_address(&instruction.mode);
+    fn jsr(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, address) = self.read_operand_address(&instruction.mode)?;
         self.stack
-            .push(self.program_counter.get().wrapping_sub(1), &mut self.bus);
+            .push(self.program_counter.get().wrapping_sub(1), &mut self.bus)?;
         self.program_counter.set(address);
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn lda(&mut self, instruction: &Instruction) -> u8 {
-        let (page_crossed, value) = self.get_value(&instruction.mode);
+    fn lda(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (page_crossed, value) = self.get_value(&instruction.mode)?;
         self.accumulator.set(value);
         self.status.set_zero_flag(value);
         self.status.set_negative_flag(value);
-        instruction.cycles + page_crossed as u8
+        Ok(instruction.cycles + page_crossed as u8)
     }
 
-    fn ldx(&mut self, instruction: &Instruction) -> u8 {
-        let (page_crossed, value) = self.get_value(&instruction.mode);
+    fn ldx(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (page_crossed, value) = self.get_value(&instruction.mode)?;
         self.register_x.set(value);
         self.status.set_zero_flag(value);
         self.status.set_negative_flag(value);
-        instruction.cycles + page_crossed as u8
+        Ok(instruction.cycles + page_crossed as u8)
     }
 
-    fn ldy(&mut self, instruction: &Instruction) -> u8 {
-        let (page_crossed, value) = self.get_value(&instruction.mode);
+    fn ldy(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (page_crossed, value) = self.get_value(&instruction.mode)?;
         self.register_y.set(value);
         self.status.set_zero_flag(value);
         self.status.set_negative_flag(value);
-        instruction.cycles + page_crossed as u8
+        Ok(instruction.cycles + page_crossed as u8)
     }
 
-    fn lsr(&mut self, instruction: &Instruction) -> u8 {
+    fn lsr(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
         let (old_value, shifted_value) = match instruction.mode {
             AddressingMode::Accumulator => {
                 let old_value = self.accumulator.get();
@@ -546,59 +778,59 @@ impl<'bus> CPU<'bus> {
                 (old_value, shifted_value)
             }
             _ => {
-                let (_, old_value_address) = self.read_operand_address(&instruction.mode);
+                let (_, old_value_address) = self.read_operand_address(&instruction.mode)?;
                 let old_value: u8 = self.bus.read(old_value_address);
                 let shifted_value = old_value >> 1;
-                self.bus.write(old_value_address, shifted_value);
+                self.write_modified(old_value_address, old_value, shifted_value);
                 (old_value, shifted_value)
             }
         };
         self.status.set_carry_flag_to(old_value & 1 != 0);
         self.status.set_negative_flag(0);
         self.status.set_zero_flag(shifted_value);
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
+    // Implied NOPs have no operand to fetch
     fn nop(&mut self, instruction: &Instruction) -> u8 {
-        let (page_crossed, _) = self.get_value(&instruction.mode);
-        instruction.cycles + page_crossed as u8
+        instruction.cycles
     }
 
-    fn ora(&mut self, instruction: &Instruction) -> u8 {
-        let (page_crossed, mut value) = self.get_value(&instruction.mode);
+    fn ora(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (page_crossed, mut value) = self.get_value(&instruction.mode)?;
         value |= self.accumulator.get();
         self.accumulator.set(value);
         self.status.set_zero_flag(value);
         self.status.set_negative_flag(value);
-        instruction.cycles + page_crossed as u8
+        Ok(instruction.cycles + page_crossed as u8)
     }
 
-    fn pha(&mut self, instruction: &Instruction) -> u8 {
-        self.stack.push(self.accumulator.get(), &mut self.bus);
-        instruction.cycles
+    fn pha(&mut self, instruction: &Instruction) -> Result<u8, StackError> {
+        self.stack.push(self.accumulator.get(), &mut self.bus)?;
+        Ok(instruction.cycles)
     }
 
-    fn php(&mut self, instruction: &Instruction) -> u8 {
+    fn php(&mut self, instruction: &Instruction) -> Result<u8, StackError> {
         let status = self.status.get() | 0b0001_0000;
-        self.stack.push(status, &mut self.bus);
-        instruction.cycles
+        self.stack.push(status, &mut self.bus)?;
+        Ok(instruction.cycles)
     }
 
-    fn pla(&mut self, instruction: &Instruction) -> u8 {
-        let value = self.stack.pull(&mut self.bus);
+    fn pla(&mut self, instruction: &Instruction) -> Result<u8, StackError> {
+        let value = self.stack.pull(&mut self.bus)?;
         self.accumulator.set(value);
         self.status.set_zero_flag(value);
         self.status.set_negative_flag(value);
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn plp(&mut self, instruction: &Instruction) -> u8 {
-        let value: u8 = self.stack.pull(&mut self.bus);
+    fn plp(&mut self, instruction: &Instruction) -> Result<u8, StackError> {
+        let value: u8 = self.stack.pull(&mut self.bus)?;
         self.status.update(value);
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn rol(&mut self, instruction: &Instruction) -> u8 {
+    fn rol(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
         let (old_value, shifted_value) = match instruction.mode {
             AddressingMode::Accumulator => {
                 let old_value = self.accumulator.get();
@@ -607,20 +839,20 @@ impl<'bus> CPU<'bus> {
                 (old_value, shifted_value)
             }
             _ => {
-                let (_, old_value_address) = self.read_operand_address(&instruction.mode);
+                let (_, old_value_address) = self.read_operand_address(&instruction.mode)?;
                 let old_value: u8 = self.bus.read(old_value_address);
                 let shifted_value = (old_value << 1).wrapping_add(self.status.get_carry_flag());
-                self.bus.write(old_value_address, shifted_value);
+                self.write_modified(old_value_address, old_value, shifted_value);
                 (old_value, shifted_value)
             }
         };
         self.status.set_carry_flag_to(old_value & 0b1000_0000 != 0);
         self.status.set_negative_flag(shifted_value);
         self.status.set_zero_flag(shifted_value);
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn ror(&mut self, instruction: &Instruction) -> u8 {
+    fn ror(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
         let (old_value, shifted_value) = match instruction.mode {
             AddressingMode::Accumulator => {
                 let old_value = self.accumulator.get();
@@ -630,38 +862,38 @@ impl<'bus> CPU<'bus> {
                 (old_value, shifted_value)
             }
             _ => {
-                let (_, old_value_address) = self.read_operand_address(&instruction.mode);
+                let (_, old_value_address) = self.read_operand_address(&instruction.mode)?;
                 let old_value: u8 = self.bus.read(old_value_address);
                 let shifted_value =
                     (old_value >> 1).wrapping_add(self.status.get_carry_flag() << 7);
-                self.bus.write(old_value_address, shifted_value);
+                self.write_modified(old_value_address, old_value, shifted_value);
                 (old_value, shifted_value)
             }
         };
         self.status.set_carry_flag_to(old_value & 1 != 0);
         self.status.set_negative_flag(shifted_value);
         self.status.set_zero_flag(shifted_value);
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn rti(&mut self, instruction: &Instruction) -> u8 {
-        let status = self.stack.pull(&mut self.bus);
-        let program_counter = self.stack.pull(&mut self.bus);
+    fn rti(&mut self, instruction: &Instruction) -> Result<u8, StackError> {
+        let status = self.stack.pull(&mut self.bus)?;
+        let program_counter = self.stack.pull(&mut self.bus)?;
         self.status.update(status);
         self.program_counter.set(program_counter);
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn rts(&mut self, instruction: &Instruction) -> u8 {
-        let program_counter: u16 = self.stack.pull(&mut self.bus);
+    fn rts(&mut self, instruction: &Instruction) -> Result<u8, StackError> {
+        let program_counter: u16 = self.stack.pull(&mut self.bus)?;
         self.program_counter.set(program_counter.wrapping_add(1));
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn sbc(&mut self, instruction: &Instruction) -> u8 {
-        let (page_crossed, value) = self.get_value(&instruction.mode);
+    fn sbc(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (page_crossed, value) = self.get_value(&instruction.mode)?;
         self.adc_operation(!value);
-        instruction.cycles + page_crossed as u8
+        Ok(instruction.cycles + page_crossed as u8)
     }
 
     fn sec(&mut self, instruction: &Instruction) -> u8 {
@@ -679,22 +911,22 @@ impl<'bus> CPU<'bus> {
         instruction.cycles
     }
 
-    fn sta(&mut self, instruction: &Instruction) -> u8 {
-        let (_, address) = self.read_operand_address(&instruction.mode);
+    fn sta(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, address) = self.read_operand_address(&instruction.mode)?;
         self.bus.write(address, self.accumulator.get());
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn stx(&mut self, instruction: &Instruction) -> u8 {
-        let (_, address) = self.read_operand_address(&instruction.mode);
+    fn stx(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, address) = self.read_operand_address(&instruction.mode)?;
         self.bus.write(address, self.register_x.get());
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn sty(&mut self, instruction: &Instruction) -> u8 {
-        let (_, address) = self.read_operand_address(&instruction.mode);
+    fn sty(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, address) = self.read_operand_address(&instruction.mode)?;
         self.bus.write(address, self.register_y.get());
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
     fn tax(&mut self, instruction: &Instruction) -> u8 {
@@ -737,25 +969,25 @@ impl<'bus> CPU<'bus> {
         instruction.cycles
     }
 
-    fn aac(&mut self, instruction: &Instruction) -> u8 {
-        let (_, mut value) = self.get_value(&instruction.mode);
+    fn aac(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, mut value) = self.get_value(&instruction.mode)?;
         value &= self.accumulator.get();
         self.accumulator.set(value);
         self.status.set_zero_flag(value);
         self.status.set_negative_flag(value);
         self.status.set_carry_flag_to(value & 0b1000_0000 != 0);
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn sax(&mut self, instruction: &Instruction) -> u8 {
-        let (_, address) = self.read_operand_address(&instruction.mode);
+    fn sax(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, address) = self.read_operand_address(&instruction.mode)?;
         self.bus
             .write(address, self.register_x.get() & self.accumulator.get());
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn arr(&mut self, instruction: &Instruction) -> u8 {
-        let (_, mut value) = self.get_value(&instruction.mode);
+    fn arr(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, mut value) = self.get_value(&instruction.mode)?;
         value = (value & self.accumulator.get()) >> 1;
         self.accumulator.set(value);
         self.status.set_zero_flag(value);
@@ -780,52 +1012,49 @@ impl<'bus> CPU<'bus> {
             }
         };
 
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn asr(&mut self, instruction: &Instruction) -> u8 {
-        let (_, mut value) = self.get_value(&instruction.mode);
+    fn asr(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, mut value) = self.get_value(&instruction.mode)?;
         value &= self.accumulator.get();
         let shifted_value = value >> 1;
         self.accumulator.set(shifted_value);
         self.status.set_zero_flag(shifted_value);
         self.status.set_negative_flag(shifted_value);
         self.status.set_carry_flag_to(value & 0b0000_0001 != 0);
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn atx(&mut self, instruction: &Instruction) -> u8 {
-        let (_, mut value) = self.get_value(&instruction.mode);
+    fn atx(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, mut value) = self.get_value(&instruction.mode)?;
         value &= self.accumulator.get();
         self.register_x.set(value);
         self.accumulator.set(value);
         self.status.set_zero_flag(value);
         self.status.set_negative_flag(value);
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn axa(&mut self, instruction: &Instruction) -> u8 {
-        let (_, address) = self.read_operand_address(&instruction.mode);
-        self.bus.write(
-            address,
-            self.register_x.get() & self.accumulator.get() & address.to_be_bytes()[0],
-        );
-        instruction.cycles
+    fn axa(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let value = self.register_x.get() & self.accumulator.get();
+        self.store_and_high_byte(instruction, value)
     }
 
-    fn axs(&mut self, instruction: &Instruction) -> u8 {
-        let (_, mut value) = self.get_value(&instruction.mode);
+    fn axs(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, mut value) = self.get_value(&instruction.mode)?;
         value = (self.accumulator.get() & self.register_x.get()).wrapping_sub(value);
         self.register_x.set(value);
         self.status.set_zero_flag(value);
         self.status.set_negative_flag(value);
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn dcp(&mut self, instruction: &Instruction) -> u8 {
-        let (_, address) = self.read_operand_address(&instruction.mode);
-        let value = BusOperation::<u8>::read(&mut self.bus, address).wrapping_sub(1);
-        self.bus.write(address, value);
+    fn dcp(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, address) = self.read_operand_address(&instruction.mode)?;
+        let old_value: u8 = self.bus.read(address);
+        let value = old_value.wrapping_sub(1);
+        self.write_modified(address, old_value, value);
 
         let result = self.accumulator.sub(value);
         self.status
@@ -833,189 +1062,265 @@ impl<'bus> CPU<'bus> {
         self.status.set_zero_flag(result);
         self.status.set_negative_flag(result);
 
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn dop(&mut self, instruction: &Instruction) -> u8 {
-        let (page_crossed, _) = self.read_operand_address(&instruction.mode);
-        self.program_counter.inc();
-        instruction.cycles + page_crossed as u8
+    // Reads the one byte operand (or the zero page value it points to) and
+    // discards it. Zero page indexing never crosses a page.
+    fn dop(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        self.get_value(&instruction.mode)?;
+        Ok(instruction.cycles)
     }
 
-    fn isb(&mut self, instruction: &Instruction) -> u8 {
-        let (_, address) = self.read_operand_address(&instruction.mode);
-        let value = BusOperation::<u8>::read(&mut self.bus, address).wrapping_add(1);
-        self.bus.write(address, value);
+    fn isb(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, address) = self.read_operand_address(&instruction.mode)?;
+        let old_value: u8 = self.bus.read(address);
+        let value = old_value.wrapping_add(1);
+        self.write_modified(address, old_value, value);
         self.adc_operation(!value);
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn lar(&mut self, instruction: &Instruction) -> u8 {
-        let (page_crossed, mut value) = self.get_value(&instruction.mode);
+    fn lar(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (page_crossed, mut value) = self.get_value(&instruction.mode)?;
         value &= self.stack.get_pointer();
         self.register_x.set(value);
         self.accumulator.set(value);
         self.stack.set_pointer(value);
         self.status.set_zero_flag(value);
         self.status.set_negative_flag(value);
-        instruction.cycles + page_crossed as u8
+        Ok(instruction.cycles + page_crossed as u8)
     }
 
-    fn lax(&mut self, instruction: &Instruction) -> u8 {
-        let (page_crossed, value) = self.get_value(&instruction.mode);
+    fn lax(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (page_crossed, value) = self.get_value(&instruction.mode)?;
         self.register_x.set(value);
         self.accumulator.set(value);
         self.status.set_zero_flag(value);
         self.status.set_negative_flag(value);
-        instruction.cycles + page_crossed as u8
+        Ok(instruction.cycles + page_crossed as u8)
     }
 
-    fn rla(&mut self, instruction: &Instruction) -> u8 {
-        let (_, address) = self.read_operand_address(&instruction.mode);
-        let mut value: u8 = self.bus.read(address);
+    fn rla(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, address) = self.read_operand_address(&instruction.mode)?;
+        let old_value: u8 = self.bus.read(address);
+        let mut value = old_value;
         let carry_flag = self.status.get_carry_flag();
 
         self.status.set_carry_flag_to(value & 0b1000_0000 != 0);
         value = (value << 1).wrapping_add(carry_flag);
-        self.bus.write(address, value);
+        self.write_modified(address, old_value, value);
 
         value = value & self.accumulator.get();
         self.accumulator.set(value);
         self.status.set_zero_flag(value);
         self.status.set_negative_flag(value);
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn rra(&mut self, instruction: &Instruction) -> u8 {
-        let (_, address) = self.read_operand_address(&instruction.mode);
-        let mut value: u8 = self.bus.read(address);
+    fn rra(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, address) = self.read_operand_address(&instruction.mode)?;
+        let old_value: u8 = self.bus.read(address);
+        let mut value = old_value;
         let set_carry_flag = value & 0b0000_0001 != 0;
         value = (value >> 1).wrapping_add(self.status.get_carry_flag() << 7);
         self.status.set_carry_flag_to(set_carry_flag);
-        self.bus.write(address, value);
+        self.write_modified(address, old_value, value);
         self.adc_operation(value);
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn slo(&mut self, instruction: &Instruction) -> u8 {
-        let (_, address) = self.read_operand_address(&instruction.mode);
-        let mut value: u8 = self.bus.read(address);
+    fn slo(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, address) = self.read_operand_address(&instruction.mode)?;
+        let old_value: u8 = self.bus.read(address);
+        let mut value = old_value;
 
         self.status.set_carry_flag_to(value & 0b1000_0000 != 0);
         value <<= 1;
-        self.bus.write(address, value);
+        self.write_modified(address, old_value, value);
 
         value = value | self.accumulator.get();
         self.accumulator.set(value);
         self.status.set_zero_flag(value);
         self.status.set_negative_flag(value);
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn sre(&mut self, instruction: &Instruction) -> u8 {
-        let (_, address) = self.read_operand_address(&instruction.mode);
-        let mut value: u8 = self.bus.read(address);
+    fn sre(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, address) = self.read_operand_address(&instruction.mode)?;
+        let old_value: u8 = self.bus.read(address);
+        let mut value = old_value;
 
         self.status.set_carry_flag_to(value & 0b0000_0001 != 0);
         value >>= 1;
-        self.bus.write(address, value);
+        self.write_modified(address, old_value, value);
 
         value = value ^ self.accumulator.get();
         self.accumulator.set(value);
         self.status.set_zero_flag(value);
         self.status.set_negative_flag(value);
-        instruction.cycles
+        Ok(instruction.cycles)
     }
 
-    fn sxa(&mut self, instruction: &Instruction) -> u8 {
-        let (_, address) = self.read_operand_address(&instruction.mode);
-        let result = (self.register_x.get() & address.to_be_bytes()[0]).wrapping_add(1);
-        self.bus.write(address, result);
-        instruction.cycles
+    fn sxa(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        self.store_and_high_byte(instruction, self.register_x.get())
     }
 
-    fn sya(&mut self, instruction: &Instruction) -> u8 {
-        let (_, address) = self.read_operand_address(&instruction.mode);
-        let result = (self.register_y.get() & address.to_be_bytes()[0]).wrapping_add(1);
-        self.bus.write(address, result);
-        instruction.cycles
+    fn sya(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        self.store_and_high_byte(instruction, self.register_y.get())
     }
 
-    fn top(&mut self, instruction: &Instruction) -> u8 {
-        let (page_crossed, _) = self.read_operand_address(&instruction.mode);
-        instruction.cycles + page_crossed as u8
+    // Reads the absolute address and discards the value, only the AbsoluteX
+    // variants can cross a page.
+    fn top(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (page_crossed, _) = self.get_value(&instruction.mode)?;
+        Ok(instruction.cycles + page_crossed as u8)
     }
 
-    fn xas(&mut self, instruction: &Instruction) -> u8 {
-        let (_, address) = self.read_operand_address(&instruction.mode);
-        let result = self.register_x.get() & self.accumulator.get();
-        self.stack.set_pointer(result);
-        self.bus
-            .write(address, (result & address.to_be_bytes()[0]).wrapping_add(1));
-        instruction.cycles
+    // A = (A | magic) & X & operand
+    // https://www.nesdev.org/wiki/Visual6502wiki/6502_Opcode_8B_(XAA,_ANE)
+    fn xaa(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let (_, value) = self.get_value(&instruction.mode)?;
+        let result = (self.accumulator.get() | self.xaa_magic) & self.register_x.get() & value;
+        self.accumulator.set(result);
+        self.status.set_zero_flag(result);
+        self.status.set_negative_flag(result);
+        Ok(instruction.cycles)
+    }
+
+    fn xas(&mut self, instruction: &Instruction) -> Result<u8, CPUError> {
+        let value = self.register_x.get() & self.accumulator.get();
+        self.stack.set_pointer(value);
+        self.store_and_high_byte(instruction, value)
+    }
+
+    // AXA, SXA, SYA and XAS store `value & (H + 1)`, where H is the high byte of
+    // the base address before indexing. When indexing crosses a page, the stored
+    // value also replaces the high byte of the target address.
+    // https://www.nesdev.org/wiki/CPU_unofficial_opcodes
+    fn store_and_high_byte(
+        &mut self,
+        instruction: &Instruction,
+        value: u8,
+    ) -> Result<u8, CPUError> {
+        let (page_crossed, address) = self.read_operand_address(&instruction.mode)?;
+        let [high, low] = address.to_be_bytes();
+        let base_high = high.wrapping_sub(page_crossed as u8);
+        let result = value & base_high.wrapping_add(1);
+        let address = if page_crossed {
+            u16::from_be_bytes([result, low])
+        } else {
+            address
+        };
+        self.bus.write(address, result);
+        Ok(instruction.cycles)
     }
 
     fn next_instruction(&mut self) -> Result<&'static Instruction, UnknownOpCode> {
         let opcode = self.bus.read(self.program_counter.get());
         self.program_counter.inc();
-        OPCODES.get(&opcode).ok_or(UnknownOpCode(opcode))
+        self.opcodes.get(opcode).ok_or(UnknownOpCode(opcode))
     }
 
-    fn nmi_interrupt(&mut self) {
+    // https://www.nesdev.org/wiki/CPU_interrupts
+    fn interrupt(&mut self, vector: u16) -> Result<(), StackError> {
         let mut status = self.status.clone();
         status.set(ProcessorStatus::B_FLAG, false);
         status.set(ProcessorStatus::B_FLAG_2, true);
 
-        self.stack.push(self.program_counter.get(), &mut self.bus);
-        self.stack.push(status.bits(), &mut self.bus);
+        self.stack.push(self.program_counter.get(), &mut self.bus)?;
+        self.stack.push(status.bits(), &mut self.bus)?;
 
         self.status.set_interrupt_disable_flag_to(true);
         self.bus.tick(2);
-        self.program_counter
-            .set(self.bus.read(Self::NMI_INTERRUPT_VECTOR));
+        self.program_counter.set(self.bus.read(vector));
+        Ok(())
     }
 
-    fn read_operand_address(&mut self, addressing_mode: &AddressingMode) -> (PageCrossed, u16) {
-        let result = self.get_operand_address(addressing_mode, self.program_counter.get());
+    fn read_operand_address(
+        &mut self,
+        addressing_mode: &AddressingMode,
+    ) -> Result<(PageCrossed, u16), CPUError> {
+        let result = self.get_operand_address(addressing_mode, self.program_counter.get())?;
         self.program_counter
             .add(addressing_mode.operand_bytes() as u16);
-        result
+        Ok(result)
     }
 
-    fn get_value(&mut self, addressing_mode: &AddressingMode) -> (PageCrossed, u8) {
-        let (page_crossed, address) = self.read_operand_address(addressing_mode);
-        (page_crossed, self.bus.read(address))
+    // Read-modify-write instructions write the unmodified value back while
+    // computing the result, so registers at the address see both writes.
+    fn write_modified(&mut self, address: u16, old_value: u8, value: u8) {
+        self.bus.write(address, old_value);
+        self.bus.write(address, value);
     }
+
+    fn get_value(
+        &mut self,
+        addressing_mode: &AddressingMode,
+    ) -> Result<(PageCrossed, u8), CPUError> {
+        let (page_crossed, address) = self.read_operand_address(addressing_mode)?;
+        Ok((page_crossed, self.bus.read(address)))
+    }
+}
+
+// Indirect addressing mode is used only in JMP instruction. The original 6502
+// doesn't carry into the high byte when fetching the target, so a vector at
+// $xxFF takes its high byte from $xx00 instead of the next page.
+// Details: https://www.nesdev.org/obelisk-6502-guide/reference.html#JMP
+pub(crate) fn resolve_indirect_jmp(bus: &mut Bus, vector: u16) -> u16 {
+    let [low, high] = vector.to_le_bytes();
+    u16::from_le_bytes([
+        bus.read(vector),
+        bus.read(u16::from_le_bytes([low.wrapping_add(1), high])),
+    ])
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::bus::EmulationFlow;
+    use crate::cpu::builder::CpuBuilder;
     use crate::cpu::trace::trace;
-    use crate::rom::rom::Rom;
+    use crate::ppu::mirroring::Mirroring;
+    use crate::ppu::render::RenderLayers;
+    use alloc::boxed::Box;
+    use alloc::format;
+    use alloc::rc::Rc;
+    use alloc::vec;
+    use core::cell::RefCell;
+    #[cfg(feature = "std")]
+    use core::iter::zip;
+    #[cfg(feature = "std")]
     use std::fs;
+    #[cfg(feature = "std")]
     use std::fs::{OpenOptions, read_to_string};
-    use std::iter::zip;
 
     // Start execution at $C000 and compare execution with a known
     // good log - https://www.qmtpro.com/~nes/misc/nestest.log
+    // The vendored copy only has the CPU columns. Timing is checked against
+    // the last line of the reference log, which starts at CYC:26554.
     #[test]
+    #[cfg(feature = "std")]
     fn test_nestest_cpu_instructions() {
         let logs_file = read_to_string("../roms/tests/nestest.log").unwrap();
-        let mut logs = logs_file.lines().map(|l| l.to_string());
         let rom_content = std::fs::read("../roms/tests/nestest.nes").unwrap();
         let mut cpu = setup_cpu_with_program(rom_content);
-        cpu.program_counter.set(0xC000);
+        cpu.set_pc(0xC000);
 
-        cpu.run(|cpu| {
-            let trace_log = trace(cpu);
-            println!("{trace_log}");
-            assert_eq!(trace_log, logs.next().unwrap());
-        })
-        .unwrap();
+        let mut trace_log = String::new();
+        for (line, expected) in logs_file.lines().enumerate() {
+            if line > 0 {
+                cpu.step().unwrap();
+            }
+            trace_log = trace(&mut cpu);
+            let (cpu_columns, _) = trace_log.split_once(" PPU:").unwrap();
+            assert_eq!(cpu_columns, expected, "line {}", line + 1);
+        }
+        assert!(trace_log.ends_with(" PPU:233,209 CYC:26554"), "{trace_log}");
     }
 
     #[test]
+    #[cfg(feature = "std")]
     fn compare_program_execution_logs() {
         let log_file = "../log.txt";
         let compare_log_file = "../compare_log.txt";
@@ -1072,11 +1377,698 @@ mod tests {
             assert_eq!(log, compare_log);
         })
     }
+
+    #[test]
+    fn test_bus_ticks_every_instruction_cycle() {
+        // LDA #$01; STA $0200; INC $0200; KIL
+        let program = [0xA9, 0x01, 0x8D, 0x00, 0x02, 0xEE, 0x00, 0x02, 0x02];
+        let mut cpu = CPU::from_program(&program);
+        assert_eq!(cpu.bus.cycles, 7);
+
+        cpu.run(|_| {}).unwrap();
+
+        // 7 reset + 2 LDA + 4 STA + 6 INC + 1 KIL opcode fetch
+        assert_eq!(cpu.bus.cycles, 20);
+        assert_eq!(cpu.bus.ppu.cycles, 60);
+        assert_eq!(BusOperation::<u8>::read(&mut cpu.bus, 0x0200), 2);
+    }
+
+    #[test]
+    fn test_oam_dma_stalls_cpu() {
+        // LDA #$02; STA $4014; KIL
+        let program = [0xA9, 0x02, 0x8D, 0x14, 0x40, 0x02];
+        let mut cpu = CPU::from_program(&program);
+        cpu.run(|_| {}).unwrap();
+        // Reset (7) + LDA (2) + STA (4) ends on an odd cycle, so the DMA takes 514
+        assert_eq!(cpu.bus.cycles, 7 + 2 + 4 + 514 + 1);
+
+        // LDA $00; LDA #$02; STA $4014; KIL
+        let program = [0xA5, 0x00, 0xA9, 0x02, 0x8D, 0x14, 0x40, 0x02];
+        let mut cpu = CPU::from_program(&program);
+        cpu.run(|_| {}).unwrap();
+        assert_eq!(cpu.bus.cycles, 7 + 3 + 2 + 4 + 513 + 1);
+    }
+
+    #[test]
+    fn test_nop_family_operands_and_cycles() {
+        // NOP; DOP $10; DOP #$55; TOP $0200; TOP $02FF,X; KIL
+        let program = [
+            0xEA, 0x04, 0x10, 0x80, 0x55, 0x0C, 0x00, 0x02, 0x1C, 0xFF, 0x02, 0x02,
+        ];
+        let mut cpu = CpuBuilder::new(&program).register_x(1).build();
+
+        cpu.run(|_| {}).unwrap();
+
+        assert_eq!(cpu.program_counter.get(), 0x800C);
+        // Reset (7) + NOP (2) + DOP zp (3) + DOP imm (2) + TOP abs (4)
+        // + TOP abs,X with page cross (5) + KIL opcode fetch (1)
+        assert_eq!(cpu.bus.cycles, 24);
+    }
+
+    #[test]
+    fn test_indirect_indexed_page_cross_penalty() {
+        // LDA ($10),Y; LDA ($12),Y; STA ($10),Y; KIL
+        let program = [0xB1, 0x10, 0xB1, 0x12, 0x91, 0x10, 0x02];
+        let mut cpu = CpuBuilder::new(&program)
+            .register_y(1)
+            .memory(0x10, 0xFF)
+            .memory(0x11, 0x02)
+            .memory(0x12, 0x00)
+            .memory(0x13, 0x03)
+            .memory(0x0300, 0x42)
+            .build();
+
+        cpu.run(|_| {}).unwrap();
+
+        assert_eq!(cpu.accumulator.get(), 0x00);
+        // Reset (7) + LDA with page cross (6) + LDA (5) + STA always (6) + KIL (1)
+        assert_eq!(cpu.bus.cycles, 25);
+        assert_eq!(read_byte(&mut cpu, 0x0300), 0x00);
+    }
+
+    #[test]
+    fn test_implied_mode_has_no_operand_address() {
+        let mut cpu = CPU::from_program(&[0x02]);
+
+        let result = cpu.get_operand_address(&AddressingMode::Implied, 0x8000);
+
+        assert!(matches!(
+            result,
+            Err(CPUError::NoAddressForMode(AddressingMode::Implied))
+        ));
+    }
+
+    #[test]
+    fn test_step_over_runs_subroutine() {
+        // JSR $8006; LDX #$01; KIL; LDA #$05; RTS
+        let program = [0x20, 0x06, 0x80, 0xA2, 0x01, 0x02, 0xA9, 0x05, 0x60];
+        let mut cpu = CPU::from_program(&program);
+
+        assert!(cpu.step_over().unwrap());
+
+        assert_eq!(cpu.program_counter.get(), 0x8003);
+        assert_eq!(cpu.accumulator.get(), 0x05);
+        assert_eq!(cpu.stack.get_pointer(), 0xFD);
+    }
+
+    #[test]
+    fn test_step_into_enters_subroutine() {
+        let program = [0x20, 0x06, 0x80, 0xA2, 0x01, 0x02, 0xA9, 0x05, 0x60];
+        let mut cpu = CPU::from_program(&program);
+
+        assert!(cpu.step_into().unwrap());
+
+        assert_eq!(cpu.program_counter.get(), 0x8006);
+        assert_eq!(cpu.accumulator.get(), 0x00);
+    }
+
+    #[test]
+    fn test_step_over_stops_at_breakpoint() {
+        let program = [0x20, 0x06, 0x80, 0xA2, 0x01, 0x02, 0xA9, 0x05, 0x60];
+        let mut cpu = CPU::from_program(&program);
+        cpu.add_breakpoint(0x8008);
+
+        assert!(cpu.step_over().unwrap());
+
+        assert_eq!(cpu.program_counter.get(), 0x8008);
+        assert_eq!(cpu.accumulator.get(), 0x05);
+    }
+
+    #[test]
+    fn test_set_pc_moves_execution() {
+        // KIL; LDA #$07; KIL
+        let mut cpu = CPU::from_program(&[0x02, 0xA9, 0x07, 0x02]);
+
+        cpu.set_pc(0x8001);
+        cpu.run(|_| {}).unwrap();
+
+        assert_eq!(cpu.accumulator.get(), 0x07);
+        assert_eq!(cpu.pc(), 0x8004);
+    }
+
+    #[test]
+    fn test_step_reports_halt() {
+        let mut cpu = CPU::from_program(&[0x02]);
+
+        assert!(!cpu.step().unwrap());
+    }
+
+    #[test]
+    fn test_kil_reports_jam_with_its_address() {
+        // LDA #$01; KIL
+        let mut cpu = CPU::from_program(&[0xA9, 0x01, 0x12]);
+
+        let reason = cpu.run(|_| {}).unwrap();
+
+        assert_eq!(
+            reason,
+            ExitReason::Jammed {
+                opcode: 0x12,
+                pc: 0x8002
+            }
+        );
+    }
+
+    #[test]
+    fn test_stack_underflow_is_reported() {
+        // LDX #$FF; TXS; PLA
+        let program = [0xA2, 0xFF, 0x9A, 0x68];
+        let mut cpu = CPU::from_program(&program);
+
+        let result = cpu.run(|_| {});
+
+        assert!(matches!(
+            result,
+            Err(CPUError::Stack(StackError::StackUnderflow))
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_run_with_trace_writes_line_per_instruction() {
+        // LDA #$01; KIL
+        let program = [0xA9, 0x01, 0x02];
+        let mut cpu = CPU::from_program(&program);
+        let mut log = Vec::new();
+
+        cpu.run_with_trace(&mut log).unwrap();
+
+        let log = String::from_utf8(log).unwrap();
+        let lines: Vec<&str> = log.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("8000  A9 01     LDA #$01"));
+        assert!(lines[1].starts_with("8002  02       *KIL"));
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_run_with_trace_stops_on_write_error() {
+        struct FullDisk;
+
+        impl std::io::Write for FullDisk {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::StorageFull.into())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        // JMP $8000
+        let mut cpu = CPU::from_program(&[0x4C, 0x00, 0x80]);
+
+        let result = cpu.run_with_trace(&mut FullDisk);
+
+        assert!(
+            matches!(&result, Err(CPUError::Io(error)) if error.kind() == std::io::ErrorKind::StorageFull),
+            "{result:?}"
+        );
+    }
+
+    #[test]
+    fn test_power_on_clears_registers() {
+        // LDA #$11; LDX #$22; LDY #$33; KIL
+        let program = [0xA9, 0x11, 0xA2, 0x22, 0xA0, 0x33, 0x02];
+        let mut cpu = CPU::from_program(&program);
+        cpu.run(|_| {}).unwrap();
+
+        cpu.power_on();
+
+        assert_eq!(cpu.accumulator.get(), 0);
+        assert_eq!(cpu.register_x.get(), 0);
+        assert_eq!(cpu.register_y.get(), 0);
+        assert_eq!(cpu.stack.get_pointer(), 0xFD);
+        assert_eq!(cpu.status.get(), 0x24);
+        assert_eq!(cpu.program_counter.get(), 0x8000);
+    }
+
+    #[test]
+    fn test_read_modify_write_writes_old_value_first() {
+        // SEC; ROL $10; INC $10; KIL
+        let program = [0x38, 0x26, 0x10, 0xE6, 0x10, 0x02];
+        let mut cpu = CpuBuilder::new(&program).memory(0x0010, 0x81).build();
+        let writes = Rc::new(RefCell::new(Vec::new()));
+        let log = writes.clone();
+        cpu.bus.set_access_log(Box::new(move |access| {
+            if access.is_write {
+                log.borrow_mut().push((access.addr, access.value));
+            }
+        }));
+
+        let cycles = cpu.bus.stats().cycles;
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+        cpu.step().unwrap();
+
+        assert_eq!(
+            *writes.borrow(),
+            [(0x10, 0x81), (0x10, 0x03), (0x10, 0x03), (0x10, 0x04)]
+        );
+        // SEC 2, ROL zp 5, INC zp 5: the extra write isn't an extra cycle
+        assert_eq!(cpu.bus.stats().cycles, cycles + 12);
+    }
+
+    #[test]
+    fn test_brk_pushes_return_address_past_padding_and_b_flag() {
+        // BRK; padding byte, the IRQ vector points back to $8000
+        let mut cpu = CpuBuilder::new(&[0x00, 0xEA])
+            .status(0x01)
+            .stack_pointer(0xFD)
+            .build();
+        let status = cpu.status.get();
+
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.stack.get_pointer(), 0xFA);
+        assert_eq!(cpu.bus.peek(0x01FD), 0x80);
+        assert_eq!(cpu.bus.peek(0x01FC), 0x02);
+        // B and the unused bit are set in the pushed status
+        assert_eq!(cpu.bus.peek(0x01FB), status | 0x30);
+        // B only exists in the pushed copy, I is set for the handler
+        assert_eq!(cpu.status.get() & 0x10, 0);
+        assert!(cpu.status.is_interrupt_disable_flag_set());
+        assert_eq!(cpu.pc(), 0x8000);
+    }
+
+    #[test]
+    fn test_indirect_jmp_vector_wraps_within_page() {
+        // JMP ($02FF)
+        let mut cpu = CPU::from_program(&[0x6C, 0xFF, 0x02]);
+        cpu.bus.poke(0x02FF, 0x34);
+        cpu.bus.poke(0x0200, 0x12);
+        cpu.bus.poke(0x0300, 0x56);
+        cpu.bus.poke(0x1234, 0x02);
+
+        let log = trace(&mut cpu);
+        cpu.step().unwrap();
+
+        assert_eq!(cpu.pc(), 0x1234);
+        assert!(log.contains("JMP ($02FF) = 1234"), "{log}");
+    }
+
+    #[test]
+    fn test_operand_bytes_wrap_around_the_address_space() {
+        let mut cpu = CPU::from_program(&[0x02]);
+        // LDA $1234 with the opcode at $FFFF and the operand at $0000
+        cpu.bus.poke(0xFFFF, 0xAD);
+        cpu.bus.poke(0x0000, 0x34);
+        cpu.bus.poke(0x0001, 0x12);
+        cpu.bus.poke(0x1234, 0x77);
+        cpu.set_pc(0xFFFF);
+
+        let log = trace(&mut cpu);
+        cpu.step().unwrap();
+
+        assert!(log.starts_with("FFFF  AD 34 12  LDA $1234 = 77"), "{log}");
+        assert_eq!(cpu.accumulator.get(), 0x77);
+        assert_eq!(cpu.pc(), 0x0002);
+    }
+
+    #[test]
+    fn test_load_rom_swaps_cartridge_and_restarts() {
+        let frames = Rc::new(RefCell::new(0));
+        let counter = frames.clone();
+        let first = Rom::from_prg(&[0xEA; 0x4000], &[], Mirroring::Horizontal);
+        let mut cpu = CPU::new(
+            Bus::new(first, move |_, _| {
+                *counter.borrow_mut() += 1;
+                EmulationFlow::Continue
+            })
+            .unwrap(),
+        );
+        cpu.power_on();
+        cpu.bus.poke(0x0010, 0x42);
+        let layers = RenderLayers {
+            sprites: false,
+            ..RenderLayers::default()
+        };
+        cpu.bus.ppu.set_render_layers(layers);
+
+        let mut prg = vec![0x02; 0x4000];
+        prg[0x3FFC..].copy_from_slice(&[0x34, 0xC2, 0x00, 0x00]);
+        cpu.load_rom(Rom::from_prg(&prg, &[], Mirroring::Vertical))
+            .unwrap();
+
+        assert_eq!(cpu.pc(), 0xC234);
+        assert_eq!(cpu.bus.peek(0x8000), 0x02);
+        assert_eq!(cpu.bus.peek(0x0010), 0x00);
+        assert_eq!(cpu.bus.ppu.current_mirroring(), Mirroring::Vertical);
+        assert_eq!(cpu.bus.ppu.render_layers(), layers);
+        // The frame callback survives the swap
+        while cpu.bus.stats().frames < 1 {
+            cpu.bus.tick(1);
+        }
+        assert_eq!(*frames.borrow(), 1);
+    }
+
+    // http://www.6502.org/tutorials/vflag.html
+    #[test]
+    fn test_adc_overflow_truth_table() {
+        // (A, operand, carry in, result, carry out, overflow)
+        let cases = [
+            (0x50, 0x10, false, 0x60, false, false),
+            (0x50, 0x50, false, 0xA0, false, true),
+            (0x50, 0x90, false, 0xE0, false, false),
+            (0x50, 0xD0, false, 0x20, true, false),
+            (0xD0, 0x10, false, 0xE0, false, false),
+            (0xD0, 0x50, false, 0x20, true, false),
+            (0xD0, 0x90, false, 0x60, true, true),
+            (0xD0, 0xD0, false, 0xA0, true, false),
+            (0x7F, 0x00, true, 0x80, false, true),
+            (0x80, 0xFF, true, 0x80, true, false),
+        ];
+
+        for (accumulator, operand, carry, result, carry_out, overflow) in cases {
+            // SEC or CLC; ADC #operand; KIL
+            let program = [if carry { 0x38 } else { 0x18 }, 0x69, operand, 0x02];
+            let mut cpu = CpuBuilder::new(&program).accumulator(accumulator).build();
+            cpu.run(|_| {}).unwrap();
+
+            let case = format!("{accumulator:02X} + {operand:02X} + {}", carry as u8);
+            assert_eq!(cpu.accumulator.get(), result, "{case}");
+            assert_eq!(cpu.status.is_carry_flag_set(), carry_out, "{case}");
+            assert_eq!(cpu.status.is_overflow_flag_set(), overflow, "{case}");
+        }
+    }
+
+    #[test]
+    fn test_unofficial_sbc_matches_official_sbc() {
+        let run = |opcode: u8, accumulator: u8, operand: u8, carry: bool| {
+            // SEC or CLC; SBC #operand; KIL
+            let program = [if carry { 0x38 } else { 0x18 }, opcode, operand, 0x02];
+            let mut cpu = CpuBuilder::new(&program).accumulator(accumulator).build();
+            cpu.step().unwrap();
+            let log = trace(&mut cpu);
+            let cycles = cpu.bus.stats().cycles;
+            cpu.step().unwrap();
+            let result = (cpu.snapshot(), cpu.bus.stats().cycles - cycles);
+            (result, log)
+        };
+
+        for (accumulator, operand, carry) in
+            [(0x50, 0xB0, true), (0x00, 0x01, false), (0x80, 0x7F, true)]
+        {
+            let (official, official_log) = run(0xE9, accumulator, operand, carry);
+            let (unofficial, unofficial_log) = run(0xEB, accumulator, operand, carry);
+            assert_eq!(official, unofficial);
+            assert!(official_log.contains(" SBC #$"), "{official_log}");
+            assert!(unofficial_log.contains("*SBC #$"), "{unofficial_log}");
+        }
+    }
+
+    #[test]
+    fn test_unknown_opcode_policy() {
+        // Only LDA #imm and KIL are defined
+        static PARTIAL_OPCODES: OpCodeTable = {
+            let mut opcodes = OpCodeTable::new();
+            opcodes.insert(
+                0xA9,
+                Instruction {
+                    opcode: OpCode::LDA,
+                    mode: AddressingMode::Immediate,
+                    cycles: 2,
+                    official: true,
+                },
+            );
+            opcodes.insert(
+                0x02,
+                Instruction {
+                    opcode: OpCode::KIL,
+                    mode: AddressingMode::Implied,
+                    cycles: 0,
+                    official: false,
+                },
+            );
+            opcodes
+        };
+        // ???; LDA #$42; KIL
+        let program = [0xEA, 0xA9, 0x42, 0x02];
+
+        let mut cpu = CPU::from_program(&program);
+        cpu.opcodes = &PARTIAL_OPCODES;
+        let result = cpu.run(|_| {});
+        assert!(matches!(
+            result,
+            Err(CPUError::UnknownOpCode(UnknownOpCode(0xEA)))
+        ));
+
+        let mut cpu = CPU::from_program(&program);
+        cpu.opcodes = &PARTIAL_OPCODES;
+        cpu.set_unknown_opcode_policy(UnknownOpCodePolicy::TreatAsNop);
+        let cycles = cpu.bus.stats().cycles;
+        cpu.step().unwrap();
+        assert_eq!((cpu.pc(), cpu.bus.stats().cycles - cycles), (0x8001, 2));
+
+        let result = cpu.run(|_| {}).unwrap();
+        assert_eq!(
+            result,
+            ExitReason::Jammed {
+                opcode: 0x02,
+                pc: 0x8003
+            }
+        );
+        assert_eq!(cpu.accumulator.get(), 0x42);
+    }
+
+    #[test]
+    fn test_lar_ands_memory_with_stack_pointer() {
+        // A = X = S = M & S, 4 cycles plus 1 when the index crosses a page
+        let run = |register_y: u8, address: u16, value: u8, stack_pointer: u8| {
+            // LAR $02F0,Y; KIL
+            let mut cpu = CpuBuilder::new(&[0xBB, 0xF0, 0x02, 0x02])
+                .register_y(register_y)
+                .stack_pointer(stack_pointer)
+                .memory(address, value)
+                .build();
+            let cycles = cpu.bus.stats().cycles;
+            cpu.step().unwrap();
+            (cpu.snapshot(), cpu.bus.stats().cycles - cycles, cpu.status)
+        };
+
+        let (snapshot, cycles, status) = run(0x20, 0x0310, 0xC7, 0xF5);
+        assert_eq!(
+            (
+                snapshot.accumulator,
+                snapshot.register_x,
+                snapshot.stack_pointer
+            ),
+            (0xC5, 0xC5, 0xC5)
+        );
+        assert!(status.is_negative_flag_set());
+        assert!(!status.is_zero_flag_set());
+        assert_eq!(cycles, 5);
+
+        let (snapshot, cycles, status) = run(0x05, 0x02F5, 0x0F, 0xF0);
+        assert_eq!(
+            (
+                snapshot.accumulator,
+                snapshot.register_x,
+                snapshot.stack_pointer
+            ),
+            (0x00, 0x00, 0x00)
+        );
+        assert!(!status.is_negative_flag_set());
+        assert!(status.is_zero_flag_set());
+        assert_eq!(cycles, 4);
+    }
+
+    #[test]
+    fn test_reset_to_skips_the_reset_vector() {
+        let mut cpu = CPU::from_program(&[0x02]);
+        // LDA #$5A; KIL in RAM
+        for (offset, byte) in [0xA9, 0x5A, 0x02].into_iter().enumerate() {
+            cpu.bus.poke(0x0300 + offset as u16, byte);
+        }
+        let cycles = cpu.bus.stats().cycles;
+
+        cpu.reset_to(0x0300);
+
+        assert_eq!(cpu.pc(), 0x0300);
+        assert_eq!(cpu.bus.stats().cycles, cycles + 7);
+        assert_eq!(cpu.stack.get_pointer(), 0xFA);
+        cpu.run(|_| {}).unwrap();
+        assert_eq!(cpu.accumulator.get(), 0x5A);
+    }
+
+    #[test]
+    fn test_reset_keeps_registers_and_decrements_stack_pointer() {
+        // LDA #$11; LDX #$22; LDY #$33; CLI; KIL
+        let program = [0xA9, 0x11, 0xA2, 0x22, 0xA0, 0x33, 0x58, 0x02];
+        let mut cpu = CPU::from_program(&program);
+        cpu.run(|_| {}).unwrap();
+        cpu.bus.write(0x0010, 0x42_u8);
+        cpu.bus.ppu.write_ppuctrl(0x80);
+        cpu.bus.ppu.write_ppuscroll(0x10);
+
+        cpu.reset();
+
+        assert_eq!(cpu.accumulator.get(), 0x11);
+        assert_eq!(cpu.register_x.get(), 0x22);
+        assert_eq!(cpu.register_y.get(), 0x33);
+        assert_eq!(cpu.stack.get_pointer(), 0xFA);
+        assert!(cpu.status.is_interrupt_disable_flag_set());
+        assert_eq!(cpu.program_counter.get(), 0x8000);
+        let ram_value: u8 = cpu.bus.read(0x0010);
+        assert_eq!(ram_value, 0x42);
+        // The write toggle is cleared, so the next PPUADDR write is the high byte
+        cpu.bus.ppu.write_ppuaddr(0x21);
+        cpu.bus.ppu.write_ppuaddr(0x00);
+        cpu.bus.ppu.write_ppudata(0x55);
+        cpu.bus.ppu.write_ppuaddr(0x21);
+        cpu.bus.ppu.write_ppuaddr(0x00);
+        cpu.bus.ppu.read_ppudata();
+        assert_eq!(cpu.bus.ppu.read_ppudata(), 0x55);
+    }
+
+    #[test]
+    fn test_xaa_uses_magic_constant() {
+        // LDA #$01; LDX #$F3; XAA #$3F; KIL
+        let program = [0xA9, 0x01, 0xA2, 0xF3, 0x8B, 0x3F, 0x02];
+
+        let mut cpu = CPU::from_program(&program);
+        cpu.run(|_| {}).unwrap();
+        assert_eq!(cpu.accumulator.get(), (0x01 | 0xEE) & 0xF3 & 0x3F);
+
+        let mut cpu = CPU::from_program(&program);
+        cpu.set_xaa_magic(0xFF);
+        cpu.run(|_| {}).unwrap();
+        assert_eq!(cpu.accumulator.get(), 0x33);
+        assert!(!cpu.status.contains(ProcessorStatus::ZERO_FLAG));
+
+        let mut cpu = CPU::from_program(&program);
+        cpu.set_xaa_magic(0x00);
+        cpu.run(|_| {}).unwrap();
+        assert_eq!(cpu.accumulator.get(), 0x01 & 0xF3 & 0x3F);
+    }
+
+    #[test]
+    fn test_xaa_sets_negative_and_zero_flags() {
+        // LDX #$80; XAA #$C0; KIL
+        let program = [0xA2, 0x80, 0x8B, 0xC0, 0x02];
+        let mut cpu = CPU::from_program(&program);
+        cpu.set_xaa_magic(0xFF);
+        cpu.run(|_| {}).unwrap();
+        assert_eq!(cpu.accumulator.get(), 0x80);
+        assert!(cpu.status.contains(ProcessorStatus::NEGATIVE_FLAG));
+
+        // XAA #$00; KIL
+        let program = [0x8B, 0x00, 0x02];
+        let mut cpu = CPU::from_program(&program);
+        cpu.run(|_| {}).unwrap();
+        assert_eq!(cpu.accumulator.get(), 0x00);
+        assert!(cpu.status.contains(ProcessorStatus::ZERO_FLAG));
+    }
+
+    #[test]
+    fn test_sxa_stores_x_and_high_byte_plus_one() {
+        // LDX #$FF; LDY #$01; SXA $1200,Y; KIL
+        let program = [0xA2, 0xFF, 0xA0, 0x01, 0x9E, 0x00, 0x12, 0x02];
+        let mut cpu = run_program(&program);
+        assert_eq!(read_byte(&mut cpu, 0x1201), 0x13);
+    }
+
+    #[test]
+    fn test_sxa_page_cross_replaces_high_byte() {
+        // LDX #$05; LDY #$10; SXA $02F8,Y; KIL
+        let program = [0xA2, 0x05, 0xA0, 0x10, 0x9E, 0xF8, 0x02, 0x02];
+        let mut cpu = run_program(&program);
+        // X & ($02 + 1) = $01 is written to $0108 instead of $0308
+        assert_eq!(read_byte(&mut cpu, 0x0108), 0x01);
+        assert_eq!(read_byte(&mut cpu, 0x0308), 0x00);
+    }
+
+    #[test]
+    fn test_sya_stores_y_and_high_byte_plus_one() {
+        // LDY #$FF; LDX #$02; SYA $0500,X; KIL
+        let program = [0xA0, 0xFF, 0xA2, 0x02, 0x9C, 0x00, 0x05, 0x02];
+        let mut cpu = run_program(&program);
+        assert_eq!(read_byte(&mut cpu, 0x0502), 0x06);
+    }
+
+    #[test]
+    fn test_axa_stores_a_and_x_and_high_byte_plus_one() {
+        // LDA #$F3; LDX #$7F; LDY #$00; AXA $0400,Y; KIL
+        let program = [0xA9, 0xF3, 0xA2, 0x7F, 0xA0, 0x00, 0x9F, 0x00, 0x04, 0x02];
+        let mut cpu = run_program(&program);
+        assert_eq!(read_byte(&mut cpu, 0x0400), 0x01);
+
+        // AXA ($10),Y; KIL
+        let mut cpu = CpuBuilder::new(&[0x93, 0x10, 0x02])
+            .accumulator(0xFF)
+            .register_x(0xFF)
+            .register_y(0x04)
+            .memory(0x0010, 0x00)
+            .memory(0x0011, 0x06)
+            .build();
+        cpu.run(|_| {}).unwrap();
+        assert_eq!(read_byte(&mut cpu, 0x0604), 0x07);
+    }
+
+    #[test]
+    fn test_xas_sets_stack_pointer_and_stores_high_byte_plus_one() {
+        // LDA #$F0; LDX #$3C; LDY #$00; XAS $0F00,Y; KIL
+        let program = [0xA9, 0xF0, 0xA2, 0x3C, 0xA0, 0x00, 0x9B, 0x00, 0x0F, 0x02];
+        let mut cpu = run_program(&program);
+        assert_eq!(cpu.stack.get_pointer(), 0x30);
+        assert_eq!(read_byte(&mut cpu, 0x0F00), 0x10);
+    }
+
+    #[test]
+    fn test_run_returns_when_frame_callback_breaks() {
+        // JMP $8000 never halts on its own
+        let mut prg = vec![0; 0x4000];
+        prg[..3].copy_from_slice(&[0x4C, 0x00, 0x80]);
+        prg[0x3FFC..0x3FFE].copy_from_slice(&[0x00, 0x80]);
+        let rom = Rom::from_prg(&prg, &[], Mirroring::Horizontal);
+
+        let mut frames = 0;
+        let bus = Bus::new(rom, |_, _| {
+            frames += 1;
+            if frames == 2 {
+                EmulationFlow::Break
+            } else {
+                EmulationFlow::Continue
+            }
+        })
+        .unwrap();
+        let mut cpu = CPU::new(bus);
+        cpu.power_on();
+        cpu.run(|_| {}).unwrap();
+        drop(cpu);
+
+        assert_eq!(frames, 2);
+    }
+
+    #[test]
+    fn test_instruction_budget_stops_infinite_loop() {
+        // JMP $8000
+        let mut cpu = CPU::from_program(&[0x4C, 0x00, 0x80]);
+        cpu.set_instruction_budget(100);
+
+        let mut executed = 0;
+        let result = cpu.run(|_| executed += 1);
+
+        assert!(matches!(result, Err(CPUError::BudgetExceeded(100))));
+        assert_eq!(executed, 100);
+    }
+
+    fn run_program<'bus>(program: &[u8]) -> CPU<'bus> {
+        let mut cpu = CPU::from_program(program);
+        cpu.run(|_| {}).unwrap();
+        cpu
+    }
+
+    fn read_byte(cpu: &mut CPU, address: u16) -> u8 {
+        cpu.bus.read(address)
+    }
+
+    #[cfg(feature = "std")]
     fn setup_cpu_with_program<'bus>(program: Vec<u8>) -> CPU<'bus> {
         let rom = Rom::new(&program).unwrap();
-        let bus = Bus::new(rom, |_, _| {});
+        let bus = Bus::new(rom, |_, _| EmulationFlow::Continue).unwrap();
         let mut cpu = CPU::new(bus);
-        cpu.reset_interrupt();
+        cpu.power_on();
         cpu
     }
 }
@@ -1,13 +1,143 @@
-use std::error::Error;
-use std::fmt::{Display, Formatter};
+use crate::cpu::opcode::AddressingMode;
+use crate::mapper::error::UnsupportedMapper;
+use core::error::Error;
+use core::fmt::{Display, Formatter};
 
 #[derive(Debug)]
 pub struct UnknownOpCode(pub u8);
 
 impl Display for UnknownOpCode {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         write!(f, "Unknown opcode {}", self.0)
     }
 }
 
 impl Error for UnknownOpCode {}
+
+#[derive(Debug, PartialEq)]
+pub struct UnknownMnemonic;
+
+impl Display for UnknownMnemonic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Unknown instruction mnemonic")
+    }
+}
+
+impl Error for UnknownMnemonic {}
+
+#[derive(Debug, PartialEq)]
+pub struct UnknownAddressingMode;
+
+impl Display for UnknownAddressingMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Unknown addressing mode syntax")
+    }
+}
+
+impl Error for UnknownAddressingMode {}
+
+#[derive(Debug)]
+pub enum StackError {
+    StackOverflow,
+    StackUnderflow,
+}
+
+impl Display for StackError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            StackError::StackOverflow => write!(f, "Stack overflow, push below $0100"),
+            StackError::StackUnderflow => write!(f, "Stack underflow, pull above $01FF"),
+        }
+    }
+}
+
+impl Error for StackError {}
+
+// Token position (0-based) of the first word that isn't a hex byte
+#[derive(Debug, PartialEq)]
+pub enum HexProgramError {
+    Empty,
+    InvalidByte(usize),
+    // Length of the program, it has to leave room for the vectors
+    TooLong(usize),
+}
+
+impl Display for HexProgramError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            HexProgramError::Empty => write!(f, "Hex program doesn't contain any bytes"),
+            HexProgramError::InvalidByte(index) => {
+                write!(f, "Token {} isn't a hex byte", index)
+            }
+            HexProgramError::TooLong(length) => write!(
+                f,
+                "Hex program of {} bytes doesn't fit in 32 KiB of PRG ROM with the vectors",
+                length
+            ),
+        }
+    }
+}
+
+impl Error for HexProgramError {}
+
+#[derive(Debug)]
+pub enum CPUError {
+    UnknownOpCode(UnknownOpCode),
+    Stack(StackError),
+    UnsupportedMapper(UnsupportedMapper),
+    NoAddressForMode(AddressingMode),
+    // Number of instructions executed before `run` gave up
+    BudgetExceeded(u64),
+    // Writing the trace log failed, the run stopped after that instruction
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+}
+
+impl Display for CPUError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            CPUError::UnknownOpCode(error) => write!(f, "{}", error),
+            CPUError::Stack(error) => write!(f, "{}", error),
+            CPUError::UnsupportedMapper(error) => write!(f, "{}", error),
+            CPUError::NoAddressForMode(mode) => {
+                write!(
+                    f,
+                    "Addressing mode {:?} doesn't have an operand address",
+                    mode
+                )
+            }
+            CPUError::BudgetExceeded(budget) => {
+                write!(f, "Program didn't stop within {} instructions", budget)
+            }
+            #[cfg(feature = "std")]
+            CPUError::Io(error) => write!(f, "Failed to write trace log: {}", error),
+        }
+    }
+}
+
+impl Error for CPUError {}
+
+impl From<UnknownOpCode> for CPUError {
+    fn from(error: UnknownOpCode) -> Self {
+        CPUError::UnknownOpCode(error)
+    }
+}
+
+impl From<UnsupportedMapper> for CPUError {
+    fn from(error: UnsupportedMapper) -> Self {
+        CPUError::UnsupportedMapper(error)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for CPUError {
+    fn from(error: std::io::Error) -> Self {
+        CPUError::Io(error)
+    }
+}
+
+impl From<StackError> for CPUError {
+    fn from(error: StackError) -> Self {
+        CPUError::Stack(error)
+    }
+}
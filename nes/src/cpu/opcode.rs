@@ -1,16 +1,20 @@
 #![cfg_attr(rustfmt, rustfmt_skip)]
-use lazy_static::lazy_static;
-use std::collections::HashMap;
-use std::fmt::{Display, Formatter};
+use crate::cpu::error::{UnknownAddressingMode, UnknownMnemonic};
+use alloc::format;
+use core::fmt::{Display, Formatter};
+use core::str::FromStr;
 
 #[derive(Debug)]
 pub struct Instruction {
     pub opcode: OpCode,
     pub mode: AddressingMode,
     pub cycles: u8,
+    // Unofficial opcodes include the duplicates of official instructions,
+    // e.g. $EB is SBC #imm and $1A is NOP.
+    pub official: bool,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OpCode {
     ADC,
     AND,
@@ -92,7 +96,7 @@ pub enum OpCode {
     XAS,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AddressingMode {
     Absolute,
     AbsoluteX,
@@ -109,36 +113,83 @@ pub enum AddressingMode {
     ZeroPageY,
 }
 
+impl OpCode {
+    pub const ALL: [OpCode; 78] = [
+        OpCode::ADC, OpCode::AND, OpCode::ASL, OpCode::BCC, OpCode::BCS, OpCode::BEQ, OpCode::BIT, OpCode::BMI,
+        OpCode::BNE, OpCode::BPL, OpCode::BRK, OpCode::BVC, OpCode::BVS, OpCode::CLC, OpCode::CLD, OpCode::CLI,
+        OpCode::CLV, OpCode::CMP, OpCode::CPX, OpCode::CPY, OpCode::DEC, OpCode::DEX, OpCode::DEY, OpCode::EOR,
+        OpCode::INC, OpCode::INX, OpCode::INY, OpCode::JMP, OpCode::JSR, OpCode::LDA, OpCode::LDX, OpCode::LDY,
+        OpCode::LSR, OpCode::NOP, OpCode::ORA, OpCode::PHA, OpCode::PHP, OpCode::PLA, OpCode::PLP, OpCode::ROL,
+        OpCode::ROR, OpCode::RTI, OpCode::RTS, OpCode::SBC, OpCode::SEC, OpCode::SED, OpCode::SEI, OpCode::STA,
+        OpCode::STX, OpCode::STY, OpCode::TAX, OpCode::TAY, OpCode::TSX, OpCode::TXA, OpCode::TXS, OpCode::TYA,
+        OpCode::AAC, OpCode::SAX, OpCode::ARR, OpCode::ASR, OpCode::ATX, OpCode::AXA, OpCode::AXS, OpCode::DCP,
+        OpCode::DOP, OpCode::ISB, OpCode::KIL, OpCode::LAR, OpCode::LAX, OpCode::RLA, OpCode::RRA, OpCode::SLO,
+        OpCode::SRE, OpCode::SXA, OpCode::SYA, OpCode::TOP, OpCode::XAA, OpCode::XAS,
+    ];
+
+    // Official when any of its opcodes is, e.g. SBC and NOP are official
+    // even though $EB and $1A aren't. Derived from `Instruction::official`.
+    // https://www.nesdev.org/wiki/CPU_unofficial_opcodes
+    pub fn is_official(&self) -> bool {
+        OPCODES
+            .iter()
+            .any(|(_, instruction)| instruction.opcode == *self && instruction.official)
+    }
+}
+
+// Mnemonic as printed by the nestest log, unofficial opcodes get a `*` prefix
+impl Display for Instruction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        if !self.official {
+            write!(f, "*")?;
+        }
+        write!(f, "{}", self.opcode)
+    }
+}
+
+// Plain mnemonic, DOP and TOP are printed as the NOP they are
 impl Display for OpCode {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         match self {
-            OpCode::AAC |
-            OpCode::SAX |
-            OpCode::ARR |
-            OpCode::ASR |
-            OpCode::ATX |
-            OpCode::AXA |
-            OpCode::AXS |
-            OpCode::DCP |
-            OpCode::ISB |
-            OpCode::KIL |
-            OpCode::LAR |
-            OpCode::LAX |
-            OpCode::RLA |
-            OpCode::RRA |
-            OpCode::SLO |
-            OpCode::SRE |
-            OpCode::SXA |
-            OpCode::SYA |
-            OpCode::XAA |
-            OpCode::XAS => write!(f, "*{:?}", self),
-            OpCode::DOP | OpCode::TOP => write!(f, "*NOP"),
+            OpCode::DOP | OpCode::TOP => write!(f, "NOP"),
             _ => write!(f, "{:?}", self),
         }
     }
 }
 
+// Case-insensitive, the `*` prefix of unofficial opcodes is optional. "*NOP"
+// parses as NOP, DOP and TOP only differ by the operand size.
+impl FromStr for OpCode {
+    type Err = UnknownMnemonic;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mnemonic = s.strip_prefix('*').unwrap_or(s);
+        OpCode::ALL
+            .into_iter()
+            .find(|opcode| format!("{:?}", opcode).eq_ignore_ascii_case(mnemonic))
+            .ok_or(UnknownMnemonic)
+    }
+}
+
 impl AddressingMode {
+    // Operand syntax as written in 6502 reference tables
+    // https://www.masswerk.at/6502/6502_instruction_set.html
+    const SYNTAX: [(AddressingMode, &'static str); 13] = [
+        (AddressingMode::Absolute, "abs"),
+        (AddressingMode::AbsoluteX, "abs,X"),
+        (AddressingMode::AbsoluteY, "abs,Y"),
+        (AddressingMode::Accumulator, "A"),
+        (AddressingMode::Immediate, "#"),
+        (AddressingMode::Implied, "impl"),
+        (AddressingMode::Indirect, "(ind)"),
+        (AddressingMode::IndexedIndirectX, "(ind,X)"),
+        (AddressingMode::IndirectIndexedY, "(ind),Y"),
+        (AddressingMode::Relative, "rel"),
+        (AddressingMode::ZeroPage, "zpg"),
+        (AddressingMode::ZeroPageX, "zpg,X"),
+        (AddressingMode::ZeroPageY, "zpg,Y"),
+    ];
+
     pub fn operand_bytes(&self) -> u8 {
         match self {
             AddressingMode::Absolute => 2,
@@ -157,462 +208,604 @@ impl AddressingMode {
     }
 }
 
+impl Display for AddressingMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let (_, syntax) = AddressingMode::SYNTAX
+            .iter()
+            .find(|(mode, _)| mode == self)
+            .expect("Every addressing mode has a syntax");
+        write!(f, "{}", syntax)
+    }
+}
+
+// Case-insensitive, so "ZPG,x" is the same as "zpg,X"
+impl FromStr for AddressingMode {
+    type Err = UnknownAddressingMode;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AddressingMode::SYNTAX
+            .iter()
+            .find(|(_, syntax)| syntax.eq_ignore_ascii_case(s))
+            .map(|(mode, _)| *mode)
+            .ok_or(UnknownAddressingMode)
+    }
+}
+
+// Lookup table indexed by the opcode byte. Built at compile time, so it
+// doesn't need an allocator or lazy initialization.
+pub struct OpCodeTable([Option<Instruction>; 256]);
+
+impl OpCodeTable {
+    pub(crate) const fn new() -> Self {
+        OpCodeTable([const { None }; 256])
+    }
+
+    pub(crate) const fn insert(&mut self, opcode: u8, instruction: Instruction) {
+        self.0[opcode as usize] = Some(instruction);
+    }
+
+    pub fn get(&self, opcode: u8) -> Option<&Instruction> {
+        self.0[opcode as usize].as_ref()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.iter().filter(|instruction| instruction.is_some()).count()
+    }
 
-lazy_static! {
-    pub static ref OPCODES: HashMap<u8, Instruction> = {
-        let mut opcodes = HashMap::new();
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    // Defined instructions ordered by their opcode byte
+    pub fn iter(&self) -> impl Iterator<Item = (u8, &Instruction)> {
+        self.0
+            .iter()
+            .enumerate()
+            .filter_map(|(opcode, instruction)| instruction.as_ref().map(|instruction| (opcode as u8, instruction)))
+    }
+}
+
+pub static OPCODES: OpCodeTable = {
+        let mut opcodes = OpCodeTable::new();
 
         // ADC - Add with Carry
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#ADC
-        opcodes.insert(0x69, Instruction { opcode: OpCode::ADC, mode: AddressingMode::Immediate, cycles: 2 });
-        opcodes.insert(0x65, Instruction { opcode: OpCode::ADC, mode: AddressingMode::ZeroPage, cycles: 3 });
-        opcodes.insert(0x75, Instruction { opcode: OpCode::ADC, mode: AddressingMode::ZeroPageX, cycles: 4 });
-        opcodes.insert(0x6D, Instruction { opcode: OpCode::ADC, mode: AddressingMode::Absolute, cycles: 4 });
-        opcodes.insert(0x7D, Instruction { opcode: OpCode::ADC, mode: AddressingMode::AbsoluteX, cycles: 4 }); // +1 cycle if page crossed
-        opcodes.insert(0x79, Instruction { opcode: OpCode::ADC, mode: AddressingMode::AbsoluteY, cycles: 4 }); // +1 cycle if page crossed
-        opcodes.insert(0x61, Instruction { opcode: OpCode::ADC, mode: AddressingMode::IndexedIndirectX, cycles: 6 });
-        opcodes.insert(0x71, Instruction { opcode: OpCode::ADC, mode: AddressingMode::IndirectIndexedY, cycles: 5 }); // +1 cycle if page crossed
+        opcodes.insert(0x69, Instruction { opcode: OpCode::ADC, mode: AddressingMode::Immediate, cycles: 2, official: true });
+        opcodes.insert(0x65, Instruction { opcode: OpCode::ADC, mode: AddressingMode::ZeroPage, cycles: 3, official: true });
+        opcodes.insert(0x75, Instruction { opcode: OpCode::ADC, mode: AddressingMode::ZeroPageX, cycles: 4, official: true });
+        opcodes.insert(0x6D, Instruction { opcode: OpCode::ADC, mode: AddressingMode::Absolute, cycles: 4, official: true });
+        opcodes.insert(0x7D, Instruction { opcode: OpCode::ADC, mode: AddressingMode::AbsoluteX, cycles: 4, official: true }); // +1 cycle if page crossed
+        opcodes.insert(0x79, Instruction { opcode: OpCode::ADC, mode: AddressingMode::AbsoluteY, cycles: 4, official: true }); // +1 cycle if page crossed
+        opcodes.insert(0x61, Instruction { opcode: OpCode::ADC, mode: AddressingMode::IndexedIndirectX, cycles: 6, official: true });
+        opcodes.insert(0x71, Instruction { opcode: OpCode::ADC, mode: AddressingMode::IndirectIndexedY, cycles: 5, official: true }); // +1 cycle if page crossed
 
         // AND - Logical AND
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#AND
-        opcodes.insert(0x29, Instruction { opcode: OpCode::AND, mode: AddressingMode::Immediate, cycles: 2 });
-        opcodes.insert(0x25, Instruction { opcode: OpCode::AND, mode: AddressingMode::ZeroPage, cycles: 3 });
-        opcodes.insert(0x35, Instruction { opcode: OpCode::AND, mode: AddressingMode::ZeroPageX, cycles: 4 });
-        opcodes.insert(0x2D, Instruction { opcode: OpCode::AND, mode: AddressingMode::Absolute, cycles: 4 });
-        opcodes.insert(0x3D, Instruction { opcode: OpCode::AND, mode: AddressingMode::AbsoluteX, cycles: 4 }); // +1 cycle if page crossed
-        opcodes.insert(0x39, Instruction { opcode: OpCode::AND, mode: AddressingMode::AbsoluteY, cycles: 4 }); // +1 cycle if page crossed
-        opcodes.insert(0x21, Instruction { opcode: OpCode::AND, mode: AddressingMode::IndexedIndirectX, cycles: 6 });
-        opcodes.insert(0x31, Instruction { opcode: OpCode::AND, mode: AddressingMode::IndirectIndexedY, cycles: 5 }); // +1 cycle if page crossed
+        opcodes.insert(0x29, Instruction { opcode: OpCode::AND, mode: AddressingMode::Immediate, cycles: 2, official: true });
+        opcodes.insert(0x25, Instruction { opcode: OpCode::AND, mode: AddressingMode::ZeroPage, cycles: 3, official: true });
+        opcodes.insert(0x35, Instruction { opcode: OpCode::AND, mode: AddressingMode::ZeroPageX, cycles: 4, official: true });
+        opcodes.insert(0x2D, Instruction { opcode: OpCode::AND, mode: AddressingMode::Absolute, cycles: 4, official: true });
+        opcodes.insert(0x3D, Instruction { opcode: OpCode::AND, mode: AddressingMode::AbsoluteX, cycles: 4, official: true }); // +1 cycle if page crossed
+        opcodes.insert(0x39, Instruction { opcode: OpCode::AND, mode: AddressingMode::AbsoluteY, cycles: 4, official: true }); // +1 cycle if page crossed
+        opcodes.insert(0x21, Instruction { opcode: OpCode::AND, mode: AddressingMode::IndexedIndirectX, cycles: 6, official: true });
+        opcodes.insert(0x31, Instruction { opcode: OpCode::AND, mode: AddressingMode::IndirectIndexedY, cycles: 5, official: true }); // +1 cycle if page crossed
 
         // ASL - Arithmetic Shift Left
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#ASL
-        opcodes.insert(0x0A, Instruction { opcode: OpCode::ASL, mode: AddressingMode::Accumulator, cycles: 2 });
-        opcodes.insert(0x06, Instruction { opcode: OpCode::ASL, mode: AddressingMode::ZeroPage, cycles: 5 });
-        opcodes.insert(0x16, Instruction { opcode: OpCode::ASL, mode: AddressingMode::ZeroPageX, cycles: 6 });
-        opcodes.insert(0x0E, Instruction { opcode: OpCode::ASL, mode: AddressingMode::Absolute, cycles: 6 });
-        opcodes.insert(0x1E, Instruction { opcode: OpCode::ASL, mode: AddressingMode::AbsoluteX, cycles: 7 });
+        opcodes.insert(0x0A, Instruction { opcode: OpCode::ASL, mode: AddressingMode::Accumulator, cycles: 2, official: true });
+        opcodes.insert(0x06, Instruction { opcode: OpCode::ASL, mode: AddressingMode::ZeroPage, cycles: 5, official: true });
+        opcodes.insert(0x16, Instruction { opcode: OpCode::ASL, mode: AddressingMode::ZeroPageX, cycles: 6, official: true });
+        opcodes.insert(0x0E, Instruction { opcode: OpCode::ASL, mode: AddressingMode::Absolute, cycles: 6, official: true });
+        opcodes.insert(0x1E, Instruction { opcode: OpCode::ASL, mode: AddressingMode::AbsoluteX, cycles: 7, official: true });
 
         // BCC - Branch if Carry Clear
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#BCC
-        opcodes.insert(0x90, Instruction { opcode: OpCode::BCC, mode: AddressingMode::Relative, cycles: 2 }); // +1 cycle if branch succeeds +2 if to a new page
+        opcodes.insert(0x90, Instruction { opcode: OpCode::BCC, mode: AddressingMode::Relative, cycles: 2, official: true }); // +1 cycle if branch succeeds +2 if to a new page
 
         // BCS - Branch if Carry Set
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#BCS
-        opcodes.insert(0xB0, Instruction { opcode: OpCode::BCS, mode: AddressingMode::Relative, cycles: 2 }); // +1 cycle if branch succeeds +2 if to a new page
+        opcodes.insert(0xB0, Instruction { opcode: OpCode::BCS, mode: AddressingMode::Relative, cycles: 2, official: true }); // +1 cycle if branch succeeds +2 if to a new page
 
         // BEQ - Branch if Equal
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#BEQ
-        opcodes.insert(0xF0, Instruction { opcode: OpCode::BEQ, mode: AddressingMode::Relative, cycles: 2 }); // +1 cycle if branch succeeds +2 if to a new page
+        opcodes.insert(0xF0, Instruction { opcode: OpCode::BEQ, mode: AddressingMode::Relative, cycles: 2, official: true }); // +1 cycle if branch succeeds +2 if to a new page
 
         // BIT - BIT Test
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#BIT
-        opcodes.insert(0x24, Instruction { opcode: OpCode::BIT, mode: AddressingMode::ZeroPage, cycles: 3 });
-        opcodes.insert(0x2C, Instruction { opcode: OpCode::BIT, mode: AddressingMode::Absolute, cycles: 4 });
+        opcodes.insert(0x24, Instruction { opcode: OpCode::BIT, mode: AddressingMode::ZeroPage, cycles: 3, official: true });
+        opcodes.insert(0x2C, Instruction { opcode: OpCode::BIT, mode: AddressingMode::Absolute, cycles: 4, official: true });
 
         // BMI - Branch if Minus
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#BMI
-        opcodes.insert(0x30, Instruction { opcode: OpCode::BMI, mode: AddressingMode::Relative, cycles: 2 }); // +1 cycle if branch succeeds +2 if to a new page
+        opcodes.insert(0x30, Instruction { opcode: OpCode::BMI, mode: AddressingMode::Relative, cycles: 2, official: true }); // +1 cycle if branch succeeds +2 if to a new page
 
         // BNE - Branch if Not Equal
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#BNE
-        opcodes.insert(0xD0, Instruction { opcode: OpCode::BNE, mode: AddressingMode::Relative, cycles: 2 }); // +1 cycle if branch succeeds +2 if to a new page
+        opcodes.insert(0xD0, Instruction { opcode: OpCode::BNE, mode: AddressingMode::Relative, cycles: 2, official: true }); // +1 cycle if branch succeeds +2 if to a new page
 
         // BPL - Branch if Positive
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#BPL
-        opcodes.insert(0x10, Instruction { opcode: OpCode::BPL, mode: AddressingMode::Relative, cycles: 2 }); // +1 cycle if branch succeeds +2 if to a new page
+        opcodes.insert(0x10, Instruction { opcode: OpCode::BPL, mode: AddressingMode::Relative, cycles: 2, official: true }); // +1 cycle if branch succeeds +2 if to a new page
 
         // BRK - Force Interrupt
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#BRK
-        opcodes.insert(0x00, Instruction { opcode: OpCode::BRK, mode: AddressingMode::Implied, cycles: 7 });
+        opcodes.insert(0x00, Instruction { opcode: OpCode::BRK, mode: AddressingMode::Implied, cycles: 7, official: true });
 
         // BVC - Branch if Overflow Clear
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#BVC
-        opcodes.insert(0x50, Instruction { opcode: OpCode::BVC, mode: AddressingMode::Relative, cycles: 2 }); // +1 cycle if branch succeeds +2 if to a new page
+        opcodes.insert(0x50, Instruction { opcode: OpCode::BVC, mode: AddressingMode::Relative, cycles: 2, official: true }); // +1 cycle if branch succeeds +2 if to a new page
 
         // BVS - Branch if Overflow Set
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#BVS
-        opcodes.insert(0x70, Instruction { opcode: OpCode::BVS, mode: AddressingMode::Relative, cycles: 2 }); // +1 cycle if branch succeeds +2 if to a new page
+        opcodes.insert(0x70, Instruction { opcode: OpCode::BVS, mode: AddressingMode::Relative, cycles: 2, official: true }); // +1 cycle if branch succeeds +2 if to a new page
 
         // CLC - Clear Carry Flag
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#CLC
-        opcodes.insert(0x18, Instruction { opcode: OpCode::CLC, mode: AddressingMode::Implied, cycles: 2 });
+        opcodes.insert(0x18, Instruction { opcode: OpCode::CLC, mode: AddressingMode::Implied, cycles: 2, official: true });
 
         // CLD - Clear Decimal Mode
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#CLD
-        opcodes.insert(0xD8, Instruction { opcode: OpCode::CLD, mode: AddressingMode::Implied, cycles: 2 });
+        opcodes.insert(0xD8, Instruction { opcode: OpCode::CLD, mode: AddressingMode::Implied, cycles: 2, official: true });
 
         // CLI - Clear Interrupt Disable
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#CLI
-        opcodes.insert(0x58, Instruction { opcode: OpCode::CLI, mode: AddressingMode::Implied, cycles: 2 });
+        opcodes.insert(0x58, Instruction { opcode: OpCode::CLI, mode: AddressingMode::Implied, cycles: 2, official: true });
 
         // CLV - Clear Overflow Flag
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#CLI
-        opcodes.insert(0xB8, Instruction { opcode: OpCode::CLV, mode: AddressingMode::Implied, cycles: 2 });
+        opcodes.insert(0xB8, Instruction { opcode: OpCode::CLV, mode: AddressingMode::Implied, cycles: 2, official: true });
 
         // CMP - Compare
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#CMP
-        opcodes.insert(0xC9, Instruction { opcode: OpCode::CMP, mode: AddressingMode::Immediate, cycles: 2 });
-        opcodes.insert(0xC5, Instruction { opcode: OpCode::CMP, mode: AddressingMode::ZeroPage, cycles: 3 });
-        opcodes.insert(0xD5, Instruction { opcode: OpCode::CMP, mode: AddressingMode::ZeroPageX, cycles: 4 });
-        opcodes.insert(0xCD, Instruction { opcode: OpCode::CMP, mode: AddressingMode::Absolute, cycles: 4 });
-        opcodes.insert(0xDD, Instruction { opcode: OpCode::CMP, mode: AddressingMode::AbsoluteX, cycles: 4 }); // +1 cycle if page crossed
-        opcodes.insert(0xD9, Instruction { opcode: OpCode::CMP, mode: AddressingMode::AbsoluteY, cycles: 4 }); // +1 cycle if page crossed
-        opcodes.insert(0xC1, Instruction { opcode: OpCode::CMP, mode: AddressingMode::IndexedIndirectX, cycles: 6 });
-        opcodes.insert(0xD1, Instruction { opcode: OpCode::CMP, mode: AddressingMode::IndirectIndexedY, cycles: 5 }); // +1 cycle if page crossed
+        opcodes.insert(0xC9, Instruction { opcode: OpCode::CMP, mode: AddressingMode::Immediate, cycles: 2, official: true });
+        opcodes.insert(0xC5, Instruction { opcode: OpCode::CMP, mode: AddressingMode::ZeroPage, cycles: 3, official: true });
+        opcodes.insert(0xD5, Instruction { opcode: OpCode::CMP, mode: AddressingMode::ZeroPageX, cycles: 4, official: true });
+        opcodes.insert(0xCD, Instruction { opcode: OpCode::CMP, mode: AddressingMode::Absolute, cycles: 4, official: true });
+        opcodes.insert(0xDD, Instruction { opcode: OpCode::CMP, mode: AddressingMode::AbsoluteX, cycles: 4, official: true }); // +1 cycle if page crossed
+        opcodes.insert(0xD9, Instruction { opcode: OpCode::CMP, mode: AddressingMode::AbsoluteY, cycles: 4, official: true }); // +1 cycle if page crossed
+        opcodes.insert(0xC1, Instruction { opcode: OpCode::CMP, mode: AddressingMode::IndexedIndirectX, cycles: 6, official: true });
+        opcodes.insert(0xD1, Instruction { opcode: OpCode::CMP, mode: AddressingMode::IndirectIndexedY, cycles: 5, official: true }); // +1 cycle if page crossed
 
         // CPX - Compare X Register
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#CPX
-        opcodes.insert(0xE0, Instruction { opcode: OpCode::CPX, mode: AddressingMode::Immediate, cycles: 2 });
-        opcodes.insert(0xE4, Instruction { opcode: OpCode::CPX, mode: AddressingMode::ZeroPage, cycles: 3 });
-        opcodes.insert(0xEC, Instruction { opcode: OpCode::CPX, mode: AddressingMode::Absolute, cycles: 4 });
+        opcodes.insert(0xE0, Instruction { opcode: OpCode::CPX, mode: AddressingMode::Immediate, cycles: 2, official: true });
+        opcodes.insert(0xE4, Instruction { opcode: OpCode::CPX, mode: AddressingMode::ZeroPage, cycles: 3, official: true });
+        opcodes.insert(0xEC, Instruction { opcode: OpCode::CPX, mode: AddressingMode::Absolute, cycles: 4, official: true });
 
         // CPY - Compare Y Register
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#CPY
-        opcodes.insert(0xC0, Instruction { opcode: OpCode::CPY, mode: AddressingMode::Immediate, cycles: 2 });
-        opcodes.insert(0xC4, Instruction { opcode: OpCode::CPY, mode: AddressingMode::ZeroPage, cycles: 3 });
-        opcodes.insert(0xCC, Instruction { opcode: OpCode::CPY, mode: AddressingMode::Absolute, cycles: 4 });
+        opcodes.insert(0xC0, Instruction { opcode: OpCode::CPY, mode: AddressingMode::Immediate, cycles: 2, official: true });
+        opcodes.insert(0xC4, Instruction { opcode: OpCode::CPY, mode: AddressingMode::ZeroPage, cycles: 3, official: true });
+        opcodes.insert(0xCC, Instruction { opcode: OpCode::CPY, mode: AddressingMode::Absolute, cycles: 4, official: true });
 
         // DEC - Decrement Memory
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#DEC
-        opcodes.insert(0xC6, Instruction { opcode: OpCode::DEC, mode: AddressingMode::ZeroPage, cycles: 5 });
-        opcodes.insert(0xD6, Instruction { opcode: OpCode::DEC, mode: AddressingMode::ZeroPageX, cycles: 6 });
-        opcodes.insert(0xCE, Instruction { opcode: OpCode::DEC, mode: AddressingMode::Absolute, cycles: 6 });
-        opcodes.insert(0xDE, Instruction { opcode: OpCode::DEC, mode: AddressingMode::AbsoluteX, cycles: 7 });
+        opcodes.insert(0xC6, Instruction { opcode: OpCode::DEC, mode: AddressingMode::ZeroPage, cycles: 5, official: true });
+        opcodes.insert(0xD6, Instruction { opcode: OpCode::DEC, mode: AddressingMode::ZeroPageX, cycles: 6, official: true });
+        opcodes.insert(0xCE, Instruction { opcode: OpCode::DEC, mode: AddressingMode::Absolute, cycles: 6, official: true });
+        opcodes.insert(0xDE, Instruction { opcode: OpCode::DEC, mode: AddressingMode::AbsoluteX, cycles: 7, official: true });
 
         // DEX - Decrement X Register
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#DEX
-        opcodes.insert(0xCA, Instruction { opcode: OpCode::DEX, mode: AddressingMode::Implied, cycles: 2 });
+        opcodes.insert(0xCA, Instruction { opcode: OpCode::DEX, mode: AddressingMode::Implied, cycles: 2, official: true });
 
         // DEY - Decrement Y Register
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#DEY
-        opcodes.insert(0x88, Instruction { opcode: OpCode::DEY, mode: AddressingMode::Implied, cycles: 2 });
+        opcodes.insert(0x88, Instruction { opcode: OpCode::DEY, mode: AddressingMode::Implied, cycles: 2, official: true });
 
         // EOR - Exclusive OR
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#EOR
-        opcodes.insert(0x49, Instruction { opcode: OpCode::EOR, mode: AddressingMode::Immediate, cycles: 2 });
-        opcodes.insert(0x45, Instruction { opcode: OpCode::EOR, mode: AddressingMode::ZeroPage, cycles: 3 });
-        opcodes.insert(0x55, Instruction { opcode: OpCode::EOR, mode: AddressingMode::ZeroPageX, cycles: 4 });
-        opcodes.insert(0x4D, Instruction { opcode: OpCode::EOR, mode: AddressingMode::Absolute, cycles: 4 });
-        opcodes.insert(0x5D, Instruction { opcode: OpCode::EOR, mode: AddressingMode::AbsoluteX, cycles: 4 }); // +1 cycle if page crossed
-        opcodes.insert(0x59, Instruction { opcode: OpCode::EOR, mode: AddressingMode::AbsoluteY, cycles: 4 }); // +1 cycle if page crossed
-        opcodes.insert(0x41, Instruction { opcode: OpCode::EOR, mode: AddressingMode::IndexedIndirectX, cycles: 6 });
-        opcodes.insert(0x51, Instruction { opcode: OpCode::EOR, mode: AddressingMode::IndirectIndexedY, cycles: 5 }); // +1 cycle if page crossed
+        opcodes.insert(0x49, Instruction { opcode: OpCode::EOR, mode: AddressingMode::Immediate, cycles: 2, official: true });
+        opcodes.insert(0x45, Instruction { opcode: OpCode::EOR, mode: AddressingMode::ZeroPage, cycles: 3, official: true });
+        opcodes.insert(0x55, Instruction { opcode: OpCode::EOR, mode: AddressingMode::ZeroPageX, cycles: 4, official: true });
+        opcodes.insert(0x4D, Instruction { opcode: OpCode::EOR, mode: AddressingMode::Absolute, cycles: 4, official: true });
+        opcodes.insert(0x5D, Instruction { opcode: OpCode::EOR, mode: AddressingMode::AbsoluteX, cycles: 4, official: true }); // +1 cycle if page crossed
+        opcodes.insert(0x59, Instruction { opcode: OpCode::EOR, mode: AddressingMode::AbsoluteY, cycles: 4, official: true }); // +1 cycle if page crossed
+        opcodes.insert(0x41, Instruction { opcode: OpCode::EOR, mode: AddressingMode::IndexedIndirectX, cycles: 6, official: true });
+        opcodes.insert(0x51, Instruction { opcode: OpCode::EOR, mode: AddressingMode::IndirectIndexedY, cycles: 5, official: true }); // +1 cycle if page crossed
 
         // INC - Increment Memory
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#INC
-        opcodes.insert(0xE6, Instruction { opcode: OpCode::INC, mode: AddressingMode::ZeroPage, cycles: 5 });
-        opcodes.insert(0xF6, Instruction { opcode: OpCode::INC, mode: AddressingMode::ZeroPageX, cycles: 6 });
-        opcodes.insert(0xEE, Instruction { opcode: OpCode::INC, mode: AddressingMode::Absolute, cycles: 6 });
-        opcodes.insert(0xFE, Instruction { opcode: OpCode::INC, mode: AddressingMode::AbsoluteX, cycles: 7 });
+        opcodes.insert(0xE6, Instruction { opcode: OpCode::INC, mode: AddressingMode::ZeroPage, cycles: 5, official: true });
+        opcodes.insert(0xF6, Instruction { opcode: OpCode::INC, mode: AddressingMode::ZeroPageX, cycles: 6, official: true });
+        opcodes.insert(0xEE, Instruction { opcode: OpCode::INC, mode: AddressingMode::Absolute, cycles: 6, official: true });
+        opcodes.insert(0xFE, Instruction { opcode: OpCode::INC, mode: AddressingMode::AbsoluteX, cycles: 7, official: true });
 
         // INX - Increment X Register
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#INX
-        opcodes.insert(0xE8, Instruction { opcode: OpCode::INX, mode: AddressingMode::Implied, cycles: 2 });
+        opcodes.insert(0xE8, Instruction { opcode: OpCode::INX, mode: AddressingMode::Implied, cycles: 2, official: true });
 
         // INY - Increment Y Register
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#INY
-        opcodes.insert(0xC8, Instruction { opcode: OpCode::INY, mode: AddressingMode::Implied, cycles: 2 });
+        opcodes.insert(0xC8, Instruction { opcode: OpCode::INY, mode: AddressingMode::Implied, cycles: 2, official: true });
 
         // JMP - Jump
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#JMP
-        opcodes.insert(0x4C, Instruction { opcode: OpCode::JMP, mode: AddressingMode::Absolute, cycles: 3 });
-        opcodes.insert(0x6C, Instruction { opcode: OpCode::JMP, mode: AddressingMode::Indirect, cycles: 5 });
+        opcodes.insert(0x4C, Instruction { opcode: OpCode::JMP, mode: AddressingMode::Absolute, cycles: 3, official: true });
+        opcodes.insert(0x6C, Instruction { opcode: OpCode::JMP, mode: AddressingMode::Indirect, cycles: 5, official: true });
 
         // JSR - Jump to Subroutine
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#JSR
-        opcodes.insert(0x20, Instruction { opcode: OpCode::JSR, mode: AddressingMode::Absolute, cycles: 6 });
+        opcodes.insert(0x20, Instruction { opcode: OpCode::JSR, mode: AddressingMode::Absolute, cycles: 6, official: true });
 
         // LDA - Load Accumulator
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#LDA
-        opcodes.insert(0xA9, Instruction { opcode: OpCode::LDA, mode: AddressingMode::Immediate, cycles: 2 });
-        opcodes.insert(0xA5, Instruction { opcode: OpCode::LDA, mode: AddressingMode::ZeroPage, cycles: 3 });
-        opcodes.insert(0xB5, Instruction { opcode: OpCode::LDA, mode: AddressingMode::ZeroPageX, cycles: 4 });
-        opcodes.insert(0xAD, Instruction { opcode: OpCode::LDA, mode: AddressingMode::Absolute, cycles: 4 });
-        opcodes.insert(0xBD, Instruction { opcode: OpCode::LDA, mode: AddressingMode::AbsoluteX, cycles: 4 }); // +1 cycle if page crossed
-        opcodes.insert(0xB9, Instruction { opcode: OpCode::LDA, mode: AddressingMode::AbsoluteY, cycles: 4 }); // +1 cycle if page crossed
-        opcodes.insert(0xA1, Instruction { opcode: OpCode::LDA, mode: AddressingMode::IndexedIndirectX, cycles: 6 });
-        opcodes.insert(0xB1, Instruction { opcode: OpCode::LDA, mode: AddressingMode::IndirectIndexedY, cycles: 5 }); // +1 cycle if page crossed
+        opcodes.insert(0xA9, Instruction { opcode: OpCode::LDA, mode: AddressingMode::Immediate, cycles: 2, official: true });
+        opcodes.insert(0xA5, Instruction { opcode: OpCode::LDA, mode: AddressingMode::ZeroPage, cycles: 3, official: true });
+        opcodes.insert(0xB5, Instruction { opcode: OpCode::LDA, mode: AddressingMode::ZeroPageX, cycles: 4, official: true });
+        opcodes.insert(0xAD, Instruction { opcode: OpCode::LDA, mode: AddressingMode::Absolute, cycles: 4, official: true });
+        opcodes.insert(0xBD, Instruction { opcode: OpCode::LDA, mode: AddressingMode::AbsoluteX, cycles: 4, official: true }); // +1 cycle if page crossed
+        opcodes.insert(0xB9, Instruction { opcode: OpCode::LDA, mode: AddressingMode::AbsoluteY, cycles: 4, official: true }); // +1 cycle if page crossed
+        opcodes.insert(0xA1, Instruction { opcode: OpCode::LDA, mode: AddressingMode::IndexedIndirectX, cycles: 6, official: true });
+        opcodes.insert(0xB1, Instruction { opcode: OpCode::LDA, mode: AddressingMode::IndirectIndexedY, cycles: 5, official: true }); // +1 cycle if page crossed
 
         // LDX - Load X Register
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#LDX
-        opcodes.insert(0xA2, Instruction { opcode: OpCode::LDX, mode: AddressingMode::Immediate, cycles: 2 });
-        opcodes.insert(0xA6, Instruction { opcode: OpCode::LDX, mode: AddressingMode::ZeroPage, cycles: 3 });
-        opcodes.insert(0xB6, Instruction { opcode: OpCode::LDX, mode: AddressingMode::ZeroPageY, cycles: 4 });
-        opcodes.insert(0xAE, Instruction { opcode: OpCode::LDX, mode: AddressingMode::Absolute, cycles: 4 });
-        opcodes.insert(0xBE, Instruction { opcode: OpCode::LDX, mode: AddressingMode::AbsoluteY, cycles: 4 }); // +1 cycle if page crossed
+        opcodes.insert(0xA2, Instruction { opcode: OpCode::LDX, mode: AddressingMode::Immediate, cycles: 2, official: true });
+        opcodes.insert(0xA6, Instruction { opcode: OpCode::LDX, mode: AddressingMode::ZeroPage, cycles: 3, official: true });
+        opcodes.insert(0xB6, Instruction { opcode: OpCode::LDX, mode: AddressingMode::ZeroPageY, cycles: 4, official: true });
+        opcodes.insert(0xAE, Instruction { opcode: OpCode::LDX, mode: AddressingMode::Absolute, cycles: 4, official: true });
+        opcodes.insert(0xBE, Instruction { opcode: OpCode::LDX, mode: AddressingMode::AbsoluteY, cycles: 4, official: true }); // +1 cycle if page crossed
 
         // LDY - Load Y Register
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#LDY
-        opcodes.insert(0xA0, Instruction { opcode: OpCode::LDY, mode: AddressingMode::Immediate, cycles: 2 });
-        opcodes.insert(0xA4, Instruction { opcode: OpCode::LDY, mode: AddressingMode::ZeroPage, cycles: 3 });
-        opcodes.insert(0xB4, Instruction { opcode: OpCode::LDY, mode: AddressingMode::ZeroPageX, cycles: 4 });
-        opcodes.insert(0xAC, Instruction { opcode: OpCode::LDY, mode: AddressingMode::Absolute, cycles: 4 });
-        opcodes.insert(0xBC, Instruction { opcode: OpCode::LDY, mode: AddressingMode::AbsoluteX, cycles: 4 }); // +1 cycle if page crossed
+        opcodes.insert(0xA0, Instruction { opcode: OpCode::LDY, mode: AddressingMode::Immediate, cycles: 2, official: true });
+        opcodes.insert(0xA4, Instruction { opcode: OpCode::LDY, mode: AddressingMode::ZeroPage, cycles: 3, official: true });
+        opcodes.insert(0xB4, Instruction { opcode: OpCode::LDY, mode: AddressingMode::ZeroPageX, cycles: 4, official: true });
+        opcodes.insert(0xAC, Instruction { opcode: OpCode::LDY, mode: AddressingMode::Absolute, cycles: 4, official: true });
+        opcodes.insert(0xBC, Instruction { opcode: OpCode::LDY, mode: AddressingMode::AbsoluteX, cycles: 4, official: true }); // +1 cycle if page crossed
 
         // LSR - Logical Shift Right
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#LSR
-        opcodes.insert(0x4A, Instruction { opcode: OpCode::LSR, mode: AddressingMode::Accumulator, cycles: 2 });
-        opcodes.insert(0x46, Instruction { opcode: OpCode::LSR, mode: AddressingMode::ZeroPage, cycles: 5 });
-        opcodes.insert(0x56, Instruction { opcode: OpCode::LSR, mode: AddressingMode::ZeroPageX, cycles: 6 });
-        opcodes.insert(0x4E, Instruction { opcode: OpCode::LSR, mode: AddressingMode::Absolute, cycles: 6 });
-        opcodes.insert(0x5E, Instruction { opcode: OpCode::LSR, mode: AddressingMode::AbsoluteX, cycles: 7 });
+        opcodes.insert(0x4A, Instruction { opcode: OpCode::LSR, mode: AddressingMode::Accumulator, cycles: 2, official: true });
+        opcodes.insert(0x46, Instruction { opcode: OpCode::LSR, mode: AddressingMode::ZeroPage, cycles: 5, official: true });
+        opcodes.insert(0x56, Instruction { opcode: OpCode::LSR, mode: AddressingMode::ZeroPageX, cycles: 6, official: true });
+        opcodes.insert(0x4E, Instruction { opcode: OpCode::LSR, mode: AddressingMode::Absolute, cycles: 6, official: true });
+        opcodes.insert(0x5E, Instruction { opcode: OpCode::LSR, mode: AddressingMode::AbsoluteX, cycles: 7, official: true });
 
         // NOP - No Operation
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#LSR
-        opcodes.insert(0xEA, Instruction { opcode: OpCode::NOP, mode: AddressingMode::Implied, cycles: 2 });
+        opcodes.insert(0xEA, Instruction { opcode: OpCode::NOP, mode: AddressingMode::Implied, cycles: 2, official: true });
         // Illegal opcodes
-        opcodes.insert(0x1A, Instruction { opcode: OpCode::NOP, mode: AddressingMode::Implied, cycles: 2 });
-        opcodes.insert(0x3A, Instruction { opcode: OpCode::NOP, mode: AddressingMode::Implied, cycles: 2 });
-        opcodes.insert(0x5A, Instruction { opcode: OpCode::NOP, mode: AddressingMode::Implied, cycles: 2 });
-        opcodes.insert(0x7A, Instruction { opcode: OpCode::NOP, mode: AddressingMode::Implied, cycles: 2 });
-        opcodes.insert(0xDA, Instruction { opcode: OpCode::NOP, mode: AddressingMode::Implied, cycles: 2 });
-        opcodes.insert(0xFA, Instruction { opcode: OpCode::NOP, mode: AddressingMode::Implied, cycles: 2 });
+        opcodes.insert(0x1A, Instruction { opcode: OpCode::NOP, mode: AddressingMode::Implied, cycles: 2, official: false });
+        opcodes.insert(0x3A, Instruction { opcode: OpCode::NOP, mode: AddressingMode::Implied, cycles: 2, official: false });
+        opcodes.insert(0x5A, Instruction { opcode: OpCode::NOP, mode: AddressingMode::Implied, cycles: 2, official: false });
+        opcodes.insert(0x7A, Instruction { opcode: OpCode::NOP, mode: AddressingMode::Implied, cycles: 2, official: false });
+        opcodes.insert(0xDA, Instruction { opcode: OpCode::NOP, mode: AddressingMode::Implied, cycles: 2, official: false });
+        opcodes.insert(0xFA, Instruction { opcode: OpCode::NOP, mode: AddressingMode::Implied, cycles: 2, official: false });
 
         // ORA - Logical Inclusive OR
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#ORA
-        opcodes.insert(0x09, Instruction { opcode: OpCode::ORA, mode: AddressingMode::Immediate, cycles: 2 });
-        opcodes.insert(0x05, Instruction { opcode: OpCode::ORA, mode: AddressingMode::ZeroPage, cycles: 3 });
-        opcodes.insert(0x15, Instruction { opcode: OpCode::ORA, mode: AddressingMode::ZeroPageX, cycles: 4 });
-        opcodes.insert(0x0D, Instruction { opcode: OpCode::ORA, mode: AddressingMode::Absolute, cycles: 4 });
-        opcodes.insert(0x1D, Instruction { opcode: OpCode::ORA, mode: AddressingMode::AbsoluteX, cycles: 4 }); // +1 cycle if page crossed
-        opcodes.insert(0x19, Instruction { opcode: OpCode::ORA, mode: AddressingMode::AbsoluteY, cycles: 4 }); // +1 cycle if page crossed
-        opcodes.insert(0x01, Instruction { opcode: OpCode::ORA, mode: AddressingMode::IndexedIndirectX, cycles: 6 });
-        opcodes.insert(0x11, Instruction { opcode: OpCode::ORA, mode: AddressingMode::IndirectIndexedY, cycles: 5 }); // +1 cycle if page crossed
+        opcodes.insert(0x09, Instruction { opcode: OpCode::ORA, mode: AddressingMode::Immediate, cycles: 2, official: true });
+        opcodes.insert(0x05, Instruction { opcode: OpCode::ORA, mode: AddressingMode::ZeroPage, cycles: 3, official: true });
+        opcodes.insert(0x15, Instruction { opcode: OpCode::ORA, mode: AddressingMode::ZeroPageX, cycles: 4, official: true });
+        opcodes.insert(0x0D, Instruction { opcode: OpCode::ORA, mode: AddressingMode::Absolute, cycles: 4, official: true });
+        opcodes.insert(0x1D, Instruction { opcode: OpCode::ORA, mode: AddressingMode::AbsoluteX, cycles: 4, official: true }); // +1 cycle if page crossed
+        opcodes.insert(0x19, Instruction { opcode: OpCode::ORA, mode: AddressingMode::AbsoluteY, cycles: 4, official: true }); // +1 cycle if page crossed
+        opcodes.insert(0x01, Instruction { opcode: OpCode::ORA, mode: AddressingMode::IndexedIndirectX, cycles: 6, official: true });
+        opcodes.insert(0x11, Instruction { opcode: OpCode::ORA, mode: AddressingMode::IndirectIndexedY, cycles: 5, official: true }); // +1 cycle if page crossed
 
         // PHA - Push Accumulator
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#PHA
-        opcodes.insert(0x48, Instruction { opcode: OpCode::PHA, mode: AddressingMode::Implied, cycles: 3 });
+        opcodes.insert(0x48, Instruction { opcode: OpCode::PHA, mode: AddressingMode::Implied, cycles: 3, official: true });
 
         // PHP - Push Processor Status
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#PHP
-        opcodes.insert(0x08, Instruction { opcode: OpCode::PHP, mode: AddressingMode::Implied, cycles: 3 });
+        opcodes.insert(0x08, Instruction { opcode: OpCode::PHP, mode: AddressingMode::Implied, cycles: 3, official: true });
 
         // PLA - Pull Accumulator
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#PLA
-        opcodes.insert(0x68, Instruction { opcode: OpCode::PLA, mode: AddressingMode::Implied, cycles: 4 });
+        opcodes.insert(0x68, Instruction { opcode: OpCode::PLA, mode: AddressingMode::Implied, cycles: 4, official: true });
 
         // PLP - Pull Processor Status
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#PLP
-        opcodes.insert(0x28, Instruction { opcode: OpCode::PLP, mode: AddressingMode::Implied, cycles: 4 });
+        opcodes.insert(0x28, Instruction { opcode: OpCode::PLP, mode: AddressingMode::Implied, cycles: 4, official: true });
 
         // ROL - Rotate Left
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#ROL
-        opcodes.insert(0x2A, Instruction { opcode: OpCode::ROL, mode: AddressingMode::Accumulator, cycles: 2 });
-        opcodes.insert(0x26, Instruction { opcode: OpCode::ROL, mode: AddressingMode::ZeroPage, cycles: 5 });
-        opcodes.insert(0x36, Instruction { opcode: OpCode::ROL, mode: AddressingMode::ZeroPageX, cycles: 6 });
-        opcodes.insert(0x2E, Instruction { opcode: OpCode::ROL, mode: AddressingMode::Absolute, cycles: 6 });
-        opcodes.insert(0x3E, Instruction { opcode: OpCode::ROL, mode: AddressingMode::AbsoluteX, cycles: 7 });
+        opcodes.insert(0x2A, Instruction { opcode: OpCode::ROL, mode: AddressingMode::Accumulator, cycles: 2, official: true });
+        opcodes.insert(0x26, Instruction { opcode: OpCode::ROL, mode: AddressingMode::ZeroPage, cycles: 5, official: true });
+        opcodes.insert(0x36, Instruction { opcode: OpCode::ROL, mode: AddressingMode::ZeroPageX, cycles: 6, official: true });
+        opcodes.insert(0x2E, Instruction { opcode: OpCode::ROL, mode: AddressingMode::Absolute, cycles: 6, official: true });
+        opcodes.insert(0x3E, Instruction { opcode: OpCode::ROL, mode: AddressingMode::AbsoluteX, cycles: 7, official: true });
 
         // ROR - Rotate Right
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#ROR
-        opcodes.insert(0x6A, Instruction { opcode: OpCode::ROR, mode: AddressingMode::Accumulator, cycles: 2 });
-        opcodes.insert(0x66, Instruction { opcode: OpCode::ROR, mode: AddressingMode::ZeroPage, cycles: 5 });
-        opcodes.insert(0x76, Instruction { opcode: OpCode::ROR, mode: AddressingMode::ZeroPageX, cycles: 6 });
-        opcodes.insert(0x6E, Instruction { opcode: OpCode::ROR, mode: AddressingMode::Absolute, cycles: 6 });
-        opcodes.insert(0x7E, Instruction { opcode: OpCode::ROR, mode: AddressingMode::AbsoluteX, cycles: 7 });
+        opcodes.insert(0x6A, Instruction { opcode: OpCode::ROR, mode: AddressingMode::Accumulator, cycles: 2, official: true });
+        opcodes.insert(0x66, Instruction { opcode: OpCode::ROR, mode: AddressingMode::ZeroPage, cycles: 5, official: true });
+        opcodes.insert(0x76, Instruction { opcode: OpCode::ROR, mode: AddressingMode::ZeroPageX, cycles: 6, official: true });
+        opcodes.insert(0x6E, Instruction { opcode: OpCode::ROR, mode: AddressingMode::Absolute, cycles: 6, official: true });
+        opcodes.insert(0x7E, Instruction { opcode: OpCode::ROR, mode: AddressingMode::AbsoluteX, cycles: 7, official: true });
 
         // RTI - Return from Interrupt
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#RTI
-        opcodes.insert(0x40, Instruction { opcode: OpCode::RTI, mode: AddressingMode::Implied, cycles: 6 });
+        opcodes.insert(0x40, Instruction { opcode: OpCode::RTI, mode: AddressingMode::Implied, cycles: 6, official: true });
 
         // RTS - Return from Subroutine
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#RTS
-        opcodes.insert(0x60, Instruction { opcode: OpCode::RTS, mode: AddressingMode::Implied, cycles: 6 });
+        opcodes.insert(0x60, Instruction { opcode: OpCode::RTS, mode: AddressingMode::Implied, cycles: 6, official: true });
 
         // SBC - Subtract with Carry
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#SBC
-        opcodes.insert(0xE9, Instruction { opcode: OpCode::SBC, mode: AddressingMode::Immediate, cycles: 2 });
-        opcodes.insert(0xE5, Instruction { opcode: OpCode::SBC, mode: AddressingMode::ZeroPage, cycles: 3 });
-        opcodes.insert(0xF5, Instruction { opcode: OpCode::SBC, mode: AddressingMode::ZeroPageX, cycles: 4 });
-        opcodes.insert(0xED, Instruction { opcode: OpCode::SBC, mode: AddressingMode::Absolute, cycles: 4 });
-        opcodes.insert(0xFD, Instruction { opcode: OpCode::SBC, mode: AddressingMode::AbsoluteX, cycles: 4 }); // +1 cycle if page crossed
-        opcodes.insert(0xF9, Instruction { opcode: OpCode::SBC, mode: AddressingMode::AbsoluteY, cycles: 4 }); // +1 cycle if page crossed
-        opcodes.insert(0xE1, Instruction { opcode: OpCode::SBC, mode: AddressingMode::IndexedIndirectX, cycles: 6 });
-        opcodes.insert(0xF1, Instruction { opcode: OpCode::SBC, mode: AddressingMode::IndirectIndexedY, cycles: 5 }); // +1 cycle if page crossed
+        opcodes.insert(0xE9, Instruction { opcode: OpCode::SBC, mode: AddressingMode::Immediate, cycles: 2, official: true });
+        opcodes.insert(0xE5, Instruction { opcode: OpCode::SBC, mode: AddressingMode::ZeroPage, cycles: 3, official: true });
+        opcodes.insert(0xF5, Instruction { opcode: OpCode::SBC, mode: AddressingMode::ZeroPageX, cycles: 4, official: true });
+        opcodes.insert(0xED, Instruction { opcode: OpCode::SBC, mode: AddressingMode::Absolute, cycles: 4, official: true });
+        opcodes.insert(0xFD, Instruction { opcode: OpCode::SBC, mode: AddressingMode::AbsoluteX, cycles: 4, official: true }); // +1 cycle if page crossed
+        opcodes.insert(0xF9, Instruction { opcode: OpCode::SBC, mode: AddressingMode::AbsoluteY, cycles: 4, official: true }); // +1 cycle if page crossed
+        opcodes.insert(0xE1, Instruction { opcode: OpCode::SBC, mode: AddressingMode::IndexedIndirectX, cycles: 6, official: true });
+        opcodes.insert(0xF1, Instruction { opcode: OpCode::SBC, mode: AddressingMode::IndirectIndexedY, cycles: 5, official: true }); // +1 cycle if page crossed
         // Illegal opcode
-        opcodes.insert(0xEB, Instruction { opcode: OpCode::SBC, mode: AddressingMode::Immediate, cycles: 2 });
+        opcodes.insert(0xEB, Instruction { opcode: OpCode::SBC, mode: AddressingMode::Immediate, cycles: 2, official: false });
 
         // SEC - Set Carry Flag
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#SEC
-        opcodes.insert(0x38, Instruction { opcode: OpCode::SEC, mode: AddressingMode::Implied, cycles: 2 });
+        opcodes.insert(0x38, Instruction { opcode: OpCode::SEC, mode: AddressingMode::Implied, cycles: 2, official: true });
 
         // SED - Set Decimal Flag
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#SED
-        opcodes.insert(0xF8, Instruction { opcode: OpCode::SED, mode: AddressingMode::Implied, cycles: 2 });
+        opcodes.insert(0xF8, Instruction { opcode: OpCode::SED, mode: AddressingMode::Implied, cycles: 2, official: true });
 
         // SEI - Set Interrupt Disable
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#SEI
-        opcodes.insert(0x78, Instruction { opcode: OpCode::SEI, mode: AddressingMode::Implied, cycles: 2 });
+        opcodes.insert(0x78, Instruction { opcode: OpCode::SEI, mode: AddressingMode::Implied, cycles: 2, official: true });
 
         // STA - Store Accumulator
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#STA
-        opcodes.insert(0x85, Instruction { opcode: OpCode::STA, mode: AddressingMode::ZeroPage, cycles: 3 });
-        opcodes.insert(0x95, Instruction { opcode: OpCode::STA, mode: AddressingMode::ZeroPageX, cycles: 4 });
-        opcodes.insert(0x8D, Instruction { opcode: OpCode::STA, mode: AddressingMode::Absolute, cycles: 4 });
-        opcodes.insert(0x9D, Instruction { opcode: OpCode::STA, mode: AddressingMode::AbsoluteX, cycles: 5 });
-        opcodes.insert(0x99, Instruction { opcode: OpCode::STA, mode: AddressingMode::AbsoluteY, cycles: 5 });
-        opcodes.insert(0x81, Instruction { opcode: OpCode::STA, mode: AddressingMode::IndexedIndirectX, cycles: 6 });
-        opcodes.insert(0x91, Instruction { opcode: OpCode::STA, mode: AddressingMode::IndirectIndexedY, cycles: 6 });
+        opcodes.insert(0x85, Instruction { opcode: OpCode::STA, mode: AddressingMode::ZeroPage, cycles: 3, official: true });
+        opcodes.insert(0x95, Instruction { opcode: OpCode::STA, mode: AddressingMode::ZeroPageX, cycles: 4, official: true });
+        opcodes.insert(0x8D, Instruction { opcode: OpCode::STA, mode: AddressingMode::Absolute, cycles: 4, official: true });
+        opcodes.insert(0x9D, Instruction { opcode: OpCode::STA, mode: AddressingMode::AbsoluteX, cycles: 5, official: true });
+        opcodes.insert(0x99, Instruction { opcode: OpCode::STA, mode: AddressingMode::AbsoluteY, cycles: 5, official: true });
+        opcodes.insert(0x81, Instruction { opcode: OpCode::STA, mode: AddressingMode::IndexedIndirectX, cycles: 6, official: true });
+        opcodes.insert(0x91, Instruction { opcode: OpCode::STA, mode: AddressingMode::IndirectIndexedY, cycles: 6, official: true });
 
         // STX - Store X Register
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#STX
-        opcodes.insert(0x86, Instruction { opcode: OpCode::STX, mode: AddressingMode::ZeroPage, cycles: 3 });
-        opcodes.insert(0x96, Instruction { opcode: OpCode::STX, mode: AddressingMode::ZeroPageY, cycles: 4 });
-        opcodes.insert(0x8E, Instruction { opcode: OpCode::STX, mode: AddressingMode::Absolute, cycles: 4 });
+        opcodes.insert(0x86, Instruction { opcode: OpCode::STX, mode: AddressingMode::ZeroPage, cycles: 3, official: true });
+        opcodes.insert(0x96, Instruction { opcode: OpCode::STX, mode: AddressingMode::ZeroPageY, cycles: 4, official: true });
+        opcodes.insert(0x8E, Instruction { opcode: OpCode::STX, mode: AddressingMode::Absolute, cycles: 4, official: true });
 
         // STY - Store Y Register
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#STY
-        opcodes.insert(0x84, Instruction { opcode: OpCode::STY, mode: AddressingMode::ZeroPage, cycles: 3 });
-        opcodes.insert(0x94, Instruction { opcode: OpCode::STY, mode: AddressingMode::ZeroPageX, cycles: 4 });
-        opcodes.insert(0x8C, Instruction { opcode: OpCode::STY, mode: AddressingMode::Absolute, cycles: 4 });
+        opcodes.insert(0x84, Instruction { opcode: OpCode::STY, mode: AddressingMode::ZeroPage, cycles: 3, official: true });
+        opcodes.insert(0x94, Instruction { opcode: OpCode::STY, mode: AddressingMode::ZeroPageX, cycles: 4, official: true });
+        opcodes.insert(0x8C, Instruction { opcode: OpCode::STY, mode: AddressingMode::Absolute, cycles: 4, official: true });
 
         // TAX - Transfer Accumulator to X
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#TAX
-        opcodes.insert(0xAA, Instruction { opcode: OpCode::TAX, mode: AddressingMode::Implied, cycles: 2 });
+        opcodes.insert(0xAA, Instruction { opcode: OpCode::TAX, mode: AddressingMode::Implied, cycles: 2, official: true });
 
         // TAY - Transfer Accumulator to Y
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#TAY
-        opcodes.insert(0xA8, Instruction { opcode: OpCode::TAY, mode: AddressingMode::Implied, cycles: 2 });
+        opcodes.insert(0xA8, Instruction { opcode: OpCode::TAY, mode: AddressingMode::Implied, cycles: 2, official: true });
 
         // TSX - Transfer Stack Pointer to X
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#TSX
-        opcodes.insert(0xBA, Instruction { opcode: OpCode::TSX, mode: AddressingMode::Implied, cycles: 2 });
+        opcodes.insert(0xBA, Instruction { opcode: OpCode::TSX, mode: AddressingMode::Implied, cycles: 2, official: true });
 
         // TXA - Transfer X to Accumulator
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#TXA
-        opcodes.insert(0x8A, Instruction { opcode: OpCode::TXA, mode: AddressingMode::Implied, cycles: 2 });
+        opcodes.insert(0x8A, Instruction { opcode: OpCode::TXA, mode: AddressingMode::Implied, cycles: 2, official: true });
 
         // TXS - Transfer X to Stack Pointer
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#TXS
-        opcodes.insert(0x9A, Instruction { opcode: OpCode::TXS, mode: AddressingMode::Implied, cycles: 2 });
+        opcodes.insert(0x9A, Instruction { opcode: OpCode::TXS, mode: AddressingMode::Implied, cycles: 2, official: true });
 
         // TYA - Transfer Y to Accumulator
         // https://www.nesdev.org/obelisk-6502-guide/reference.html#TYA
-        opcodes.insert(0x98, Instruction { opcode: OpCode::TYA, mode: AddressingMode::Implied, cycles: 2 });
+        opcodes.insert(0x98, Instruction { opcode: OpCode::TYA, mode: AddressingMode::Implied, cycles: 2, official: true });
 
         // Unofficial opcodes
         // https://www.nesdev.org/undocumented_opcodes.txt
         // https://www.nesdev.org/wiki/Programming_with_unofficial_opcodes
-        opcodes.insert(0x0B, Instruction { opcode: OpCode::AAC, mode: AddressingMode::Immediate, cycles: 2 });
-        opcodes.insert(0x2B, Instruction { opcode: OpCode::AAC, mode: AddressingMode::Immediate, cycles: 2 });
-
-        opcodes.insert(0x87, Instruction { opcode: OpCode::SAX, mode: AddressingMode::ZeroPage, cycles: 3 });
-        opcodes.insert(0x97, Instruction { opcode: OpCode::SAX, mode: AddressingMode::ZeroPageY, cycles: 4 });
-        opcodes.insert(0x83, Instruction { opcode: OpCode::SAX, mode: AddressingMode::IndexedIndirectX, cycles: 6 });
-        opcodes.insert(0x8F, Instruction { opcode: OpCode::SAX, mode: AddressingMode::Absolute, cycles: 4 });
-
-        opcodes.insert(0x6B, Instruction { opcode: OpCode::ARR, mode: AddressingMode::Immediate, cycles: 2 });
-
-        opcodes.insert(0x4B, Instruction { opcode: OpCode::ASR, mode: AddressingMode::Immediate, cycles: 2 });
-
-        opcodes.insert(0xAB, Instruction { opcode: OpCode::ATX, mode: AddressingMode::Immediate, cycles: 2 });
-
-        opcodes.insert(0x9F, Instruction { opcode: OpCode::AXA, mode: AddressingMode::AbsoluteY, cycles: 5 });
-        opcodes.insert(0x93, Instruction { opcode: OpCode::AXA, mode: AddressingMode::IndirectIndexedY, cycles: 6 });
-
-        opcodes.insert(0xCB, Instruction { opcode: OpCode::AXS, mode: AddressingMode::Immediate, cycles: 2 });
-
-        opcodes.insert(0xC7, Instruction { opcode: OpCode::DCP, mode: AddressingMode::ZeroPage, cycles: 5 });
-        opcodes.insert(0xD7, Instruction { opcode: OpCode::DCP, mode: AddressingMode::ZeroPageX, cycles: 6 });
-        opcodes.insert(0xCF, Instruction { opcode: OpCode::DCP, mode: AddressingMode::Absolute, cycles: 6 });
-        opcodes.insert(0xDF, Instruction { opcode: OpCode::DCP, mode: AddressingMode::AbsoluteX, cycles: 7 });
-        opcodes.insert(0xDB, Instruction { opcode: OpCode::DCP, mode: AddressingMode::AbsoluteY, cycles: 7 });
-        opcodes.insert(0xC3, Instruction { opcode: OpCode::DCP, mode: AddressingMode::IndexedIndirectX, cycles: 8 });
-        opcodes.insert(0xD3, Instruction { opcode: OpCode::DCP, mode: AddressingMode::IndirectIndexedY, cycles: 8 });
-
-        opcodes.insert(0x04, Instruction { opcode: OpCode::DOP, mode: AddressingMode::ZeroPage, cycles: 3 });
-        opcodes.insert(0x14, Instruction { opcode: OpCode::DOP, mode: AddressingMode::ZeroPageX, cycles: 4 });
-        opcodes.insert(0x34, Instruction { opcode: OpCode::DOP, mode: AddressingMode::ZeroPageX, cycles: 4 });
-        opcodes.insert(0x44, Instruction { opcode: OpCode::DOP, mode: AddressingMode::ZeroPage, cycles: 3 });
-        opcodes.insert(0x54, Instruction { opcode: OpCode::DOP, mode: AddressingMode::ZeroPageX, cycles: 4 });
-        opcodes.insert(0x64, Instruction { opcode: OpCode::DOP, mode: AddressingMode::ZeroPage, cycles: 3 });
-        opcodes.insert(0x74, Instruction { opcode: OpCode::DOP, mode: AddressingMode::ZeroPageX, cycles: 4 });
-        opcodes.insert(0x80, Instruction { opcode: OpCode::DOP, mode: AddressingMode::Immediate, cycles: 2 });
-        opcodes.insert(0x82, Instruction { opcode: OpCode::DOP, mode: AddressingMode::Immediate, cycles: 2 });
-        opcodes.insert(0x89, Instruction { opcode: OpCode::DOP, mode: AddressingMode::Immediate, cycles: 2 });
-        opcodes.insert(0xC2, Instruction { opcode: OpCode::DOP, mode: AddressingMode::Immediate, cycles: 2 });
-        opcodes.insert(0xD4, Instruction { opcode: OpCode::DOP, mode: AddressingMode::ZeroPageX, cycles: 4 });
-        opcodes.insert(0xE2, Instruction { opcode: OpCode::DOP, mode: AddressingMode::Immediate, cycles: 2 });
-        opcodes.insert(0xF4, Instruction { opcode: OpCode::DOP, mode: AddressingMode::ZeroPageX, cycles: 4 });
-
-        opcodes.insert(0xE7, Instruction { opcode: OpCode::ISB, mode: AddressingMode::ZeroPage, cycles: 5 });
-        opcodes.insert(0xF7, Instruction { opcode: OpCode::ISB, mode: AddressingMode::ZeroPageX, cycles: 6 });
-        opcodes.insert(0xEF, Instruction { opcode: OpCode::ISB, mode: AddressingMode::Absolute, cycles: 6 });
-        opcodes.insert(0xFF, Instruction { opcode: OpCode::ISB, mode: AddressingMode::AbsoluteX, cycles: 7 });
-        opcodes.insert(0xFB, Instruction { opcode: OpCode::ISB, mode: AddressingMode::AbsoluteY, cycles: 7 });
-        opcodes.insert(0xE3, Instruction { opcode: OpCode::ISB, mode: AddressingMode::IndexedIndirectX, cycles: 8 });
-        opcodes.insert(0xF3, Instruction { opcode: OpCode::ISB, mode: AddressingMode::IndirectIndexedY, cycles: 8 });
-
-        opcodes.insert(0x02, Instruction { opcode: OpCode::KIL, mode: AddressingMode::Implied, cycles: 0 });
-        opcodes.insert(0x12, Instruction { opcode: OpCode::KIL, mode: AddressingMode::Implied, cycles: 0 });
-        opcodes.insert(0x22, Instruction { opcode: OpCode::KIL, mode: AddressingMode::Implied, cycles: 0 });
-        opcodes.insert(0x32, Instruction { opcode: OpCode::KIL, mode: AddressingMode::Implied, cycles: 0 });
-        opcodes.insert(0x42, Instruction { opcode: OpCode::KIL, mode: AddressingMode::Implied, cycles: 0 });
-        opcodes.insert(0x52, Instruction { opcode: OpCode::KIL, mode: AddressingMode::Implied, cycles: 0 });
-        opcodes.insert(0x62, Instruction { opcode: OpCode::KIL, mode: AddressingMode::Implied, cycles: 0 });
-        opcodes.insert(0x72, Instruction { opcode: OpCode::KIL, mode: AddressingMode::Implied, cycles: 0 });
-        opcodes.insert(0x92, Instruction { opcode: OpCode::KIL, mode: AddressingMode::Implied, cycles: 0 });
-        opcodes.insert(0xB2, Instruction { opcode: OpCode::KIL, mode: AddressingMode::Implied, cycles: 0 });
-        opcodes.insert(0xD2, Instruction { opcode: OpCode::KIL, mode: AddressingMode::Implied, cycles: 0 });
-        opcodes.insert(0xF2, Instruction { opcode: OpCode::KIL, mode: AddressingMode::Implied, cycles: 0 });
-
-        opcodes.insert(0xBB, Instruction { opcode: OpCode::LAR, mode: AddressingMode::AbsoluteY, cycles: 4 }); // +1 cycle if page crossed
-
-        opcodes.insert(0xA7, Instruction { opcode: OpCode::LAX, mode: AddressingMode::ZeroPage, cycles: 3 });
-        opcodes.insert(0xB7, Instruction { opcode: OpCode::LAX, mode: AddressingMode::ZeroPageY, cycles: 4 });
-        opcodes.insert(0xAF, Instruction { opcode: OpCode::LAX, mode: AddressingMode::Absolute, cycles: 4 });
-        opcodes.insert(0xBF, Instruction { opcode: OpCode::LAX, mode: AddressingMode::AbsoluteY, cycles: 4 }); // +1 cycle if page crossed
-        opcodes.insert(0xA3, Instruction { opcode: OpCode::LAX, mode: AddressingMode::IndexedIndirectX, cycles: 6 });
-        opcodes.insert(0xB3, Instruction { opcode: OpCode::LAX, mode: AddressingMode::IndirectIndexedY, cycles: 5 }); // +1 cycle if page crossed
-
-        opcodes.insert(0x27, Instruction { opcode: OpCode::RLA, mode: AddressingMode::ZeroPage, cycles: 5 });
-        opcodes.insert(0x37, Instruction { opcode: OpCode::RLA, mode: AddressingMode::ZeroPageX, cycles: 6 });
-        opcodes.insert(0x2F, Instruction { opcode: OpCode::RLA, mode: AddressingMode::Absolute, cycles: 6 });
-        opcodes.insert(0x3F, Instruction { opcode: OpCode::RLA, mode: AddressingMode::AbsoluteX, cycles: 7 });
-        opcodes.insert(0x3B, Instruction { opcode: OpCode::RLA, mode: AddressingMode::AbsoluteY, cycles: 7 });
-        opcodes.insert(0x23, Instruction { opcode: OpCode::RLA, mode: AddressingMode::IndexedIndirectX, cycles: 8 });
-        opcodes.insert(0x33, Instruction { opcode: OpCode::RLA, mode: AddressingMode::IndirectIndexedY, cycles: 8 });
-
-        opcodes.insert(0x67, Instruction { opcode: OpCode::RRA, mode: AddressingMode::ZeroPage, cycles: 5 });
-        opcodes.insert(0x77, Instruction { opcode: OpCode::RRA, mode: AddressingMode::ZeroPageX, cycles: 6 });
-        opcodes.insert(0x6F, Instruction { opcode: OpCode::RRA, mode: AddressingMode::Absolute, cycles: 6 });
-        opcodes.insert(0x7F, Instruction { opcode: OpCode::RRA, mode: AddressingMode::AbsoluteX, cycles: 7 });
-        opcodes.insert(0x7B, Instruction { opcode: OpCode::RRA, mode: AddressingMode::AbsoluteY, cycles: 7 });
-        opcodes.insert(0x63, Instruction { opcode: OpCode::RRA, mode: AddressingMode::IndexedIndirectX, cycles: 8 });
-        opcodes.insert(0x73, Instruction { opcode: OpCode::RRA, mode: AddressingMode::IndirectIndexedY, cycles: 8 });
+        opcodes.insert(0x0B, Instruction { opcode: OpCode::AAC, mode: AddressingMode::Immediate, cycles: 2, official: false });
+        opcodes.insert(0x2B, Instruction { opcode: OpCode::AAC, mode: AddressingMode::Immediate, cycles: 2, official: false });
+
+        opcodes.insert(0x87, Instruction { opcode: OpCode::SAX, mode: AddressingMode::ZeroPage, cycles: 3, official: false });
+        opcodes.insert(0x97, Instruction { opcode: OpCode::SAX, mode: AddressingMode::ZeroPageY, cycles: 4, official: false });
+        opcodes.insert(0x83, Instruction { opcode: OpCode::SAX, mode: AddressingMode::IndexedIndirectX, cycles: 6, official: false });
+        opcodes.insert(0x8F, Instruction { opcode: OpCode::SAX, mode: AddressingMode::Absolute, cycles: 4, official: false });
+
+        opcodes.insert(0x6B, Instruction { opcode: OpCode::ARR, mode: AddressingMode::Immediate, cycles: 2, official: false });
+
+        opcodes.insert(0x4B, Instruction { opcode: OpCode::ASR, mode: AddressingMode::Immediate, cycles: 2, official: false });
+
+        opcodes.insert(0xAB, Instruction { opcode: OpCode::ATX, mode: AddressingMode::Immediate, cycles: 2, official: false });
+
+        opcodes.insert(0x9F, Instruction { opcode: OpCode::AXA, mode: AddressingMode::AbsoluteY, cycles: 5, official: false });
+        opcodes.insert(0x93, Instruction { opcode: OpCode::AXA, mode: AddressingMode::IndirectIndexedY, cycles: 6, official: false });
+
+        opcodes.insert(0xCB, Instruction { opcode: OpCode::AXS, mode: AddressingMode::Immediate, cycles: 2, official: false });
+
+        opcodes.insert(0xC7, Instruction { opcode: OpCode::DCP, mode: AddressingMode::ZeroPage, cycles: 5, official: false });
+        opcodes.insert(0xD7, Instruction { opcode: OpCode::DCP, mode: AddressingMode::ZeroPageX, cycles: 6, official: false });
+        opcodes.insert(0xCF, Instruction { opcode: OpCode::DCP, mode: AddressingMode::Absolute, cycles: 6, official: false });
+        opcodes.insert(0xDF, Instruction { opcode: OpCode::DCP, mode: AddressingMode::AbsoluteX, cycles: 7, official: false });
+        opcodes.insert(0xDB, Instruction { opcode: OpCode::DCP, mode: AddressingMode::AbsoluteY, cycles: 7, official: false });
+        opcodes.insert(0xC3, Instruction { opcode: OpCode::DCP, mode: AddressingMode::IndexedIndirectX, cycles: 8, official: false });
+        opcodes.insert(0xD3, Instruction { opcode: OpCode::DCP, mode: AddressingMode::IndirectIndexedY, cycles: 8, official: false });
+
+        opcodes.insert(0x04, Instruction { opcode: OpCode::DOP, mode: AddressingMode::ZeroPage, cycles: 3, official: false });
+        opcodes.insert(0x14, Instruction { opcode: OpCode::DOP, mode: AddressingMode::ZeroPageX, cycles: 4, official: false });
+        opcodes.insert(0x34, Instruction { opcode: OpCode::DOP, mode: AddressingMode::ZeroPageX, cycles: 4, official: false });
+        opcodes.insert(0x44, Instruction { opcode: OpCode::DOP, mode: AddressingMode::ZeroPage, cycles: 3, official: false });
+        opcodes.insert(0x54, Instruction { opcode: OpCode::DOP, mode: AddressingMode::ZeroPageX, cycles: 4, official: false });
+        opcodes.insert(0x64, Instruction { opcode: OpCode::DOP, mode: AddressingMode::ZeroPage, cycles: 3, official: false });
+        opcodes.insert(0x74, Instruction { opcode: OpCode::DOP, mode: AddressingMode::ZeroPageX, cycles: 4, official: false });
+        opcodes.insert(0x80, Instruction { opcode: OpCode::DOP, mode: AddressingMode::Immediate, cycles: 2, official: false });
+        opcodes.insert(0x82, Instruction { opcode: OpCode::DOP, mode: AddressingMode::Immediate, cycles: 2, official: false });
+        opcodes.insert(0x89, Instruction { opcode: OpCode::DOP, mode: AddressingMode::Immediate, cycles: 2, official: false });
+        opcodes.insert(0xC2, Instruction { opcode: OpCode::DOP, mode: AddressingMode::Immediate, cycles: 2, official: false });
+        opcodes.insert(0xD4, Instruction { opcode: OpCode::DOP, mode: AddressingMode::ZeroPageX, cycles: 4, official: false });
+        opcodes.insert(0xE2, Instruction { opcode: OpCode::DOP, mode: AddressingMode::Immediate, cycles: 2, official: false });
+        opcodes.insert(0xF4, Instruction { opcode: OpCode::DOP, mode: AddressingMode::ZeroPageX, cycles: 4, official: false });
+
+        opcodes.insert(0xE7, Instruction { opcode: OpCode::ISB, mode: AddressingMode::ZeroPage, cycles: 5, official: false });
+        opcodes.insert(0xF7, Instruction { opcode: OpCode::ISB, mode: AddressingMode::ZeroPageX, cycles: 6, official: false });
+        opcodes.insert(0xEF, Instruction { opcode: OpCode::ISB, mode: AddressingMode::Absolute, cycles: 6, official: false });
+        opcodes.insert(0xFF, Instruction { opcode: OpCode::ISB, mode: AddressingMode::AbsoluteX, cycles: 7, official: false });
+        opcodes.insert(0xFB, Instruction { opcode: OpCode::ISB, mode: AddressingMode::AbsoluteY, cycles: 7, official: false });
+        opcodes.insert(0xE3, Instruction { opcode: OpCode::ISB, mode: AddressingMode::IndexedIndirectX, cycles: 8, official: false });
+        opcodes.insert(0xF3, Instruction { opcode: OpCode::ISB, mode: AddressingMode::IndirectIndexedY, cycles: 8, official: false });
+
+        opcodes.insert(0x02, Instruction { opcode: OpCode::KIL, mode: AddressingMode::Implied, cycles: 0, official: false });
+        opcodes.insert(0x12, Instruction { opcode: OpCode::KIL, mode: AddressingMode::Implied, cycles: 0, official: false });
+        opcodes.insert(0x22, Instruction { opcode: OpCode::KIL, mode: AddressingMode::Implied, cycles: 0, official: false });
+        opcodes.insert(0x32, Instruction { opcode: OpCode::KIL, mode: AddressingMode::Implied, cycles: 0, official: false });
+        opcodes.insert(0x42, Instruction { opcode: OpCode::KIL, mode: AddressingMode::Implied, cycles: 0, official: false });
+        opcodes.insert(0x52, Instruction { opcode: OpCode::KIL, mode: AddressingMode::Implied, cycles: 0, official: false });
+        opcodes.insert(0x62, Instruction { opcode: OpCode::KIL, mode: AddressingMode::Implied, cycles: 0, official: false });
+        opcodes.insert(0x72, Instruction { opcode: OpCode::KIL, mode: AddressingMode::Implied, cycles: 0, official: false });
+        opcodes.insert(0x92, Instruction { opcode: OpCode::KIL, mode: AddressingMode::Implied, cycles: 0, official: false });
+        opcodes.insert(0xB2, Instruction { opcode: OpCode::KIL, mode: AddressingMode::Implied, cycles: 0, official: false });
+        opcodes.insert(0xD2, Instruction { opcode: OpCode::KIL, mode: AddressingMode::Implied, cycles: 0, official: false });
+        opcodes.insert(0xF2, Instruction { opcode: OpCode::KIL, mode: AddressingMode::Implied, cycles: 0, official: false });
+
+        opcodes.insert(0xBB, Instruction { opcode: OpCode::LAR, mode: AddressingMode::AbsoluteY, cycles: 4, official: false }); // +1 cycle if page crossed
+
+        opcodes.insert(0xA7, Instruction { opcode: OpCode::LAX, mode: AddressingMode::ZeroPage, cycles: 3, official: false });
+        opcodes.insert(0xB7, Instruction { opcode: OpCode::LAX, mode: AddressingMode::ZeroPageY, cycles: 4, official: false });
+        opcodes.insert(0xAF, Instruction { opcode: OpCode::LAX, mode: AddressingMode::Absolute, cycles: 4, official: false });
+        opcodes.insert(0xBF, Instruction { opcode: OpCode::LAX, mode: AddressingMode::AbsoluteY, cycles: 4, official: false }); // +1 cycle if page crossed
+        opcodes.insert(0xA3, Instruction { opcode: OpCode::LAX, mode: AddressingMode::IndexedIndirectX, cycles: 6, official: false });
+        opcodes.insert(0xB3, Instruction { opcode: OpCode::LAX, mode: AddressingMode::IndirectIndexedY, cycles: 5, official: false }); // +1 cycle if page crossed
+
+        opcodes.insert(0x27, Instruction { opcode: OpCode::RLA, mode: AddressingMode::ZeroPage, cycles: 5, official: false });
+        opcodes.insert(0x37, Instruction { opcode: OpCode::RLA, mode: AddressingMode::ZeroPageX, cycles: 6, official: false });
+        opcodes.insert(0x2F, Instruction { opcode: OpCode::RLA, mode: AddressingMode::Absolute, cycles: 6, official: false });
+        opcodes.insert(0x3F, Instruction { opcode: OpCode::RLA, mode: AddressingMode::AbsoluteX, cycles: 7, official: false });
+        opcodes.insert(0x3B, Instruction { opcode: OpCode::RLA, mode: AddressingMode::AbsoluteY, cycles: 7, official: false });
+        opcodes.insert(0x23, Instruction { opcode: OpCode::RLA, mode: AddressingMode::IndexedIndirectX, cycles: 8, official: false });
+        opcodes.insert(0x33, Instruction { opcode: OpCode::RLA, mode: AddressingMode::IndirectIndexedY, cycles: 8, official: false });
+
+        opcodes.insert(0x67, Instruction { opcode: OpCode::RRA, mode: AddressingMode::ZeroPage, cycles: 5, official: false });
+        opcodes.insert(0x77, Instruction { opcode: OpCode::RRA, mode: AddressingMode::ZeroPageX, cycles: 6, official: false });
+        opcodes.insert(0x6F, Instruction { opcode: OpCode::RRA, mode: AddressingMode::Absolute, cycles: 6, official: false });
+        opcodes.insert(0x7F, Instruction { opcode: OpCode::RRA, mode: AddressingMode::AbsoluteX, cycles: 7, official: false });
+        opcodes.insert(0x7B, Instruction { opcode: OpCode::RRA, mode: AddressingMode::AbsoluteY, cycles: 7, official: false });
+        opcodes.insert(0x63, Instruction { opcode: OpCode::RRA, mode: AddressingMode::IndexedIndirectX, cycles: 8, official: false });
+        opcodes.insert(0x73, Instruction { opcode: OpCode::RRA, mode: AddressingMode::IndirectIndexedY, cycles: 8, official: false });
         
-        opcodes.insert(0x07, Instruction { opcode: OpCode::SLO, mode: AddressingMode::ZeroPage, cycles: 5 });
-        opcodes.insert(0x17, Instruction { opcode: OpCode::SLO, mode: AddressingMode::ZeroPageX, cycles: 6 });
-        opcodes.insert(0x0F, Instruction { opcode: OpCode::SLO, mode: AddressingMode::Absolute, cycles: 6 });
-        opcodes.insert(0x1F, Instruction { opcode: OpCode::SLO, mode: AddressingMode::AbsoluteX, cycles: 7 });
-        opcodes.insert(0x1B, Instruction { opcode: OpCode::SLO, mode: AddressingMode::AbsoluteY, cycles: 7 });
-        opcodes.insert(0x03, Instruction { opcode: OpCode::SLO, mode: AddressingMode::IndexedIndirectX, cycles: 8 });
-        opcodes.insert(0x13, Instruction { opcode: OpCode::SLO, mode: AddressingMode::IndirectIndexedY, cycles: 8 });
-
-        opcodes.insert(0x47, Instruction { opcode: OpCode::SRE, mode: AddressingMode::ZeroPage, cycles: 5 });
-        opcodes.insert(0x57, Instruction { opcode: OpCode::SRE, mode: AddressingMode::ZeroPageX, cycles: 6 });
-        opcodes.insert(0x4F, Instruction { opcode: OpCode::SRE, mode: AddressingMode::Absolute, cycles: 6 });
-        opcodes.insert(0x5F, Instruction { opcode: OpCode::SRE, mode: AddressingMode::AbsoluteX, cycles: 7 });
-        opcodes.insert(0x5B, Instruction { opcode: OpCode::SRE, mode: AddressingMode::AbsoluteY, cycles: 7 });
-        opcodes.insert(0x43, Instruction { opcode: OpCode::SRE, mode: AddressingMode::IndexedIndirectX, cycles: 8 });
-        opcodes.insert(0x53, Instruction { opcode: OpCode::SRE, mode: AddressingMode::IndirectIndexedY, cycles: 8 });
+        opcodes.insert(0x07, Instruction { opcode: OpCode::SLO, mode: AddressingMode::ZeroPage, cycles: 5, official: false });
+        opcodes.insert(0x17, Instruction { opcode: OpCode::SLO, mode: AddressingMode::ZeroPageX, cycles: 6, official: false });
+        opcodes.insert(0x0F, Instruction { opcode: OpCode::SLO, mode: AddressingMode::Absolute, cycles: 6, official: false });
+        opcodes.insert(0x1F, Instruction { opcode: OpCode::SLO, mode: AddressingMode::AbsoluteX, cycles: 7, official: false });
+        opcodes.insert(0x1B, Instruction { opcode: OpCode::SLO, mode: AddressingMode::AbsoluteY, cycles: 7, official: false });
+        opcodes.insert(0x03, Instruction { opcode: OpCode::SLO, mode: AddressingMode::IndexedIndirectX, cycles: 8, official: false });
+        opcodes.insert(0x13, Instruction { opcode: OpCode::SLO, mode: AddressingMode::IndirectIndexedY, cycles: 8, official: false });
+
+        opcodes.insert(0x47, Instruction { opcode: OpCode::SRE, mode: AddressingMode::ZeroPage, cycles: 5, official: false });
+        opcodes.insert(0x57, Instruction { opcode: OpCode::SRE, mode: AddressingMode::ZeroPageX, cycles: 6, official: false });
+        opcodes.insert(0x4F, Instruction { opcode: OpCode::SRE, mode: AddressingMode::Absolute, cycles: 6, official: false });
+        opcodes.insert(0x5F, Instruction { opcode: OpCode::SRE, mode: AddressingMode::AbsoluteX, cycles: 7, official: false });
+        opcodes.insert(0x5B, Instruction { opcode: OpCode::SRE, mode: AddressingMode::AbsoluteY, cycles: 7, official: false });
+        opcodes.insert(0x43, Instruction { opcode: OpCode::SRE, mode: AddressingMode::IndexedIndirectX, cycles: 8, official: false });
+        opcodes.insert(0x53, Instruction { opcode: OpCode::SRE, mode: AddressingMode::IndirectIndexedY, cycles: 8, official: false });
         
-        opcodes.insert(0x9E, Instruction { opcode: OpCode::SXA, mode: AddressingMode::AbsoluteY, cycles: 5 });
+        opcodes.insert(0x9E, Instruction { opcode: OpCode::SXA, mode: AddressingMode::AbsoluteY, cycles: 5, official: false });
 
-        opcodes.insert(0x9C, Instruction { opcode: OpCode::SYA, mode: AddressingMode::AbsoluteX, cycles: 5 });
+        opcodes.insert(0x9C, Instruction { opcode: OpCode::SYA, mode: AddressingMode::AbsoluteX, cycles: 5, official: false });
         
-        opcodes.insert(0x0C, Instruction { opcode: OpCode::TOP, mode: AddressingMode::Absolute, cycles: 4 });
-        opcodes.insert(0x1C, Instruction { opcode: OpCode::TOP, mode: AddressingMode::AbsoluteX, cycles: 4 }); // +1 cycle if page crossed
-        opcodes.insert(0x3C, Instruction { opcode: OpCode::TOP, mode: AddressingMode::AbsoluteX, cycles: 4 }); // +1 cycle if page crossed
-        opcodes.insert(0x5C, Instruction { opcode: OpCode::TOP, mode: AddressingMode::AbsoluteX, cycles: 4 }); // +1 cycle if page crossed
-        opcodes.insert(0x7C, Instruction { opcode: OpCode::TOP, mode: AddressingMode::AbsoluteX, cycles: 4 }); // +1 cycle if page crossed
-        opcodes.insert(0xDC, Instruction { opcode: OpCode::TOP, mode: AddressingMode::AbsoluteX, cycles: 4 }); // +1 cycle if page crossed
-        opcodes.insert(0xFC, Instruction { opcode: OpCode::TOP, mode: AddressingMode::AbsoluteX, cycles: 4 }); // +1 cycle if page crossed
+        opcodes.insert(0x0C, Instruction { opcode: OpCode::TOP, mode: AddressingMode::Absolute, cycles: 4, official: false });
+        opcodes.insert(0x1C, Instruction { opcode: OpCode::TOP, mode: AddressingMode::AbsoluteX, cycles: 4, official: false }); // +1 cycle if page crossed
+        opcodes.insert(0x3C, Instruction { opcode: OpCode::TOP, mode: AddressingMode::AbsoluteX, cycles: 4, official: false }); // +1 cycle if page crossed
+        opcodes.insert(0x5C, Instruction { opcode: OpCode::TOP, mode: AddressingMode::AbsoluteX, cycles: 4, official: false }); // +1 cycle if page crossed
+        opcodes.insert(0x7C, Instruction { opcode: OpCode::TOP, mode: AddressingMode::AbsoluteX, cycles: 4, official: false }); // +1 cycle if page crossed
+        opcodes.insert(0xDC, Instruction { opcode: OpCode::TOP, mode: AddressingMode::AbsoluteX, cycles: 4, official: false }); // +1 cycle if page crossed
+        opcodes.insert(0xFC, Instruction { opcode: OpCode::TOP, mode: AddressingMode::AbsoluteX, cycles: 4, official: false }); // +1 cycle if page crossed
         
-        opcodes.insert(0x8B, Instruction { opcode: OpCode::XAA, mode: AddressingMode::Immediate, cycles: 2 });
+        opcodes.insert(0x8B, Instruction { opcode: OpCode::XAA, mode: AddressingMode::Immediate, cycles: 2, official: false });
 
-        opcodes.insert(0x9B, Instruction { opcode: OpCode::XAS, mode: AddressingMode::AbsoluteY, cycles: 5 });
+        opcodes.insert(0x9B, Instruction { opcode: OpCode::XAS, mode: AddressingMode::AbsoluteY, cycles: 5, official: false });
         
         opcodes
-    };
+};
+
+// Every defined instruction ordered by its opcode byte
+pub fn opcodes_iter() -> impl Iterator<Item = (u8, &'static Instruction)> {
+    OPCODES.iter()
+}
+
+// Base cycle cost of an opcode without executing it. Branches take 1 more
+// cycle when taken and 2 more when the target is on another page, indexed
+// reads take 1 more cycle when the index crosses a page.
+pub fn instruction_cycles(opcode: u8) -> Option<u8> {
+    OPCODES.get(opcode).map(|instruction| instruction.cycles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_opcodes_iter_is_ordered_and_complete() {
+        let opcodes: Vec<u8> = opcodes_iter().map(|(opcode, _)| opcode).collect();
+        assert_eq!(opcodes.len(), OPCODES.len());
+        assert!(opcodes.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_instruction_cycles() {
+        assert_eq!(instruction_cycles(0xA9), Some(2));
+        assert_eq!(instruction_cycles(0x1E), Some(7));
+        assert_eq!(instruction_cycles(0xD0), Some(2));
+    }
+
+    #[test]
+    fn test_is_official() {
+        let (_, lda) = opcodes_iter().find(|(opcode, _)| *opcode == 0xA9).unwrap();
+        let (_, lax) = opcodes_iter().find(|(opcode, _)| *opcode == 0xA7).unwrap();
+        assert!(lda.opcode.is_official());
+        assert!(!lax.opcode.is_official());
+        assert!(OpCode::SBC.is_official());
+        assert!(!OpCode::DOP.is_official());
+        assert_eq!(lax.opcode.to_string(), "LAX");
+        assert_eq!(lax.to_string(), "*LAX");
+    }
+
+    #[test]
+    fn test_unofficial_instructions() {
+        for (opcode, instruction) in opcodes_iter() {
+            if !instruction.opcode.is_official() {
+                assert!(!instruction.official, "{opcode:02X}");
+            }
+        }
+        let mnemonic = |opcode: u8| OPCODES.get(opcode).unwrap().to_string();
+        assert_eq!(mnemonic(0xE9), "SBC");
+        assert_eq!(mnemonic(0xEB), "*SBC");
+        assert_eq!(mnemonic(0xEA), "NOP");
+        assert_eq!(mnemonic(0x1A), "*NOP");
+        assert_eq!(mnemonic(0x04), "*NOP");
+        assert_eq!(mnemonic(0xA7), "*LAX");
+    }
+
+    #[test]
+    fn test_every_mnemonic_parses_back() {
+        for (_, instruction) in opcodes_iter() {
+            let expected = match instruction.opcode {
+                OpCode::DOP | OpCode::TOP => OpCode::NOP,
+                opcode => opcode,
+            };
+            let mnemonic = instruction.opcode.to_string();
+            assert_eq!(mnemonic.parse::<OpCode>(), Ok(expected), "{mnemonic}");
+        }
+        for opcode in OpCode::ALL {
+            assert_eq!(format!("{:?}", opcode).parse::<OpCode>(), Ok(opcode));
+        }
+        assert_eq!("lda".parse::<OpCode>(), Ok(OpCode::LDA));
+        assert_eq!("LDQ".parse::<OpCode>(), Err(UnknownMnemonic));
+    }
+
+    #[test]
+    fn test_addressing_mode_round_trips() {
+        for (mode, _) in AddressingMode::SYNTAX {
+            assert_eq!(mode.to_string().parse::<AddressingMode>(), Ok(mode));
+        }
+        assert_eq!(AddressingMode::IndirectIndexedY.to_string(), "(ind),Y");
+        assert_eq!("ZPG,x".parse::<AddressingMode>(), Ok(AddressingMode::ZeroPageX));
+        assert_eq!("abs,Z".parse::<AddressingMode>(), Err(UnknownAddressingMode));
+    }
 }
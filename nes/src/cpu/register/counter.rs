@@ -30,6 +30,11 @@ impl ProgramCounter {
         self.register.set(value);
     }
 
+    // Address relative to PC, wrapping from $FFFF to $0000 like the 6502 does
+    pub fn peek_offset(&self, offset: i16) -> u16 {
+        self.register.get().wrapping_add_signed(offset)
+    }
+
     pub fn move_with_offset(&mut self, value: u8) -> PageCrossed {
         let previous_val = self.register.get();
         let current_value = self.register.add_signed(value as i8 as i16);
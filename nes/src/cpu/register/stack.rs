@@ -1,4 +1,5 @@
 use crate::bus::{Bus, BusOperation};
+use crate::cpu::error::StackError;
 use crate::cpu::register::register::Register;
 
 pub struct Stack {
@@ -33,33 +34,40 @@ impl Stack {
 }
 
 pub trait StackOperation<T> {
-    fn push(&mut self, value: T, bus: &mut Bus);
+    fn push(&mut self, value: T, bus: &mut Bus) -> Result<(), StackError>;
 
-    fn pull(&mut self, bus: &mut Bus) -> T;
+    fn pull(&mut self, bus: &mut Bus) -> Result<T, StackError>;
 }
 
 impl StackOperation<u8> for Stack {
-    fn push(&mut self, value: u8, bus: &mut Bus) {
+    fn push(&mut self, value: u8, bus: &mut Bus) -> Result<(), StackError> {
+        if self.stack_pointer.get() == 0x00 {
+            return Err(StackError::StackOverflow);
+        }
         bus.write(self.get_stack_address(), value);
         self.stack_pointer.dec();
+        Ok(())
     }
 
-    fn pull(&mut self, bus: &mut Bus) -> u8 {
+    fn pull(&mut self, bus: &mut Bus) -> Result<u8, StackError> {
+        if self.stack_pointer.get() == 0xFF {
+            return Err(StackError::StackUnderflow);
+        }
         self.stack_pointer.inc();
-        bus.read(self.get_stack_address())
+        Ok(bus.read(self.get_stack_address()))
     }
 }
 
 impl StackOperation<u16> for Stack {
-    fn push(&mut self, value: u16, bus: &mut Bus) {
+    fn push(&mut self, value: u16, bus: &mut Bus) -> Result<(), StackError> {
         let value_bytes: [u8; 2] = value.to_be_bytes();
-        StackOperation::<u8>::push(self, value_bytes[0], bus);
-        StackOperation::<u8>::push(self, value_bytes[1], bus);
+        StackOperation::<u8>::push(self, value_bytes[0], bus)?;
+        StackOperation::<u8>::push(self, value_bytes[1], bus)
     }
 
-    fn pull(&mut self, bus: &mut Bus) -> u16 {
-        let lo_byte = StackOperation::<u8>::pull(self, bus);
-        let hi_byte = StackOperation::<u8>::pull(self, bus);
-        u16::from_le_bytes([lo_byte, hi_byte])
+    fn pull(&mut self, bus: &mut Bus) -> Result<u16, StackError> {
+        let lo_byte = StackOperation::<u8>::pull(self, bus)?;
+        let hi_byte = StackOperation::<u8>::pull(self, bus)?;
+        Ok(u16::from_le_bytes([lo_byte, hi_byte]))
     }
 }
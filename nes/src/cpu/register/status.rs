@@ -60,6 +60,10 @@ impl ProcessorStatus {
         self.contains(ProcessorStatus::ZERO_FLAG)
     }
 
+    pub fn is_interrupt_disable_flag_set(&self) -> bool {
+        self.contains(ProcessorStatus::INTERRUPT_DISABLE_FLAG)
+    }
+
     pub fn is_overflow_flag_set(&self) -> bool {
         self.contains(ProcessorStatus::OVERFLOW_FLAG)
     }
@@ -0,0 +1,161 @@
+use crate::cpu::cpu::CPU;
+use crate::cpu::state::MachineState;
+use alloc::collections::VecDeque;
+
+// Save states taken every `interval` frames, the oldest one is dropped once
+// `capacity` states are kept. Memory use is bounded by `capacity` times the
+// size of a `MachineState`, e.g. 10 seconds of NTSC history with a state
+// every 6 frames keeps 100 states, about 1.6 MiB (2.4 MiB with CHR RAM).
+pub struct RewindBuffer {
+    states: VecDeque<(usize, MachineState)>,
+    capacity: usize,
+    interval: usize,
+}
+
+impl RewindBuffer {
+    fn new(history_frames: usize, interval: usize) -> Self {
+        let interval = interval.max(1);
+        let capacity = history_frames.div_ceil(interval).max(1);
+        RewindBuffer {
+            states: VecDeque::with_capacity(capacity),
+            capacity,
+            interval,
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.states.clear();
+    }
+
+    fn should_record(&self, frame: usize) -> bool {
+        frame.is_multiple_of(self.interval)
+            && self.states.back().is_none_or(|(last, _)| *last < frame)
+    }
+
+    fn push(&mut self, frame: usize, state: MachineState) {
+        if self.states.len() == self.capacity {
+            self.states.pop_front();
+        }
+        self.states.push_back((frame, state));
+    }
+
+    // Latest state not newer than `frame`, the states after it are dropped
+    fn rewind_to(&mut self, frame: usize) -> Option<&MachineState> {
+        let index = self
+            .states
+            .iter()
+            .rposition(|(recorded, _)| *recorded <= frame)?;
+        self.states.truncate(index + 1);
+        self.states.back().map(|(_, state)| state)
+    }
+}
+
+impl CPU<'_> {
+    // Keeps save states of the last `history_frames` frames, one every
+    // `interval` frames, so `rewind` can go back in time. A longer interval
+    // needs less memory but rewinds in coarser steps. Replaces the history
+    // of a previous call.
+    pub fn enable_rewind(&mut self, history_frames: usize, interval: usize) {
+        self.rewind = Some(RewindBuffer::new(history_frames, interval));
+    }
+
+    pub fn disable_rewind(&mut self) {
+        self.rewind = None;
+    }
+
+    // Goes back at least `frames` frames, to the latest recorded state that
+    // old. Returns false when rewinding is disabled or no state is that old,
+    // the CPU is left untouched then.
+    pub fn rewind(&mut self, frames: usize) -> bool {
+        let current = self.bus.stats().frames;
+        let Some(target) = current.checked_sub(frames) else {
+            return false;
+        };
+        let Some(mut buffer) = self.rewind.take() else {
+            return false;
+        };
+        let restored = match buffer.rewind_to(target) {
+            Some(state) => {
+                self.load_state(state);
+                true
+            }
+            None => false,
+        };
+        self.rewind = Some(buffer);
+        restored
+    }
+
+    // Called before every instruction, takes a state once per recorded frame
+    pub(crate) fn record_rewind_state(&mut self) {
+        let frame = self.bus.stats().frames;
+        if self
+            .rewind
+            .as_ref()
+            .is_some_and(|buffer| buffer.should_record(frame))
+        {
+            let state = self.save_state();
+            if let Some(buffer) = self.rewind.as_mut() {
+                buffer.push(frame, state);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cpu::cpu::CPU;
+    use alloc::vec;
+
+    fn run_to_frame(cpu: &mut CPU, frame: usize) {
+        while cpu.bus.stats().frames < frame {
+            cpu.step().unwrap();
+        }
+    }
+
+    fn counter(cpu: &CPU) -> u8 {
+        cpu.bus.peek(0x0000)
+    }
+
+    #[test]
+    fn test_rewind_replays_the_same_timeline() {
+        // INC $00; JMP $8000
+        let mut cpu = CPU::from_program(&[0xE6, 0x00, 0x4C, 0x00, 0x80]);
+        cpu.enable_rewind(10, 2);
+
+        let mut timeline = vec![];
+        for frame in 1..=20 {
+            run_to_frame(&mut cpu, frame);
+            timeline.push((cpu.snapshot(), counter(&cpu), cpu.bus.cycles));
+        }
+
+        assert!(cpu.rewind(5));
+        assert_eq!(cpu.bus.stats().frames, 14);
+        assert_eq!(
+            timeline[13],
+            (cpu.snapshot(), counter(&cpu), cpu.bus.cycles)
+        );
+
+        for frame in 15..=20 {
+            run_to_frame(&mut cpu, frame);
+            let replayed = (cpu.snapshot(), counter(&cpu), cpu.bus.cycles);
+            assert_eq!(timeline[frame - 1], replayed);
+        }
+    }
+
+    #[test]
+    fn test_rewind_past_history_fails() {
+        // INC $00; JMP $8000
+        let mut cpu = CPU::from_program(&[0xE6, 0x00, 0x4C, 0x00, 0x80]);
+        assert!(!cpu.rewind(1));
+
+        cpu.enable_rewind(10, 2);
+        run_to_frame(&mut cpu, 20);
+        let snapshot = cpu.snapshot();
+
+        // States of frames 10-18 are kept
+        assert!(!cpu.rewind(11));
+        assert_eq!(cpu.snapshot(), snapshot);
+        assert!(cpu.rewind(10));
+        assert_eq!(cpu.bus.stats().frames, 10);
+    }
+}
@@ -0,0 +1,79 @@
+use crate::cpu::cpu::CPU;
+use core::fmt::{Display, Formatter};
+
+// Register values of the CPU at some point in time. Unlike `trace` it doesn't
+// read the bus, so it can be taken from a shared reference.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CpuSnapshot {
+    pub accumulator: u8,
+    pub register_x: u8,
+    pub register_y: u8,
+    pub status: u8,
+    pub stack_pointer: u8,
+    pub program_counter: u16,
+}
+
+impl CpuSnapshot {
+    // Flag letters from bit 7 to bit 0, bit 5 is unused
+    const FLAGS: [char; 8] = ['n', 'v', '-', 'b', 'd', 'i', 'z', 'c'];
+
+    // Upper case letter for a set flag, lower case for a cleared one
+    fn flags(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        for (i, flag) in CpuSnapshot::FLAGS.iter().enumerate() {
+            let set = self.status & (0x80 >> i) != 0;
+            if set {
+                write!(f, "{}", flag.to_ascii_uppercase())?;
+            } else {
+                write!(f, "{}", flag)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for CpuSnapshot {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "A:{:02X} X:{:02X} Y:{:02X} P:",
+            self.accumulator, self.register_x, self.register_y
+        )?;
+        self.flags(f)?;
+        write!(
+            f,
+            " SP:{:02X} PC:{:04X}",
+            self.stack_pointer, self.program_counter
+        )
+    }
+}
+
+impl CPU<'_> {
+    pub fn snapshot(&self) -> CpuSnapshot {
+        CpuSnapshot {
+            accumulator: self.accumulator.get(),
+            register_x: self.register_x.get(),
+            register_y: self.register_y.get(),
+            status: self.status.get(),
+            stack_pointer: self.stack.get_pointer(),
+            program_counter: self.program_counter.get(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn test_snapshot_display() {
+        // LDA #$80; LDX #$01; SEC; KIL
+        let mut cpu = CPU::from_program(&[0xA9, 0x80, 0xA2, 0x01, 0x38, 0x02]);
+        cpu.run(|_| {}).unwrap();
+
+        assert_eq!(
+            cpu.snapshot().to_string(),
+            "A:80 X:01 Y:00 P:nv-bdIzC SP:FD PC:8006"
+        );
+    }
+}
@@ -0,0 +1,64 @@
+use crate::bus::state::BusState;
+use crate::cpu::cpu::CPU;
+use crate::cpu::register::status::ProcessorStatus;
+use crate::cpu::snapshot::CpuSnapshot;
+
+// Save state of the whole console, see `BusState` for what is left out.
+// Roughly 16 KiB, plus 8 KiB when the cartridge has CHR RAM.
+pub struct MachineState {
+    cpu: CpuSnapshot,
+    bus: BusState,
+}
+
+impl MachineState {
+    pub fn cpu(&self) -> CpuSnapshot {
+        self.cpu
+    }
+}
+
+impl CPU<'_> {
+    pub fn save_state(&self) -> MachineState {
+        MachineState {
+            cpu: self.snapshot(),
+            bus: self.bus.save_state(),
+        }
+    }
+
+    // Only load states saved with the same cartridge inserted
+    pub fn load_state(&mut self, state: &MachineState) {
+        self.accumulator.set(state.cpu.accumulator);
+        self.register_x.set(state.cpu.register_x);
+        self.register_y.set(state.cpu.register_y);
+        self.status = ProcessorStatus::from_bits_retain(state.cpu.status);
+        self.stack.set_pointer(state.cpu.stack_pointer);
+        self.program_counter.set(state.cpu.program_counter);
+        self.bus.load_state(&state.bus);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bus::BusOperation;
+    use crate::cpu::cpu::CPU;
+
+    #[test]
+    fn test_load_state_restores_registers_and_memory() {
+        // LDA #$42; STA $10; LDX #$07; SEC; KIL
+        let mut cpu = CPU::from_program(&[0xA9, 0x42, 0x85, 0x10, 0xA2, 0x07, 0x38, 0x02]);
+        cpu.step().unwrap();
+        let state = cpu.save_state();
+
+        cpu.run(|_| {}).unwrap();
+        cpu.load_state(&state);
+
+        assert_eq!(cpu.snapshot(), state.cpu());
+        assert_eq!(cpu.accumulator.get(), 0x42);
+        assert!(!cpu.status.is_carry_flag_set());
+        let stored: u8 = cpu.bus.read(0x10);
+        assert_eq!(stored, 0);
+
+        cpu.run(|_| {}).unwrap();
+        let stored: u8 = cpu.bus.read(0x10);
+        assert_eq!((stored, cpu.register_x.get()), (0x42, 0x07));
+    }
+}
@@ -0,0 +1,189 @@
+use crate::bus::{Bus, EmulationFlow};
+use crate::cpu::cpu::CPU;
+use crate::cpu::error::CPUError;
+use crate::cpu::snapshot::CpuSnapshot;
+use crate::rom::rom::Rom;
+use alloc::string::String;
+
+// Result reported by blargg-style test ROMs through PRG RAM.
+// https://github.com/christopherpow/nes-test-roms/blob/master/instr_test-v5/readme.txt
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestRomResult {
+    // 0 means the test passed, anything else is a failure code
+    pub code: u8,
+    pub message: String,
+}
+
+impl TestRomResult {
+    pub fn passed(&self) -> bool {
+        self.code == 0
+    }
+}
+
+// Powers the ROM on without a frontend and steps it until `stop` returns true
+// or the CPU halts. The machine is dropped afterwards, so a batch runner can
+// call it in a loop without keeping anything alive between ROMs:
+//
+//     for bytes in roms {
+//         let rom = Rom::new(&bytes)?;
+//         let state = run_rom_headless(rom, |cpu| cpu.bus.stats().frames >= 60)?;
+//         println!("{state}");
+//     }
+pub fn run_rom_headless<S>(rom: Rom, stop: S) -> Result<CpuSnapshot, CPUError>
+where
+    S: Fn(&CPU) -> bool,
+{
+    let mut cpu = CPU::new(Bus::new(rom, |_, _| EmulationFlow::Continue)?);
+    cpu.power_on();
+    while !stop(&cpu) && cpu.step()? {}
+    Ok(cpu.snapshot())
+}
+
+impl CPU<'_> {
+    const STATUS_ADDR: usize = 0x0000;
+    const SIGNATURE_ADDR: usize = 0x0001;
+    const MESSAGE_ADDR: usize = 0x0004;
+    // Written to $6001-$6003 once the status byte is valid
+    const SIGNATURE: [u8; 3] = [0xDE, 0xB0, 0x61];
+    const STATUS_RUNNING: u8 = 0x80;
+    const STATUS_NEEDS_RESET: u8 = 0x81;
+    // Several minutes of emulated time, far more than any blargg ROM needs
+    const TEST_ROM_BUDGET: u64 = 500_000_000;
+
+    // Runs the ROM until the status byte at $6000 leaves the running state and
+    // returns the final code with the text at $6004. A ROM asking for a reset
+    // gets one after 100 ms of emulated time, as the protocol requires.
+    // Fails with `BudgetExceeded` once the instruction budget, or a default
+    // one when none is set, runs out before the ROM reports a result.
+    pub fn run_test_rom(&mut self) -> Result<TestRomResult, CPUError> {
+        let budget = self.instruction_budget.unwrap_or(CPU::TEST_ROM_BUDGET);
+        let mut executed: u64 = 0;
+        let mut running = false;
+        let mut reset_at = None;
+        while self.step()? {
+            executed += 1;
+            if executed >= budget {
+                return Err(CPUError::BudgetExceeded(budget));
+            }
+            if self.bus.sram()[CPU::SIGNATURE_ADDR..CPU::MESSAGE_ADDR] != CPU::SIGNATURE {
+                continue;
+            }
+            match self.bus.sram()[CPU::STATUS_ADDR] {
+                CPU::STATUS_RUNNING => {
+                    running = true;
+                    reset_at = None;
+                }
+                CPU::STATUS_NEEDS_RESET => {
+                    let delay = self.bus.ppu.region().cpu_frequency() as usize / 10;
+                    if self.bus.cycles >= *reset_at.get_or_insert(self.bus.cycles + delay) {
+                        self.reset();
+                        reset_at = None;
+                    }
+                }
+                _ if running => break,
+                _ => {}
+            }
+        }
+        Ok(self.test_rom_result())
+    }
+
+    fn test_rom_result(&self) -> TestRomResult {
+        let sram = self.bus.sram();
+        let message = sram[CPU::MESSAGE_ADDR..]
+            .iter()
+            .take_while(|byte| **byte != 0)
+            .map(|byte| *byte as char)
+            .collect();
+        TestRomResult {
+            code: sram[CPU::STATUS_ADDR],
+            message,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::builder::CpuBuilder;
+    use crate::rom::rom::ines_image;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn test_run_test_rom_reads_code_and_message() {
+        let mut program = vec![];
+        // Signature and running status
+        for (address, value) in [(0x6001u16, 0xDE), (0x6002, 0xB0), (0x6003, 0x61)] {
+            program.extend([0xA9, value, 0x8D, address as u8, (address >> 8) as u8]);
+        }
+        program.extend([0xA9, 0x80, 0x8D, 0x00, 0x60]);
+        // "OK" message, then failure code 3
+        program.extend([0xA9, b'O', 0x8D, 0x04, 0x60, 0xA9, b'K', 0x8D, 0x05, 0x60]);
+        program.extend([0xA9, 0x03, 0x8D, 0x00, 0x60]);
+        // Never halts on its own
+        let end = CpuBuilder::PROGRAM_START + program.len() as u16;
+        program.extend([0x4C, end as u8, (end >> 8) as u8]);
+        let mut cpu = CPU::from_program(&program);
+
+        let result = cpu.run_test_rom().unwrap();
+
+        assert_eq!(result.code, 3);
+        assert_eq!(result.message, "OK");
+        assert!(!result.passed());
+    }
+
+    // NROM image running the program from $8000
+    fn rom_with_program(program: &[u8]) -> Rom {
+        let mut content = ines_image(0, 2, 1);
+        let prg = &mut content[16..16 + 2 * 0x4000];
+        prg[..program.len()].copy_from_slice(program);
+        // Reset vector
+        prg[0x7FFC] = 0x00;
+        prg[0x7FFD] = 0x80;
+        Rom::new(&content).unwrap()
+    }
+
+    #[test]
+    fn test_run_rom_headless_runs_roms_one_after_another() {
+        let roms = [
+            // LDA #$01; KIL
+            rom_with_program(&[0xA9, 0x01, 0x02]),
+            // LDX #$05; INX; JMP $8002
+            rom_with_program(&[0xA2, 0x05, 0xE8, 0x4C, 0x02, 0x80]),
+        ];
+
+        let results: Vec<CpuSnapshot> = roms
+            .into_iter()
+            .map(|rom| run_rom_headless(rom, |cpu| cpu.register_x.get() >= 0x10).unwrap())
+            .collect();
+
+        assert_eq!(results[0].accumulator, 0x01);
+        assert_eq!(results[1].register_x, 0x10);
+    }
+
+    #[test]
+    fn test_run_test_rom_stops_at_the_instruction_budget() {
+        // Never writes the signature: JMP $8000
+        let mut cpu = CPU::from_program(&[0x4C, 0x00, 0x80]);
+        cpu.set_instruction_budget(1000);
+
+        let result = cpu.run_test_rom();
+
+        assert!(matches!(result, Err(CPUError::BudgetExceeded(1000))));
+    }
+
+    // Built by roms/tests/official_nrom.py, asks for a reset before testing
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_official_nrom_passes() {
+        let bytes = std::fs::read("../roms/tests/official_nrom.nes").unwrap();
+        let rom = Rom::new(&bytes).unwrap();
+        let mut cpu = CPU::new(Bus::new(rom, |_, _| EmulationFlow::Continue).unwrap());
+        cpu.power_on();
+
+        let result = cpu.run_test_rom().unwrap();
+
+        assert!(result.passed(), "{}", result.message);
+        assert!(result.message.ends_with("Passed\n"), "{}", result.message);
+    }
+}
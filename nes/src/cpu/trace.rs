@@ -1,17 +1,35 @@
 use crate::bus::BusOperation;
-use crate::cpu::cpu::CPU;
+use crate::cpu::cpu::{CPU, resolve_indirect_jmp};
 use crate::cpu::error::UnknownOpCode;
 use crate::cpu::opcode::{AddressingMode, OPCODES, OpCode};
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
 
 const NON_READABLE_ADDRESSES: [u16; 11] = [
     0x2000, 0x2001, 0x2002, 0x2003, 0x2004, 0x2005, 0x2006, 0x2007, 0x4014, 0x4016, 0x4017,
 ];
 
+// Reading $4015 clears the frame interrupt flag, so APU registers aren't read
+// either. They're shown as $FF like in the reference nestest log.
+const APU_REGISTERS: [u16; 21] = [
+    0x4000, 0x4001, 0x4002, 0x4003, 0x4004, 0x4005, 0x4006, 0x4007, 0x4008, 0x4009, 0x400A, 0x400B,
+    0x400C, 0x400D, 0x400E, 0x400F, 0x4010, 0x4011, 0x4012, 0x4013, 0x4015,
+];
+
 pub fn trace(cpu: &mut CPU) -> String {
+    cpu.bus.set_access_ticking(false);
+    let log = trace_instruction(cpu);
+    cpu.bus.set_access_ticking(true);
+    log
+}
+
+fn trace_instruction(cpu: &mut CPU) -> String {
     let program_counter = cpu.program_counter.get();
     let raw_opcode = cpu.bus.read(program_counter);
     let opcode = OPCODES
-        .get(&raw_opcode)
+        .get(raw_opcode)
         .ok_or(UnknownOpCode(raw_opcode))
         .unwrap();
 
@@ -23,9 +41,13 @@ pub fn trace(cpu: &mut CPU) -> String {
         | AddressingMode::Implied
         | AddressingMode::Relative => (0, 0),
         _ => {
-            let (_, addr) = cpu.get_operand_address(&opcode.mode, program_counter + 1);
+            let (_, addr) = cpu
+                .get_operand_address(&opcode.mode, cpu.program_counter.peek_offset(1))
+                .expect("Modes with an operand always have an address");
 
-            if !NON_READABLE_ADDRESSES.contains(&addr) {
+            if APU_REGISTERS.contains(&addr) {
+                (addr, 0xFF)
+            } else if !NON_READABLE_ADDRESSES.contains(&addr) {
                 (addr, cpu.bus.read(addr))
             } else {
                 (addr, 0u8)
@@ -39,7 +61,7 @@ pub fn trace(cpu: &mut CPU) -> String {
             _ => "".to_string(),
         },
         1 => {
-            let address: u8 = cpu.bus.read(program_counter + 1);
+            let address: u8 = cpu.bus.read(cpu.program_counter.peek_offset(1));
             hex_dump.push(address);
 
             match opcode.mode {
@@ -70,8 +92,10 @@ pub fn trace(cpu: &mut CPU) -> String {
                 AddressingMode::Accumulator
                 | AddressingMode::Implied
                 | AddressingMode::Relative => {
-                    let address: usize =
-                        (program_counter as usize + 2).wrapping_add((address as i8) as usize);
+                    let address = cpu
+                        .program_counter
+                        .peek_offset(2)
+                        .wrapping_add_signed(address as i8 as i16);
                     format!("${:04x}", address)
                 }
                 _ => panic!(
@@ -81,22 +105,16 @@ pub fn trace(cpu: &mut CPU) -> String {
             }
         }
         2 => {
-            let address_lo = cpu.bus.read(program_counter + 1);
-            let address_hi = cpu.bus.read(program_counter + 2);
+            let address_lo = cpu.bus.read(cpu.program_counter.peek_offset(1));
+            let address_hi = cpu.bus.read(cpu.program_counter.peek_offset(2));
             hex_dump.push(address_lo);
             hex_dump.push(address_hi);
 
-            let address = cpu.bus.read(program_counter + 1);
+            let address = cpu.bus.read(cpu.program_counter.peek_offset(1));
 
             match (&opcode.opcode, &opcode.mode) {
                 (_, AddressingMode::Indirect) => {
-                    let jmp_addr = if address & 0x00FF == 0x00FF {
-                        let lo: u8 = cpu.bus.read(address);
-                        let hi: u8 = cpu.bus.read(address & 0xFF00);
-                        (hi as u16) << 8 | (lo as u16)
-                    } else {
-                        cpu.bus.read(address)
-                    };
+                    let jmp_addr = resolve_indirect_jmp(&mut cpu.bus, address);
                     format!("(${address:04x}) = {jmp_addr:04x}")
                 }
                 (
@@ -139,11 +157,8 @@ pub fn trace(cpu: &mut CPU) -> String {
         "{:04x}  {:8} {: >4} {}",
         program_counter,
         hex_str,
-        match raw_opcode {
-            0x1A | 0x3A | 0x5A | 0x7A | 0xDA | 0xFA => "*NOP".to_string(),
-            0xEB => "*SBC".to_string(),
-            _ => opcode.opcode.to_string(),
-        },
+        // Padding applies to strings, not to the Display impl
+        opcode.to_string(),
         tmp
     )
     .trim()
@@ -0,0 +1,78 @@
+use alloc::collections::VecDeque;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+// Timeline of interrupts and PPU status changes for post-mortem debugging
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EventKind {
+    // Taken by the CPU
+    Nmi,
+    Irq,
+    VblankSet,
+    // Cleared by a $2002 read or at the end of vblank
+    VblankCleared,
+    Sprite0Hit,
+    // Write to a mapper register ($8000-$FFFF), e.g. a bank switch
+    MapperWrite { address: u16, value: u8 },
+}
+
+// Tagged with the PPU position the event happened at
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Event {
+    pub kind: EventKind,
+    pub scanline: u16,
+    pub dot: usize,
+}
+
+// Ring buffer keeping the last `capacity` events
+pub struct EventLog {
+    events: VecDeque<Event>,
+    capacity: usize,
+}
+
+pub type SharedEventLog = Rc<RefCell<EventLog>>;
+
+impl EventLog {
+    pub fn new(capacity: usize) -> EventLog {
+        EventLog {
+            events: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, event: Event) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    pub fn drain(&mut self) -> Vec<Event> {
+        self.events.drain(..).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_log_keeps_the_latest_events() {
+        let mut log = EventLog::new(2);
+        for dot in 0..3 {
+            log.push(Event {
+                kind: EventKind::Nmi,
+                scanline: 0,
+                dot,
+            });
+        }
+
+        let dots: Vec<usize> = log.drain().iter().map(|event| event.dot).collect();
+        assert_eq!(dots, [1, 2]);
+        assert!(log.drain().is_empty());
+    }
+}
@@ -1,5 +1,15 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod apu;
 pub mod bus;
 pub mod controller;
 pub mod cpu;
+pub mod event;
+pub mod mapper;
 pub mod ppu;
+pub mod region;
 pub mod rom;
+
+pub use cpu::trace::trace;
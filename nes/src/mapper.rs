@@ -0,0 +1,5 @@
+pub mod error;
+pub mod mapper;
+pub mod mmc3;
+pub mod nrom;
+pub mod registry;
@@ -0,0 +1,14 @@
+use core::error::Error;
+use core::fmt::{Display, Formatter};
+
+// iNES mapper number without a mapper implementation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnsupportedMapper(pub u8);
+
+impl Display for UnsupportedMapper {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(f, "Mapper {} isn't supported", self.0)
+    }
+}
+
+impl Error for UnsupportedMapper {}
@@ -0,0 +1,67 @@
+use crate::mapper::error::UnsupportedMapper;
+use crate::mapper::mmc3::MMC3;
+use crate::mapper::nrom::NROM;
+use crate::ppu::mirroring::Mirroring;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::RefCell;
+
+pub type SharedMapper = Rc<RefCell<dyn Mapper>>;
+
+// https://www.nesdev.org/wiki/Mapper
+//
+// A mapper translates CPU and PPU addresses into offsets of the cartridge's
+// PRG and CHR memory. The memory itself stays in the bus and the PPU, so a
+// mapper only keeps its registers. Offsets must be within the PRG ROM and
+// CHR sizes the mapper was created for, wrap them when a game selects a bank
+// past the end.
+pub trait Mapper {
+    // Translates a CPU address in $8000-$FFFF into a PRG ROM offset.
+    fn map_prg(&self, address: u16) -> usize;
+
+    // Translates a PPU address in $0000-$1FFF into a CHR offset. Called for
+    // every pattern fetch, so bank switches take effect on the next fetch.
+    fn map_chr(&self, address: u16) -> usize;
+
+    // Handles a CPU write into $8000-$FFFF, i.e. a write to mapper registers.
+    fn write(&mut self, address: u16, value: u8);
+
+    // Mirroring selected by the mapper, `None` keeps the one from the iNES header.
+    // Queried on every nametable access.
+    fn mirroring(&self) -> Option<Mirroring> {
+        None
+    }
+
+    // Called when the PPU address line A12 goes from low to high.
+    fn on_a12_rise(&mut self) {}
+
+    // Level of the IRQ line, checked by the CPU before every instruction.
+    // It stays asserted until the mapper acknowledges it through `write`.
+    fn poll_irq(&self) -> bool {
+        false
+    }
+
+    // Registers as bytes for a save state, mappers without registers keep
+    // the default empty state. `load_state` only gets bytes returned by
+    // `save_state` of the same mapper.
+    fn save_state(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn load_state(&mut self, _state: &[u8]) {}
+}
+
+// Built-in mappers by iNES mapper number
+pub fn create_mapper(
+    mapper: u8,
+    prg_rom_size: usize,
+    chr_size: usize,
+    mirroring: Mirroring,
+) -> Result<SharedMapper, UnsupportedMapper> {
+    let mapper: SharedMapper = match mapper {
+        0 => Rc::new(RefCell::new(NROM::new(prg_rom_size))),
+        4 => Rc::new(RefCell::new(MMC3::new(prg_rom_size, chr_size, mirroring))),
+        _ => return Err(UnsupportedMapper(mapper)),
+    };
+    Ok(mapper)
+}
@@ -0,0 +1,228 @@
+use crate::mapper::mapper::Mapper;
+use crate::ppu::mirroring::Mirroring;
+use alloc::vec::Vec;
+
+// MMC3 (mapper 4)
+// https://www.nesdev.org/wiki/MMC3
+//
+// Bank select ($8000-$9FFE, even)
+// 7  bit  0
+// ---- ----
+// CPMx xRRR
+// |||   |||
+// |||   +++- Specify which bank register to update on next write to Bank Data register
+// |||          000: R0: Select 2 KB CHR bank at PPU $0000-$07FF (or $1000-$17FF)
+// |||          001: R1: Select 2 KB CHR bank at PPU $0800-$0FFF (or $1800-$1FFF)
+// |||          010: R2: Select 1 KB CHR bank at PPU $1000-$13FF (or $0000-$03FF)
+// |||          011: R3: Select 1 KB CHR bank at PPU $1400-$17FF (or $0400-$07FF)
+// |||          100: R4: Select 1 KB CHR bank at PPU $1800-$1BFF (or $0800-$0BFF)
+// |||          101: R5: Select 1 KB CHR bank at PPU $1C00-$1FFF (or $0C00-$0FFF)
+// |||          110: R6: Select 8 KB PRG ROM bank at $8000-$9FFF (or $C000-$DFFF)
+// |||          111: R7: Select 8 KB PRG ROM bank at $A000-$BFFF
+// ||+------- Nothing on the MMC3, see MMC6
+// |+-------- PRG ROM bank mode (0: $8000-$9FFF swappable,
+// |                                $C000-$DFFF fixed to second-last bank;
+// |                             1: $C000-$DFFF swappable,
+// |                                $8000-$9FFF fixed to second-last bank)
+// +--------- CHR A12 inversion (0: two 2 KB banks at $0000-$0FFF,
+//                                  four 1 KB banks at $1000-$1FFF;
+//                               1: two 2 KB banks at $1000-$1FFF,
+//                                  four 1 KB banks at $0000-$0FFF)
+pub struct MMC3 {
+    prg_rom_size: usize,
+    chr_size: usize,
+
+    bank_select: u8,
+    bank_registers: [u8; 8],
+    mirroring: Mirroring,
+
+    irq_latch: u8,
+    irq_counter: u8,
+    irq_reload: bool,
+    irq_enabled: bool,
+    irq_pending: bool,
+}
+
+impl MMC3 {
+    const PRG_BANK_SIZE: usize = 0x2000;
+    const CHR_BANK_SIZE: usize = 0x0400;
+
+    const PRG_BANK_MODE: u8 = 0b0100_0000;
+    const CHR_A12_INVERSION: u8 = 0b1000_0000;
+    // Bank select, 8 bank registers, mirroring and 5 IRQ bytes
+    const STATE_SIZE: usize = 15;
+
+    // Starts with the header's mirroring. Four-screen cartridges have their
+    // own nametable RAM and ignore the mirroring register.
+    pub fn new(prg_rom_size: usize, chr_size: usize, mirroring: Mirroring) -> MMC3 {
+        MMC3 {
+            prg_rom_size,
+            chr_size,
+
+            bank_select: 0,
+            bank_registers: [0; 8],
+            mirroring,
+
+            irq_latch: 0,
+            irq_counter: 0,
+            irq_reload: false,
+            irq_enabled: false,
+            irq_pending: false,
+        }
+    }
+
+    fn prg_bank_count(&self) -> usize {
+        self.prg_rom_size / MMC3::PRG_BANK_SIZE
+    }
+}
+
+impl Mapper for MMC3 {
+    fn map_prg(&self, address: u16) -> usize {
+        let second_last = self.prg_bank_count() - 2;
+        let last = self.prg_bank_count() - 1;
+        let r6 = self.bank_registers[6] as usize;
+        let r7 = self.bank_registers[7] as usize;
+
+        let bank = match (self.bank_select & MMC3::PRG_BANK_MODE != 0, address) {
+            (false, 0x8000..=0x9FFF) => r6,
+            (true, 0x8000..=0x9FFF) => second_last,
+            (_, 0xA000..=0xBFFF) => r7,
+            (false, 0xC000..=0xDFFF) => second_last,
+            (true, 0xC000..=0xDFFF) => r6,
+            _ => last,
+        };
+
+        (bank % self.prg_bank_count()) * MMC3::PRG_BANK_SIZE
+            + (address as usize % MMC3::PRG_BANK_SIZE)
+    }
+
+    fn map_chr(&self, mut address: u16) -> usize {
+        if self.bank_select & MMC3::CHR_A12_INVERSION != 0 {
+            address ^= 0x1000;
+        }
+
+        let registers = &self.bank_registers;
+        let bank = match address as usize / MMC3::CHR_BANK_SIZE {
+            0 => registers[0] & 0xFE,
+            1 => registers[0] | 0x01,
+            2 => registers[1] & 0xFE,
+            3 => registers[1] | 0x01,
+            4 => registers[2],
+            5 => registers[3],
+            6 => registers[4],
+            _ => registers[5],
+        } as usize;
+
+        (bank * MMC3::CHR_BANK_SIZE + address as usize % MMC3::CHR_BANK_SIZE) % self.chr_size
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match (address, address & 1) {
+            (0x8000..=0x9FFF, 0) => self.bank_select = value,
+            (0x8000..=0x9FFF, _) => {
+                self.bank_registers[(self.bank_select & 0b111) as usize] = value;
+            }
+            (0xA000..=0xBFFF, 0) if self.mirroring != Mirroring::FourScreen => {
+                self.mirroring = if value & 1 == 0 {
+                    Mirroring::Vertical
+                } else {
+                    Mirroring::Horizontal
+                };
+            }
+            // PRG RAM protect isn't emulated
+            (0xA000..=0xBFFF, _) => {}
+            (0xC000..=0xDFFF, 0) => self.irq_latch = value,
+            (0xC000..=0xDFFF, _) => {
+                self.irq_counter = 0;
+                self.irq_reload = true;
+            }
+            (0xE000..=0xFFFF, 0) => {
+                self.irq_enabled = false;
+                self.irq_pending = false;
+            }
+            (0xE000..=0xFFFF, _) => self.irq_enabled = true,
+            _ => {}
+        }
+    }
+
+    fn mirroring(&self) -> Option<Mirroring> {
+        (self.mirroring != Mirroring::FourScreen).then_some(self.mirroring)
+    }
+
+    // https://www.nesdev.org/wiki/MMC3#IRQ_Specifics
+    fn on_a12_rise(&mut self) {
+        if self.irq_counter == 0 || self.irq_reload {
+            self.irq_counter = self.irq_latch;
+            self.irq_reload = false;
+        } else {
+            self.irq_counter -= 1;
+        }
+
+        if self.irq_counter == 0 && self.irq_enabled {
+            self.irq_pending = true;
+        }
+    }
+
+    fn poll_irq(&self) -> bool {
+        self.irq_pending
+    }
+
+    fn save_state(&self) -> Vec<u8> {
+        let mut state = Vec::with_capacity(MMC3::STATE_SIZE);
+        state.push(self.bank_select);
+        state.extend_from_slice(&self.bank_registers);
+        state.push((self.mirroring == Mirroring::Horizontal) as u8);
+        state.extend_from_slice(&[
+            self.irq_latch,
+            self.irq_counter,
+            self.irq_reload as u8,
+            self.irq_enabled as u8,
+            self.irq_pending as u8,
+        ]);
+        state
+    }
+
+    fn load_state(&mut self, state: &[u8]) {
+        let Some(state) = state.get(..MMC3::STATE_SIZE) else {
+            return;
+        };
+        self.bank_select = state[0];
+        self.bank_registers.copy_from_slice(&state[1..9]);
+        if self.mirroring != Mirroring::FourScreen {
+            self.mirroring = if state[9] == 0 {
+                Mirroring::Vertical
+            } else {
+                Mirroring::Horizontal
+            };
+        }
+        self.irq_latch = state[10];
+        self.irq_counter = state[11];
+        self.irq_reload = state[12] != 0;
+        self.irq_enabled = state[13] != 0;
+        self.irq_pending = state[14] != 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mirroring_starts_from_the_header() {
+        let mut mmc3 = MMC3::new(0x8000, 0x2000, Mirroring::Horizontal);
+        assert_eq!(mmc3.mirroring(), Some(Mirroring::Horizontal));
+
+        mmc3.write(0xA000, 0);
+        assert_eq!(mmc3.mirroring(), Some(Mirroring::Vertical));
+    }
+
+    #[test]
+    fn test_four_screen_ignores_the_mirroring_register() {
+        let mut mmc3 = MMC3::new(0x8000, 0x2000, Mirroring::FourScreen);
+
+        mmc3.write(0xA000, 1);
+        mmc3.load_state(&[0; MMC3::STATE_SIZE]);
+
+        assert_eq!(mmc3.mirroring(), None);
+    }
+}
@@ -0,0 +1,29 @@
+use crate::mapper::mapper::Mapper;
+
+// NROM (mapper 0)
+// https://www.nesdev.org/wiki/NROM
+//
+// 16 KiB PRG ROM is mirrored into $C000-$FFFF, CHR is not banked.
+pub struct NROM {
+    prg_rom_size: usize,
+}
+
+impl NROM {
+    pub fn new(prg_rom_size: usize) -> NROM {
+        NROM { prg_rom_size }
+    }
+}
+
+impl Mapper for NROM {
+    fn map_prg(&self, address: u16) -> usize {
+        (address as usize - 0x8000) % self.prg_rom_size
+    }
+
+    fn map_chr(&self, address: u16) -> usize {
+        address as usize
+    }
+
+    // NROM has no registers and ROM ignores writes, e.g. the dummy write of
+    // a read-modify-write instruction
+    fn write(&mut self, _address: u16, _value: u8) {}
+}
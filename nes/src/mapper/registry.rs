@@ -0,0 +1,176 @@
+use crate::mapper::error::UnsupportedMapper;
+use crate::mapper::mapper::{SharedMapper, create_mapper};
+use crate::rom::rom::Rom;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::sync::{PoisonError, RwLock};
+
+// Builds a mapper for a cartridge. It borrows the ROM and returns a shared
+// mapper, since the bus keeps the ROM and hands the mapper to the PPU too.
+pub type MapperFactory = fn(&Rom) -> SharedMapper;
+
+// Custom mappers `Bus::new` and `Bus::load_rom` fall back to when the ROM's
+// mapper number isn't built in
+#[cfg(feature = "std")]
+static REGISTRY: RwLock<MapperRegistry> = RwLock::new(MapperRegistry::new());
+
+// Lets applications add mappers the crate doesn't implement. Built-in
+// mappers are looked up first, so only unsupported numbers can be added.
+// Buses created afterwards with `Bus::new` load such cartridges:
+//
+//     register_mapper(2, |rom| Rc::new(RefCell::new(UxROM::new(rom))));
+//     let bus = Bus::new(rom, |_, _| EmulationFlow::Continue)?;
+#[cfg(feature = "std")]
+pub fn register_mapper(number: u8, factory: MapperFactory) {
+    REGISTRY
+        .write()
+        .unwrap_or_else(PoisonError::into_inner)
+        .register_mapper(number, factory);
+}
+
+// A set of custom mappers. Without `std` there is no global registry, pass
+// one to `Bus::with_registry` instead.
+#[derive(Clone)]
+pub struct MapperRegistry {
+    factories: Vec<(u8, MapperFactory)>,
+}
+
+impl MapperRegistry {
+    pub const fn new() -> MapperRegistry {
+        MapperRegistry {
+            factories: Vec::new(),
+        }
+    }
+
+    // Mappers added with `register_mapper` so far
+    pub fn global() -> MapperRegistry {
+        #[cfg(feature = "std")]
+        {
+            REGISTRY
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .clone()
+        }
+        #[cfg(not(feature = "std"))]
+        {
+            MapperRegistry::new()
+        }
+    }
+
+    // Registering the same number again replaces the previous factory
+    pub fn register_mapper(&mut self, number: u8, factory: MapperFactory) {
+        self.factories
+            .retain(|(registered, _)| *registered != number);
+        self.factories.push((number, factory));
+    }
+
+    pub fn create(&self, rom: &Rom) -> Result<SharedMapper, UnsupportedMapper> {
+        create_mapper(
+            rom.mapper(),
+            rom.prg_rom.len(),
+            rom.chr_rom.len(),
+            rom.mirroring,
+        )
+        .or_else(|error| {
+            self.factories
+                .iter()
+                .find(|(number, _)| *number == rom.mapper())
+                .map(|(_, factory)| factory(rom))
+                .ok_or(error)
+        })
+    }
+}
+
+impl Default for MapperRegistry {
+    fn default() -> Self {
+        MapperRegistry::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bus::{Bus, BusOperation, EmulationFlow};
+    use crate::mapper::mapper::Mapper;
+    use crate::ppu::mirroring::Mirroring;
+    use crate::rom::rom::ines_image;
+    use alloc::rc::Rc;
+    use core::cell::RefCell;
+
+    // UxROM (mapper 2): switchable 16 KiB bank at $8000, last bank fixed at $C000
+    struct UxROM {
+        bank: usize,
+        bank_count: usize,
+    }
+
+    impl Mapper for UxROM {
+        fn map_prg(&self, address: u16) -> usize {
+            let bank = if address < 0xC000 {
+                self.bank
+            } else {
+                self.bank_count - 1
+            };
+            bank * 0x4000 + (address as usize & 0x3FFF)
+        }
+
+        fn map_chr(&self, address: u16) -> usize {
+            address as usize
+        }
+
+        fn write(&mut self, _address: u16, value: u8) {
+            self.bank = value as usize % self.bank_count;
+        }
+    }
+
+    fn uxrom_rom() -> Rom {
+        let mut content = ines_image(2, 4, 0);
+        for (bank, data) in content[16..].chunks_mut(0x4000).enumerate() {
+            data.fill(bank as u8);
+        }
+        Rom::new(&content).unwrap()
+    }
+
+    fn uxrom(rom: &Rom) -> SharedMapper {
+        Rc::new(RefCell::new(UxROM {
+            bank: 0,
+            bank_count: rom.prg_rom.len() / 0x4000,
+        }))
+    }
+
+    fn assert_uxrom_banks(bus: &mut Bus) {
+        assert_eq!(bus.peek(0x8000), 0);
+        assert_eq!(bus.peek(0xC000), 3);
+        BusOperation::<u8>::write(bus, 0x8000, 2);
+        assert_eq!(bus.peek(0x8000), 2);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_registered_mapper_loads_through_bus_new() {
+        register_mapper(2, uxrom);
+        let rom = uxrom_rom();
+        assert_eq!(rom.mapper(), 2);
+
+        let mut bus = Bus::new(rom, |_, _| EmulationFlow::Continue).unwrap();
+
+        assert_uxrom_banks(&mut bus);
+    }
+
+    #[test]
+    fn test_bus_registry_is_used_when_loading_a_rom() {
+        let mut registry = MapperRegistry::new();
+        registry.register_mapper(2, uxrom);
+        let nrom = Rom::from_prg(&[0; 0x4000], &[], Mirroring::Vertical);
+        let mut bus = Bus::with_registry(nrom, registry, |_, _| EmulationFlow::Continue).unwrap();
+
+        bus.load_rom(uxrom_rom()).unwrap();
+
+        assert_uxrom_banks(&mut bus);
+    }
+
+    #[test]
+    fn test_unregistered_mapper_is_not_created() {
+        let result = MapperRegistry::new().create(&uxrom_rom());
+        assert!(matches!(result, Err(UnsupportedMapper(2))));
+    }
+}
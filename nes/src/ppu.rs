@@ -2,3 +2,5 @@ pub mod mirroring;
 pub mod palette;
 pub mod ppu;
 pub mod register;
+pub mod render;
+pub mod sprite;
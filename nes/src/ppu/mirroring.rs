@@ -1,6 +1,10 @@
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Mirroring {
     Vertical,
     Horizontal,
     FourScreen,
+    // Every nametable shows the first (A) or the second (B) 1 KiB of VRAM
+    SingleScreenA,
+    SingleScreenB,
 }
@@ -1,14 +1,23 @@
+use crate::controller::zapper::Zapper;
+use crate::event::{Event, EventKind, SharedEventLog};
+use crate::mapper::mapper::SharedMapper;
 use crate::ppu::mirroring::Mirroring;
+use crate::ppu::palette::SYSTEM_PALETTE;
+use crate::ppu::register::loopy::LoopyRegisters;
 use crate::ppu::register::oamaddr::OAMADDR;
 use crate::ppu::register::oamdata::OAMDATA;
 use crate::ppu::register::oamdma::OAMDMA;
-use crate::ppu::register::ppuaddr::PPUADDR;
 use crate::ppu::register::ppuctrl::PPUCTRL;
 use crate::ppu::register::ppudata::PPUDATA;
 use crate::ppu::register::ppumask::PPUMASK;
-use crate::ppu::register::ppuscroll::PPUSCROLL;
 use crate::ppu::register::ppustatus::PPUSTATUS;
-use std::ops::Range;
+use crate::ppu::render::{FrameBuffer, RenderLayers, render_indices};
+use crate::ppu::sprite::{Sprite, SpriteEntry};
+use crate::region::Region;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use core::ops::Range;
 
 pub struct PPU {
     // PPU Registers
@@ -18,89 +27,342 @@ pub struct PPU {
     ppustatus: PPUSTATUS,
     oamaddr: OAMADDR,
     oamdata: OAMDATA,
-    ppuscroll: PPUSCROLL,
-    ppuaddr: PPUADDR,
     ppudata: PPUDATA,
     oamdma: OAMDMA,
+    // PPUSCROLL and PPUADDR are written through the internal v/t/x/w registers
+    loopy: LoopyRegisters,
 
     chr_rom: Vec<u8>,
+    chr_ram: bool,
     mirroring: Mirroring,
-    vram: [u8; 2048],
+    mapper: SharedMapper,
+    a12: bool,
+    // 2 KiB of console VRAM plus 2 KiB a four-screen cartridge adds
+    vram: [u8; 4096],
     palette_table: [u8; 32],
     oam_data: [u8; 256],
 
+    // Scroll position and nametable each visible scanline was drawn with,
+    // so the renderer can reproduce mid-frame scroll changes (split screens).
+    scanline_scroll: [(u8, u8); 240],
+    scanline_nametable: [u16; 240],
+    scanline_mirroring: [Mirroring; 240],
+    render_layers: RenderLayers,
+    frame: FrameBuffer,
+    skip_rendering: bool,
+
     pub scanline: u16,
     pub cycles: usize,
     nmi_interrupt: bool,
+    // Vblank of the current frame already started or was suppressed by a $2002 read
+    vblank_started: bool,
+    event_log: Option<SharedEventLog>,
+    region: Region,
 }
 
 impl PPU {
     const CHR_ROM_START: u16 = 0x0000;
     const CHR_ROM_END: u16 = 0x1FFF;
+    const PATTERN_TABLE_SIZE: usize = 0x1000;
+    const EMPTY_TILE: [u8; 16] = [0; 16];
+    // Palette contents commonly observed after power on
+    // https://www.nesdev.org/wiki/PPU_power_up_state
+    const POWER_UP_PALETTE: [u8; 32] = [
+        0x09, 0x01, 0x00, 0x01, 0x00, 0x02, 0x02, 0x0D, 0x08, 0x10, 0x08, 0x24, 0x00, 0x00, 0x04,
+        0x2C, 0x09, 0x01, 0x34, 0x03, 0x00, 0x04, 0x00, 0x14, 0x08, 0x3A, 0x00, 0x02, 0x00, 0x20,
+        0x2C, 0x08,
+    ];
+    const OAM_ATTRIBUTE_MASK: u8 = 0b1110_0011;
+    pub const SPRITES_PER_SCANLINE: usize = 8;
+    pub const FRAME_WIDTH: usize = 256;
+    pub const FRAME_HEIGHT: usize = 240;
+    const VISIBLE_SCANLINES: usize = 240;
 
     const VRAM_START: u16 = 0x2000;
     const VRAM_END: u16 = 0x2FFF;
     const VRAM_NAMETABLE_SIZE: u16 = 0x0400;
+    // Mirrors $2000-$2EFF
+    const UNUSED_SPACE_START: u16 = 0x3000;
+    const UNUSED_SPACE_END: u16 = 0x3EFF;
+    const ATTRIBUTE_TABLE_OFFSET: usize = 0x03C0;
 
     const PALETTE_RAM_START: u16 = 0x3F00;
     const PALETTE_RAM_END: u16 = 0x3FFF;
 
-    pub fn new(chr_rom: Vec<u8>, mirroring: Mirroring) -> Self {
+    pub fn new(
+        chr_rom: Vec<u8>,
+        mirroring: Mirroring,
+        chr_ram: bool,
+        region: Region,
+        mapper: SharedMapper,
+    ) -> Self {
         PPU {
             ppuctrl: PPUCTRL::new(),
             ppumask: PPUMASK::new(),
             ppustatus: PPUSTATUS::new(),
             oamaddr: OAMADDR::new(),
             oamdata: OAMDATA::new(),
-            ppuscroll: PPUSCROLL::new(),
-            ppuaddr: PPUADDR::new(),
             ppudata: PPUDATA::new(),
             oamdma: OAMDMA::new(),
+            loopy: LoopyRegisters::new(),
 
             chr_rom,
+            chr_ram,
             mirroring,
-            vram: [0; 2048],
+            mapper,
+            a12: false,
+            vram: [0; 4096],
             palette_table: [0; 32],
             oam_data: [0; 256],
 
+            scanline_scroll: [(0, 0); 240],
+            scanline_nametable: [PPU::VRAM_START; 240],
+            scanline_mirroring: [mirroring; 240],
+            render_layers: RenderLayers::default(),
+            frame: FrameBuffer::new(),
+            skip_rendering: false,
+
             scanline: 0,
             cycles: 0,
             nmi_interrupt: false,
+            vblank_started: false,
+            event_log: None,
+            region,
         }
     }
 
+    pub fn region(&self) -> Region {
+        self.region
+    }
+
+    // A frontend setting rather than emulation state, save states keep it as is
+    pub fn set_render_layers(&mut self, layers: RenderLayers) {
+        self.render_layers = layers;
+    }
+
+    pub fn render_layers(&self) -> RenderLayers {
+        self.render_layers
+    }
+
+    // Palette indices (0-63) of the last frame, row by row, before they are
+    // mapped to RGB. Filters working in NES color space (e.g. NTSC) use it.
+    pub fn index_buffer(&self) -> &[u8] {
+        &self.frame.indices
+    }
+
+    // Luma of a pixel of the last frame in the default system palette, what
+    // the Zapper's photodiode reacts to. Pixels off the screen are dark.
+    pub fn pixel_brightness(&self, x: usize, y: usize) -> u8 {
+        if x >= PPU::FRAME_WIDTH || y >= PPU::FRAME_HEIGHT {
+            return 0;
+        }
+        let color_idx = self.frame.indices[y * PPU::FRAME_WIDTH + x];
+        let (red, green, blue) = SYSTEM_PALETTE[(color_idx & 0x3F) as usize];
+        Zapper::luma(red, green, blue)
+    }
+
+    // Set by the bus for frames its frame skip drops, `index_buffer` keeps
+    // the last drawn frame then.
+    pub(crate) fn set_skip_rendering(&mut self, skip: bool) {
+        self.skip_rendering = skip;
+    }
+
+    // Draws the frame from the scroll and mirroring recorded per scanline
+    fn render_frame(&mut self) {
+        let mut frame = core::mem::take(&mut self.frame);
+        render_indices(self, &mut frame);
+        self.frame = frame;
+    }
+
+    // Replaces the zeroed palette and OAM with what hardware tends to power
+    // up with, for games and test ROMs reading them before writing. OAM is
+    // filled with $FF, which also keeps every sprite below the screen.
+    pub fn load_power_up_memory(&mut self) {
+        self.palette_table = PPU::POWER_UP_PALETTE;
+        self.oam_data = [0xFF; 256];
+    }
+
+    // PPUCTRL, PPUMASK, PPUSCROLL, the write toggle and the PPUDATA read buffer are cleared
+    // on reset, VRAM, OAM and palette contents are left as is.
+    // https://www.nesdev.org/wiki/PPU_power_up_state
+    pub fn reset(&mut self) {
+        self.write_ppuctrl(0);
+        self.write_ppumask(0);
+        self.loopy.reset_w();
+        self.write_ppuscroll(0);
+        self.write_ppuscroll(0);
+        self.ppudata = PPUDATA::new();
+        self.nmi_interrupt = false;
+    }
+
     pub fn tick(&mut self, cycles: u8) -> bool {
         self.cycles += cycles as usize;
 
         if self.cycles < 341 {
+            self.start_vblank();
             return false;
         }
 
-        if self.is_sprite_0_hit(self.cycles) {
-            self.ppustatus.set(PPUSTATUS::SPRITE_ZERO_HIT_FLAG, false);
+        if !self.ppustatus.contains(PPUSTATUS::SPRITE_ZERO_HIT_FLAG)
+            && self.is_sprite_0_hit(self.cycles)
+        {
+            self.ppustatus.set(PPUSTATUS::SPRITE_ZERO_HIT_FLAG, true);
+            self.record_event(EventKind::Sprite0Hit);
+        }
+
+        if self.is_rendering_scanline() {
+            if self.scanline < 240 {
+                let line = self.scanline as usize;
+                self.scanline_scroll[line] = (self.loopy.scroll_x(), self.loopy.scroll_y());
+                self.scanline_nametable[line] = self.loopy.nametable_address();
+                self.scanline_mirroring[line] = self.current_mirroring();
+                if self
+                    .sprites_on_scanline(line)
+                    .nth(PPU::SPRITES_PER_SCANLINE)
+                    .is_some()
+                {
+                    self.ppustatus.set(PPUSTATUS::SPRITE_OVERFLOW, true);
+                }
+            }
+            self.fetch_scanline_patterns();
+            // Dot 257: horizontal position is reloaded from t
+            self.loopy.copy_horizontal_t_to_v();
+            // OAMADDR is cleared during sprite tile loading (dots 257-320)
+            self.oamaddr.write(0);
         }
 
         self.cycles -= 341;
         self.scanline += 1;
 
-        // https://www.nesdev.org/wiki/PPU_rendering#Vertical_blanking_lines_(241-260)
-        if self.scanline == 241 {
-            self.ppustatus.set(PPUSTATUS::VBLANK_FLAG, true);
-            self.ppustatus.set(PPUSTATUS::SPRITE_ZERO_HIT_FLAG, false);
-            self.nmi_interrupt = self.ppuctrl.contains(PPUCTRL::NMI_ENABLE);
-        }
+        self.start_vblank();
 
-        if self.scanline >= 262 {
+        if self.scanline >= self.region.scanlines_per_frame() {
             self.scanline = 0;
             self.nmi_interrupt = false;
+            self.vblank_started = false;
+            if self.ppustatus.contains(PPUSTATUS::VBLANK_FLAG) {
+                self.record_event(EventKind::VblankCleared);
+            }
             self.ppustatus.set(PPUSTATUS::VBLANK_FLAG, false);
             self.ppustatus.set(PPUSTATUS::SPRITE_ZERO_HIT_FLAG, false);
+            self.ppustatus.set(PPUSTATUS::SPRITE_OVERFLOW, false);
+            // Pre-render scanline: the whole scroll position is reloaded from t
+            if self.is_rendering_enabled() {
+                self.loopy.copy_t_to_v();
+            }
+            if !self.skip_rendering {
+                self.render_frame();
+            }
             return true;
         }
 
         false
     }
 
+    // Vblank starts at dot 1 of the first vblank scanline
+    // https://www.nesdev.org/wiki/PPU_rendering#Vertical_blanking_lines_(241-260)
+    fn start_vblank(&mut self) {
+        if self.vblank_started || self.scanline != self.region.vblank_scanline() || self.cycles < 1
+        {
+            return;
+        }
+        self.vblank_started = true;
+        self.ppustatus.set(PPUSTATUS::VBLANK_FLAG, true);
+        self.record_event(EventKind::VblankSet);
+        self.ppustatus.set(PPUSTATUS::SPRITE_ZERO_HIT_FLAG, false);
+        self.nmi_interrupt = self.ppuctrl.contains(PPUCTRL::NMI_ENABLE);
+    }
+
+    pub(crate) fn set_event_log(&mut self, event_log: Option<SharedEventLog>) {
+        self.event_log = event_log;
+    }
+
+    // Copy of the PPU for a save state. CHR ROM never changes, so it's only
+    // copied when the cartridge has CHR RAM. The mapper is shared, its
+    // registers are saved by the bus.
+    pub(crate) fn save_state(&self) -> PPU {
+        PPU {
+            ppuctrl: self.ppuctrl,
+            ppumask: self.ppumask,
+            ppustatus: self.ppustatus,
+            oamaddr: self.oamaddr.clone(),
+            oamdata: self.oamdata.clone(),
+            ppudata: self.ppudata.clone(),
+            oamdma: self.oamdma.clone(),
+            loopy: self.loopy.clone(),
+
+            chr_rom: if self.chr_ram {
+                self.chr_rom.clone()
+            } else {
+                Vec::new()
+            },
+            chr_ram: self.chr_ram,
+            mirroring: self.mirroring,
+            mapper: self.mapper.clone(),
+            a12: self.a12,
+            vram: self.vram,
+            palette_table: self.palette_table,
+            oam_data: self.oam_data,
+
+            scanline_scroll: self.scanline_scroll,
+            scanline_nametable: self.scanline_nametable,
+            scanline_mirroring: self.scanline_mirroring,
+            render_layers: self.render_layers,
+            // The frame is redrawn at the end of every frame
+            frame: FrameBuffer::default(),
+            skip_rendering: self.skip_rendering,
+
+            scanline: self.scanline,
+            cycles: self.cycles,
+            nmi_interrupt: self.nmi_interrupt,
+            vblank_started: self.vblank_started,
+            event_log: None,
+            region: self.region,
+        }
+    }
+
+    // Restores a state taken by `save_state`, keeping the mapper, the event
+    // log and CHR ROM of this PPU.
+    pub(crate) fn load_state(&mut self, state: &PPU) {
+        self.ppuctrl = state.ppuctrl;
+        self.ppumask = state.ppumask;
+        self.ppustatus = state.ppustatus;
+        self.oamaddr = state.oamaddr.clone();
+        self.oamdata = state.oamdata.clone();
+        self.ppudata = state.ppudata.clone();
+        self.oamdma = state.oamdma.clone();
+        self.loopy = state.loopy.clone();
+
+        if self.chr_ram {
+            self.chr_rom.clone_from(&state.chr_rom);
+        }
+        self.mirroring = state.mirroring;
+        self.a12 = state.a12;
+        self.vram = state.vram;
+        self.palette_table = state.palette_table;
+        self.oam_data = state.oam_data;
+
+        self.scanline_scroll = state.scanline_scroll;
+        self.scanline_nametable = state.scanline_nametable;
+        self.scanline_mirroring = state.scanline_mirroring;
+
+        self.scanline = state.scanline;
+        self.cycles = state.cycles;
+        self.nmi_interrupt = state.nmi_interrupt;
+        self.vblank_started = state.vblank_started;
+        self.region = state.region;
+    }
+
+    pub(crate) fn record_event(&self, kind: EventKind) {
+        if let Some(event_log) = &self.event_log {
+            event_log.borrow_mut().push(Event {
+                kind,
+                scanline: self.scanline,
+                dot: self.cycles,
+            });
+        }
+    }
+
     pub fn poll_nmi_interrupt(&mut self) -> bool {
         if self.nmi_interrupt {
             self.nmi_interrupt = false;
@@ -113,6 +375,7 @@ impl PPU {
     pub fn write_ppuctrl(&mut self, value: u8) {
         let nmi_disabled = !self.ppuctrl.contains(PPUCTRL::NMI_ENABLE);
         self.ppuctrl.write(value);
+        self.loopy.write_ppuctrl(value);
         self.nmi_interrupt = nmi_disabled
             && self.ppuctrl.contains(PPUCTRL::NMI_ENABLE)
             && self.ppustatus.contains(PPUSTATUS::VBLANK_FLAG);
@@ -127,28 +390,35 @@ impl PPU {
     }
 
     pub fn write_oamdata(&mut self, value: u8) {
-        self.oam_data[self.oamaddr.read() as usize] = value;
-        self.oamaddr.inc()
+        self.store_oam_byte(value);
     }
 
     pub fn write_ppuscroll(&mut self, value: u8) {
-        self.ppuscroll.write(value);
+        self.loopy.write_ppuscroll(value);
     }
 
     pub fn write_ppuaddr(&mut self, address_part: u8) {
-        self.ppuaddr.write(address_part);
+        self.loopy.write_ppuaddr(address_part);
+        self.update_a12(self.loopy.vram_address());
     }
 
     pub fn write_ppudata(&mut self, value: u8) {
-        let address = self.ppuaddr.read();
+        let address = self.loopy.vram_address();
+        self.update_a12(address);
 
         match address {
-            PPU::CHR_ROM_START..=PPU::CHR_ROM_END => self.chr_rom[address as usize] = value,
-            PPU::VRAM_START..=PPU::VRAM_END => {
+            // Pattern tables are writable only when a cartridge has CHR RAM
+            PPU::CHR_ROM_START..=PPU::CHR_ROM_END => {
+                if self.chr_ram {
+                    let chr_address = self.mapper.borrow().map_chr(address);
+                    self.chr_rom[chr_address] = value
+                }
+            }
+            PPU::VRAM_START..=PPU::VRAM_END | PPU::UNUSED_SPACE_START..=PPU::UNUSED_SPACE_END => {
                 self.vram[self.mirror_vram_addr(address) as usize] = value
             }
             PPU::PALETTE_RAM_START..=PPU::PALETTE_RAM_END => {
-                self.palette_table[(address - PPU::PALETTE_RAM_START) as usize] = value
+                self.palette_table[PPU::mirror_palette_addr(address as usize)] = value
             }
             _ => panic!("Unexpected access to mirrored space {address:04x}"),
         };
@@ -156,16 +426,38 @@ impl PPU {
         self.increment_ppuaddr();
     }
 
+    // DMA writes through OAMDATA starting at OAMADDR, after 256 increments
+    // the address wraps back to where it started.
     pub fn write_oamdma(&mut self, value: &[u8; 256]) {
         for &x in value.iter() {
-            self.oam_data[self.oamaddr.read() as usize] = x;
-            self.oamaddr.inc();
+            self.store_oam_byte(x);
         }
     }
 
+    // Bits 2-4 of the sprite attribute byte don't exist in OAM and read back as 0
+    fn store_oam_byte(&mut self, value: u8) {
+        let address = self.oamaddr.read() as usize;
+        self.oam_data[address] = if address % 4 == 2 {
+            value & PPU::OAM_ATTRIBUTE_MASK
+        } else {
+            value
+        };
+        self.oamaddr.inc();
+    }
+
     pub fn read_sprite_tile(&self, tile: usize) -> &[u8] {
         let bank = self.ppuctrl.sprite_pattern_address() as usize;
-        &self.chr_rom[(bank + tile * 16)..=(bank + tile * 16 + 15)]
+        self.read_chr_tile(bank + tile * 16)
+    }
+
+    // Reads a tile from a 4 KiB pattern table regardless of PPUCTRL.
+    // Bank 0 is $0000, bank 1 is $1000, higher banks page through the rest of CHR.
+    pub fn pattern_tile(&self, bank: usize, tile: usize) -> &[u8; 16] {
+        self.chr_tile_at(bank * PPU::PATTERN_TABLE_SIZE + tile * 16)
+    }
+
+    pub fn chr_len(&self) -> usize {
+        self.chr_rom.len()
     }
 
     pub fn read_vram(&self, address: usize) -> u8 {
@@ -176,15 +468,69 @@ impl PPU {
         self.oam_data[address]
     }
 
+    pub fn sprite_height(&self) -> usize {
+        if self.ppuctrl.contains(PPUCTRL::SPRITE_SIZE) {
+            16
+        } else {
+            8
+        }
+    }
+
+    // Sprite evaluation: OAM indices of the sprites covering the scanline in
+    // priority order. Hardware keeps only the first 8 and flags an overflow.
+    // https://www.nesdev.org/wiki/PPU_sprite_evaluation
+    pub fn sprites_on_scanline(&self, scanline: usize) -> impl Iterator<Item = usize> + '_ {
+        let height = self.sprite_height();
+        (0..64).filter(move |&i| {
+            let y = self.oam_data[i * 4] as usize;
+            (y..y + height).contains(&scanline)
+        })
+    }
+
+    // Sprites placed on the screen in OAM (priority) order. Sprites moved below
+    // the last scanline, the usual way to hide them, are skipped.
+    pub fn visible_sprites(&self) -> impl DoubleEndedIterator<Item = Sprite> + '_ {
+        (0..64)
+            .map(|i| Sprite::new(i, self.oam_entry(i)))
+            .filter(|sprite| (sprite.y as usize) < PPU::VISIBLE_SCANLINES)
+    }
+
+    // Debugger access to one of the 64 OAM entries
+    pub fn oam_entry(&self, index: usize) -> SpriteEntry {
+        SpriteEntry::new(&self.oam_data[index * 4..index * 4 + 4])
+    }
+
+    // Debugger access to a byte of a logical nametable (0-3, $2000/$2400/$2800/$2C00)
+    // after mirroring is applied. Unlike PPUDATA it doesn't touch v or the read buffer.
+    pub fn nametable_byte(&self, nt: usize, offset: usize) -> u8 {
+        let address = PPU::VRAM_START as usize
+            + (nt % 4) * PPU::VRAM_NAMETABLE_SIZE as usize
+            + offset % PPU::VRAM_NAMETABLE_SIZE as usize;
+        self.vram[self.mirror_vram_addr(address as u16) as usize]
+    }
+
     pub fn read_palette_table(&self, address: usize) -> u8 {
-        self.palette_table[address]
+        self.palette_table[PPU::mirror_palette_addr(address)]
     }
 
+    // Reading $2002 races with the vblank flag being set: a read one dot
+    // before returns it clear and the flag is never set for the frame, a read
+    // on the same dot or one later returns it set but cancels the NMI.
+    // https://www.nesdev.org/wiki/NMI#Race_condition
     pub fn read_ppustatus(&mut self) -> u8 {
+        if self.scanline == self.region.vblank_scanline() {
+            match self.cycles {
+                0 => self.vblank_started = true,
+                1 | 2 => self.nmi_interrupt = false,
+                _ => {}
+            }
+        }
         let status = self.ppustatus.read();
+        if self.ppustatus.contains(PPUSTATUS::VBLANK_FLAG) {
+            self.record_event(EventKind::VblankCleared);
+        }
         self.ppustatus.set(PPUSTATUS::VBLANK_FLAG, false);
-        self.ppuaddr.reset_latch();
-        self.ppuscroll.reset_latch();
+        self.loopy.reset_w();
         status
     }
 
@@ -193,56 +539,215 @@ impl PPU {
     }
 
     pub fn read_ppudata(&mut self) -> u8 {
-        let address = self.ppuaddr.read();
+        let address = self.loopy.vram_address();
+        self.update_a12(address);
 
         self.increment_ppuaddr();
 
         match address {
             PPU::CHR_ROM_START..=PPU::CHR_ROM_END => {
-                self.ppudata.read(self.chr_rom[address as usize])
+                let chr_address = self.mapper.borrow().map_chr(address);
+                self.ppudata.read(self.chr_rom[chr_address])
+            }
+            PPU::VRAM_START..=PPU::VRAM_END | PPU::UNUSED_SPACE_START..=PPU::UNUSED_SPACE_END => {
+                self.ppudata
+                    .read(self.vram[self.mirror_vram_addr(address) as usize])
             }
-            PPU::VRAM_START..=PPU::VRAM_END => self
-                .ppudata
-                .read(self.vram[self.mirror_vram_addr(address) as usize]),
+            // Palette reads aren't buffered, the buffer is filled with the
+            // nametable byte "underneath" the palette instead.
             PPU::PALETTE_RAM_START..=PPU::PALETTE_RAM_END => {
-                self.palette_table[(address - PPU::PALETTE_RAM_START) as usize]
+                self.ppudata
+                    .read(self.vram[self.mirror_vram_addr(address) as usize]);
+                self.palette_table[PPU::mirror_palette_addr(address as usize)]
             }
             _ => panic!("Unexpected access to mirrored space {address:04x}"),
         }
     }
 
+    // Byte at v without the read buffer, the increment or A12 updates
+    pub fn peek_ppudata(&self) -> u8 {
+        let address = self.loopy.vram_address();
+        match address {
+            PPU::CHR_ROM_START..=PPU::CHR_ROM_END => {
+                self.chr_rom[self.mapper.borrow().map_chr(address)]
+            }
+            PPU::VRAM_START..=PPU::VRAM_END | PPU::UNUSED_SPACE_START..=PPU::UNUSED_SPACE_END => {
+                self.vram[self.mirror_vram_addr(address) as usize]
+            }
+            PPU::PALETTE_RAM_START..=PPU::PALETTE_RAM_END => {
+                self.palette_table[PPU::mirror_palette_addr(address as usize)]
+            }
+            _ => panic!("Unexpected access to mirrored space {address:04x}"),
+        }
+    }
+
+    // Stores a byte at v without the increment, CHR ROM included
+    pub fn poke_ppudata(&mut self, value: u8) {
+        let address = self.loopy.vram_address();
+        match address {
+            PPU::CHR_ROM_START..=PPU::CHR_ROM_END => {
+                let chr_address = self.mapper.borrow().map_chr(address);
+                self.chr_rom[chr_address] = value
+            }
+            PPU::VRAM_START..=PPU::VRAM_END | PPU::UNUSED_SPACE_START..=PPU::UNUSED_SPACE_END => {
+                self.vram[self.mirror_vram_addr(address) as usize] = value
+            }
+            PPU::PALETTE_RAM_START..=PPU::PALETTE_RAM_END => {
+                self.palette_table[PPU::mirror_palette_addr(address as usize)] = value
+            }
+            _ => panic!("Unexpected access to mirrored space {address:04x}"),
+        }
+    }
+
+    // Stores a byte at OAMADDR without the increment
+    pub fn poke_oamdata(&mut self, value: u8) {
+        let address = self.oamaddr.read();
+        self.store_oam_byte(value);
+        self.oamaddr.write(address);
+    }
+
+    pub fn mask_flags(&self) -> PPUMASK {
+        self.ppumask
+    }
+
+    // Unlike `read_ppustatus` doesn't clear vblank or the write toggle
+    pub fn status_flags(&self) -> PPUSTATUS {
+        self.ppustatus
+    }
+
+    pub fn in_vblank(&self) -> bool {
+        self.ppustatus.contains(PPUSTATUS::VBLANK_FLAG)
+    }
+
+    pub fn sprite_zero_hit(&self) -> bool {
+        self.ppustatus.contains(PPUSTATUS::SPRITE_ZERO_HIT_FLAG)
+    }
+
+    pub fn sprite_overflow(&self) -> bool {
+        self.ppustatus.contains(PPUSTATUS::SPRITE_OVERFLOW)
+    }
+
     pub fn get_x_scroll(&self) -> u8 {
-        self.ppuscroll.x_scroll()
+        self.loopy.scroll_x()
     }
 
     pub fn get_y_scroll(&self) -> u8 {
-        self.ppuscroll.y_scroll()
+        self.loopy.scroll_y()
+    }
+
+    // Scroll the visible scanline `y` of the last frame was rendered with
+    pub fn scroll_at_scanline(&self, y: usize) -> (u8, u8) {
+        self.scanline_scroll[y]
+    }
+
+    // Logical nametable (0-3) the visible scanline `y` of the last frame started in
+    pub fn nametable_at_scanline(&self, y: usize) -> usize {
+        ((self.scanline_nametable[y] - PPU::VRAM_START) / PPU::VRAM_NAMETABLE_SIZE) as usize % 4
+    }
+
+    // Mirroring the visible scanline `y` of the last frame was rendered with,
+    // mappers may switch it in the middle of a frame.
+    pub fn mirroring_at_scanline(&self, y: usize) -> Mirroring {
+        self.scanline_mirroring[y]
+    }
+
+    // VRAM range backing a logical nametable (0-3) after mirroring is applied
+    pub fn name_table_range(&self, nametable: usize) -> Range<usize> {
+        self.name_table_range_with(nametable, self.current_mirroring())
+    }
+
+    // Like `name_table_range`, for a mirroring other than the current one
+    pub fn name_table_range_with(&self, nametable: usize, mirroring: Mirroring) -> Range<usize> {
+        let address =
+            PPU::VRAM_START as usize + (nametable % 4) * PPU::VRAM_NAMETABLE_SIZE as usize;
+        let start = PPU::mirror_vram_addr_with(address as u16, mirroring) as usize;
+        start..start + PPU::VRAM_NAMETABLE_SIZE as usize
+    }
+
+    // Debug view of a logical nametable (0-3): the 32x30 tile indices followed
+    // by the 8x8 attribute table, all in hex.
+    pub fn dump_nametable(&self, nametable: usize) -> String {
+        let range = self.name_table_range(nametable);
+        let mut dump = String::new();
+        let mut write_rows = |start: usize, rows: usize, columns: usize| {
+            for row in 0..rows {
+                let line = (0..columns)
+                    .map(|column| self.read_vram(start + row * columns + column))
+                    .fold(String::new(), |mut line, tile| {
+                        if !line.is_empty() {
+                            line.push(' ');
+                        }
+                        let _ = write!(line, "{tile:02X}");
+                        line
+                    });
+                dump.push_str(&line);
+                dump.push('\n');
+            }
+        };
+
+        write_rows(range.start, 30, 32);
+        write_rows(range.start + PPU::ATTRIBUTE_TABLE_OFFSET, 8, 8);
+        dump
     }
 
     pub fn read_tile(&self, tile: usize, name_table_range: &Range<usize>) -> &[u8] {
         let bank_addr = self.ppuctrl.background_pattern_address() as usize;
-        let tile_index = self.vram[name_table_range.clone()][tile] as usize;
-        &self.chr_rom[(bank_addr + tile_index * 16)..=(bank_addr + tile_index * 16 + 15)]
+        match self.vram[name_table_range.clone()].get(tile) {
+            Some(&tile_index) => self.read_chr_tile(bank_addr + tile_index as usize * 16),
+            None => &PPU::EMPTY_TILE,
+        }
     }
 
-    pub fn get_name_table_ranges(&self) -> (Range<usize>, Range<usize>) {
-        match (&self.mirroring, self.ppuctrl.nametable_address()) {
-            (Mirroring::Vertical, 0x2000)
-            | (Mirroring::Vertical, 0x2800)
-            | (Mirroring::Horizontal, 0x2000)
-            | (Mirroring::Horizontal, 0x2400) => (0..0x400, 0x400..0x800),
-            (Mirroring::Vertical, 0x2400)
-            | (Mirroring::Vertical, 0x2C00)
-            | (Mirroring::Horizontal, 0x2800)
-            | (Mirroring::Horizontal, 0x2C00) => (0x400..0x800, 0..0x400),
-            (_, _) => {
-                panic!("Not supported mirroring type {:?}", self.mirroring);
-            }
+    // Tiles are 16 bytes and never cross a CHR bank boundary
+    fn read_chr_tile(&self, address: usize) -> &[u8] {
+        let chr_address = self.mapper.borrow().map_chr(address as u16);
+        self.chr_tile_at(chr_address)
+    }
+
+    // Offsets past the end of CHR mirror back into it like unconnected address
+    // lines do. A tile that still doesn't fit, or missing CHR, reads as empty.
+    fn chr_tile_at(&self, offset: usize) -> &[u8; 16] {
+        if self.chr_rom.is_empty() {
+            return &PPU::EMPTY_TILE;
         }
+        let start = offset % self.chr_rom.len();
+        self.chr_rom
+            .get(start..start + 16)
+            .and_then(|tile| tile.try_into().ok())
+            .unwrap_or(&PPU::EMPTY_TILE)
+    }
+
+    // Mirroring in effect right now. A mapper that controls mirroring (e.g.
+    // MMC3) wins, otherwise it's the one set with `set_mirroring`, which
+    // starts as the iNES header's.
+    pub fn current_mirroring(&self) -> Mirroring {
+        self.mapper.borrow().mirroring().unwrap_or(self.mirroring)
+    }
+
+    // Sets the cartridge mirroring. It only takes effect while the mapper
+    // reports `None` from `Mapper::mirroring`, mappers switching mirroring
+    // do it through their registers instead.
+    pub fn set_mirroring(&mut self, mirroring: Mirroring) {
+        self.mirroring = mirroring;
+    }
+
+    // VRAM of the nametable selected in PPUCTRL and of the one it scrolls into:
+    // the one below it for horizontal mirroring, the one to the right otherwise.
+    pub fn get_name_table_ranges(&self) -> (Range<usize>, Range<usize>) {
+        let nametable = ((self.loopy.nametable_address() - PPU::VRAM_START)
+            / PPU::VRAM_NAMETABLE_SIZE) as usize;
+        let neighbour = match self.current_mirroring() {
+            Mirroring::Horizontal => nametable ^ 2,
+            _ => nametable ^ 1,
+        };
+        (
+            self.name_table_range(nametable),
+            self.name_table_range(neighbour),
+        )
     }
 
     fn increment_ppuaddr(&mut self) {
-        self.ppuaddr.inc(self.ppuctrl.address_increment());
+        self.loopy.increment(self.ppuctrl.address_increment());
     }
 
     // https://www.nesdev.org/wiki/Mirroring#Nametable_Mirroring
@@ -254,17 +759,67 @@ impl PPU {
     // Vertical Mirroring:
     //   [ A ] [ B ]
     //   [ A ] [ B ]
+    //
+    // Single-screen mirroring shows A (or B) in all four, four-screen
+    // cartridges back each nametable with its own memory.
     fn mirror_vram_addr(&self, address: u16) -> u16 {
+        PPU::mirror_vram_addr_with(address, self.current_mirroring())
+    }
+
+    fn mirror_vram_addr_with(address: u16, mirroring: Mirroring) -> u16 {
         let vram_index = (address & PPU::VRAM_END) - PPU::VRAM_START;
-        match (&self.mirroring, vram_index / PPU::VRAM_NAMETABLE_SIZE) {
-            (Mirroring::Vertical, 2 | 3) | (Mirroring::Horizontal, 3) => {
-                vram_index - 2 * PPU::VRAM_NAMETABLE_SIZE
-            }
-            (Mirroring::Horizontal, 1 | 2) => vram_index - PPU::VRAM_NAMETABLE_SIZE,
-            _ => vram_index,
+        let offset = vram_index % PPU::VRAM_NAMETABLE_SIZE;
+        let nametable = vram_index / PPU::VRAM_NAMETABLE_SIZE;
+        let bank = match mirroring {
+            Mirroring::FourScreen => nametable,
+            Mirroring::SingleScreenA => 0,
+            Mirroring::SingleScreenB => 1,
+            Mirroring::Vertical => nametable % 2,
+            Mirroring::Horizontal => nametable / 2,
+        };
+        bank * PPU::VRAM_NAMETABLE_SIZE + offset
+    }
+
+    // https://www.nesdev.org/wiki/PPU_palettes#Memory_Map
+    //
+    // 32 bytes of palette RAM are mirrored through $3F00-$3FFF, and entries
+    // $3F10/$3F14/$3F18/$3F1C mirror $3F00/$3F04/$3F08/$3F0C.
+    fn mirror_palette_addr(address: usize) -> usize {
+        let index = address & 0x1F;
+        if index & 0x13 == 0x10 {
+            index & 0x0F
+        } else {
+            index
         }
     }
 
+    fn is_rendering_enabled(&self) -> bool {
+        self.ppumask.contains(PPUMASK::ENABLE_BG_RENDERING)
+            || self.ppumask.contains(PPUMASK::ENABLE_SPRITE_RENDERING)
+    }
+
+    fn is_rendering_scanline(&self) -> bool {
+        let pre_render_scanline = self.region.scanlines_per_frame() - 1;
+        self.is_rendering_enabled() && (self.scanline < 240 || self.scanline == pre_render_scanline)
+    }
+
+    // At the end of a scanline the PPU fetches sprite patterns for the next
+    // line (dots 257-320) followed by the first two background tiles
+    // (dots 321-336). The mappers watching A12 (e.g. MMC3) see these fetches.
+    // https://www.nesdev.org/wiki/PPU_rendering#Cycles_257-320
+    fn fetch_scanline_patterns(&mut self) {
+        self.update_a12(self.ppuctrl.sprite_pattern_address());
+        self.update_a12(self.ppuctrl.background_pattern_address());
+    }
+
+    fn update_a12(&mut self, address: u16) {
+        let a12 = address & 0x1000 != 0;
+        if a12 && !self.a12 {
+            self.mapper.borrow_mut().on_a12_rise();
+        }
+        self.a12 = a12;
+    }
+
     fn is_sprite_0_hit(&self, cycle: usize) -> bool {
         let y = self.oam_data[0] as usize;
         let x = self.oam_data[3] as usize;
@@ -273,3 +828,601 @@ impl PPU {
             && self.ppumask.contains(PPUMASK::ENABLE_SPRITE_RENDERING)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapper::mapper::create_mapper;
+    use alloc::vec;
+
+    #[test]
+    fn test_dots_per_frame_ntsc() {
+        assert_eq!(dots_per_frame(Region::Ntsc), 341 * 262);
+    }
+
+    #[test]
+    fn test_dots_per_frame_pal() {
+        assert_eq!(dots_per_frame(Region::Pal), 341 * 312);
+    }
+
+    #[test]
+    fn test_dots_per_frame_dendy() {
+        assert_eq!(dots_per_frame(Region::Dendy), 341 * 312);
+    }
+
+    #[test]
+    fn test_scroll_and_address_share_write_toggle() {
+        let mut ppu = setup_ppu(Region::Ntsc);
+
+        ppu.write_ppuscroll(0x12);
+        ppu.write_ppuaddr(0x34);
+        assert_eq!(ppu.loopy.vram_address(), 0x0034);
+
+        ppu.write_ppuscroll(0x56);
+        ppu.read_ppustatus();
+        ppu.write_ppuaddr(0x21);
+        ppu.write_ppuaddr(0x08);
+        assert_eq!(ppu.loopy.vram_address(), 0x2108);
+    }
+
+    #[test]
+    fn test_scroll_is_reloaded_from_t_on_pre_render_scanline() {
+        let mut ppu = setup_ppu(Region::Ntsc);
+        ppu.write_ppumask(PPUMASK::ENABLE_BG_RENDERING.bits());
+
+        ppu.write_ppuctrl(0b01);
+        ppu.write_ppuscroll(0x7D);
+        ppu.write_ppuscroll(0x5E);
+        // Scroll writes only update t until the PPU copies it into v,
+        // fine X is the only part that takes effect right away
+        assert_eq!(ppu.get_x_scroll(), 0x7D & 0b111);
+        assert_eq!(ppu.get_y_scroll(), 0);
+
+        while !ppu.tick(1) {}
+        assert_eq!(ppu.get_x_scroll(), 0x7D);
+        assert_eq!(ppu.get_y_scroll(), 0x5E);
+        assert_eq!(ppu.get_name_table_ranges(), (0..0x400, 0x400..0x800));
+    }
+
+    #[test]
+    fn test_scroll_change_mid_frame_is_captured_per_scanline() {
+        let mut ppu = setup_ppu(Region::Ntsc);
+        ppu.write_ppumask(PPUMASK::ENABLE_BG_RENDERING.bits());
+        ppu.write_ppuscroll(0);
+        ppu.write_ppuscroll(0);
+        while !ppu.tick(1) {}
+
+        // Status bar split: scroll changes while scanline 31 is drawn
+        while ppu.scanline != 31 {
+            ppu.tick(1);
+        }
+        ppu.write_ppuctrl(0b01);
+        ppu.write_ppuscroll(0x48);
+        ppu.write_ppuscroll(0);
+        while !ppu.tick(1) {}
+
+        assert_eq!(ppu.scroll_at_scanline(0), (0, 0));
+        assert_eq!(ppu.scroll_at_scanline(31), (0, 0));
+        assert_eq!(ppu.scroll_at_scanline(32), (0x48, 0));
+        assert_eq!(ppu.scroll_at_scanline(239), (0x48, 0));
+        assert_eq!(ppu.nametable_at_scanline(0), 0);
+        assert_eq!(ppu.nametable_at_scanline(32), 1);
+        // Horizontal mirroring: $2400 is the same table as $2000
+        assert_eq!(ppu.name_table_range(1), 0..0x400);
+        assert_eq!(ppu.name_table_range(2), 0x400..0x800);
+    }
+
+    #[test]
+    fn test_mirroring_change_mid_frame_is_captured_per_scanline() {
+        let mut ppu = setup_ppu(Region::Ntsc);
+        ppu.write_ppumask(PPUMASK::ENABLE_BG_RENDERING.bits());
+        while !ppu.tick(1) {}
+
+        while ppu.scanline != 100 {
+            ppu.tick(1);
+        }
+        ppu.set_mirroring(Mirroring::SingleScreenB);
+        while !ppu.tick(1) {}
+
+        assert_eq!(ppu.mirroring_at_scanline(99), Mirroring::Horizontal);
+        assert_eq!(ppu.mirroring_at_scanline(101), Mirroring::SingleScreenB);
+        assert_eq!(
+            ppu.name_table_range_with(0, Mirroring::Horizontal),
+            0..0x400
+        );
+        assert_eq!(ppu.name_table_range(0), 0x400..0x800);
+    }
+
+    #[test]
+    fn test_ppudata_access_increments_v() {
+        let mut ppu = setup_ppu(Region::Ntsc);
+
+        ppu.write_ppuaddr(0x23);
+        ppu.write_ppuaddr(0xFF);
+        ppu.write_ppudata(0x01);
+        assert_eq!(ppu.loopy.vram_address(), 0x2400);
+
+        ppu.write_ppuctrl(PPUCTRL::ADDR_INCREMENT.bits());
+        ppu.write_ppudata(0x02);
+        assert_eq!(ppu.loopy.vram_address(), 0x2420);
+    }
+
+    #[test]
+    fn test_status_flags_peek_does_not_clear_vblank() {
+        let mut ppu = setup_ppu(Region::Ntsc);
+        // Vblank flag is set on dot 1
+        while ppu.scanline != Region::Ntsc.vblank_scanline() || ppu.cycles < 1 {
+            ppu.tick(1);
+        }
+
+        assert!(ppu.in_vblank());
+        assert!(ppu.status_flags().contains(PPUSTATUS::VBLANK_FLAG));
+        assert!(ppu.in_vblank());
+        assert!(!ppu.sprite_zero_hit());
+        assert!(!ppu.sprite_overflow());
+
+        assert_eq!(ppu.read_ppustatus() & 0x80, 0x80);
+        assert!(!ppu.in_vblank());
+    }
+
+    #[test]
+    fn test_sprite_zero_over_opaque_background_sets_hit() {
+        let mut ppu = setup_ppu(Region::Ntsc);
+        // Tile 1 is solid color 3
+        ppu.write_ppuaddr(0x00);
+        ppu.write_ppuaddr(0x10);
+        for _ in 0..16 {
+            ppu.write_ppudata(0xFF);
+        }
+        ppu.write_ppuaddr(0x20);
+        ppu.write_ppuaddr(0x00);
+        for _ in 0..960 {
+            ppu.write_ppudata(0x01);
+        }
+        // Sprite 0 at (40, 30) drawing tile 1
+        ppu.write_oamaddr(0);
+        for value in [30, 0x01, 0x00, 40] {
+            ppu.write_oamdata(value);
+        }
+        ppu.write_ppumask(0x1E);
+
+        while ppu.scanline < 30 {
+            ppu.tick(1);
+        }
+        assert!(!ppu.sprite_zero_hit());
+        while ppu.scanline < 32 {
+            ppu.tick(1);
+        }
+        assert!(ppu.sprite_zero_hit());
+        assert!(ppu.status_flags().contains(PPUSTATUS::SPRITE_ZERO_HIT_FLAG));
+    }
+
+    #[test]
+    fn test_oam_entry_reads_sprite_attributes() {
+        let mut ppu = setup_ppu(Region::Ntsc);
+        ppu.write_oamaddr(4);
+        for value in [0x10, 0x21, 0b1110_0010, 0x30] {
+            ppu.write_oamdata(value);
+        }
+
+        let sprite = ppu.oam_entry(1);
+        assert_eq!(
+            sprite,
+            SpriteEntry {
+                y: 0x10,
+                tile: 0x21,
+                attributes: 0b1110_0010,
+                x: 0x30
+            }
+        );
+        assert_eq!(sprite.palette(), 2);
+        assert!(sprite.is_behind_background());
+        assert!(sprite.flip_horizontal());
+        assert!(sprite.flip_vertical());
+        assert_eq!(ppu.read_oamaddr(), 8);
+    }
+
+    #[test]
+    fn test_ppustatus_read_before_vblank_suppresses_flag_and_nmi() {
+        let mut ppu = ppu_at_vblank_dot(0);
+
+        assert_eq!(ppu.read_ppustatus() & 0x80, 0);
+        ppu.tick(3);
+        assert!(!ppu.in_vblank());
+        assert!(!ppu.poll_nmi_interrupt());
+    }
+
+    #[test]
+    fn test_ppustatus_read_on_vblank_dot_suppresses_nmi() {
+        for dot in [1, 2] {
+            let mut ppu = ppu_at_vblank_dot(dot);
+
+            assert_eq!(ppu.read_ppustatus() & 0x80, 0x80);
+            assert!(!ppu.poll_nmi_interrupt());
+        }
+    }
+
+    #[test]
+    fn test_ppustatus_read_after_vblank_keeps_nmi() {
+        let mut ppu = ppu_at_vblank_dot(3);
+
+        assert_eq!(ppu.read_ppustatus() & 0x80, 0x80);
+        assert!(ppu.poll_nmi_interrupt());
+    }
+
+    #[test]
+    fn test_reset_clears_registers_and_keeps_memory() {
+        let mut ppu = setup_ppu(Region::Ntsc);
+        ppu.write_ppuaddr(0x20);
+        ppu.write_ppuaddr(0x05);
+        ppu.write_ppudata(0x42);
+        ppu.write_oamdata(0x17);
+        ppu.write_ppuctrl(0x83);
+        ppu.write_ppumask(0x1E);
+        ppu.write_ppuscroll(0x7D);
+
+        ppu.reset();
+
+        assert!(ppu.ppuctrl.is_empty());
+        assert!(ppu.mask_flags().is_empty());
+        assert_eq!(ppu.nametable_byte(0, 5), 0x42);
+        assert_eq!(ppu.read_oamdata(0), 0x17);
+        // PPUSCROLL is cleared, the next frame starts from the top left
+        ppu.loopy.copy_t_to_v();
+        assert_eq!((ppu.loopy.scroll_x(), ppu.loopy.scroll_y()), (0, 0));
+        // The write toggle is cleared, so this is the first PPUADDR write
+        ppu.write_ppuaddr(0x20);
+        ppu.write_ppuaddr(0x05);
+        ppu.read_ppudata();
+        assert_eq!(ppu.read_ppudata(), 0x42);
+    }
+
+    #[test]
+    fn test_oamdata_reads_do_not_advance_oamaddr() {
+        let mut ppu = setup_ppu(Region::Ntsc);
+        ppu.write_oamaddr(0x10);
+        ppu.write_oamdata(0x42);
+        ppu.write_oamaddr(0x10);
+
+        assert_eq!(ppu.read_oamdata(ppu.read_oamaddr() as usize), 0x42);
+        assert_eq!(ppu.read_oamdata(ppu.read_oamaddr() as usize), 0x42);
+        assert_eq!(ppu.read_oamaddr(), 0x10);
+    }
+
+    #[test]
+    fn test_oam_attribute_unused_bits_read_as_zero() {
+        let mut ppu = setup_ppu(Region::Ntsc);
+        ppu.write_oamaddr(2);
+        ppu.write_oamdata(0xFF);
+        ppu.write_oamdata(0xFF);
+
+        assert_eq!(ppu.read_oamdata(2), 0xE3);
+        assert_eq!(ppu.read_oamdata(3), 0xFF);
+    }
+
+    #[test]
+    fn test_oam_dma_starts_at_and_preserves_oamaddr() {
+        let mut ppu = setup_ppu(Region::Ntsc);
+        let mut data = [0; 256];
+        for (i, value) in data.iter_mut().enumerate() {
+            *value = i as u8;
+        }
+        ppu.write_oamaddr(4);
+
+        ppu.write_oamdma(&data);
+
+        assert_eq!(ppu.read_oamaddr(), 4);
+        assert_eq!(ppu.read_oamdata(4), 0x00);
+        assert_eq!(ppu.read_oamdata(0), 0xFC);
+        // Attribute byte of the second sprite came from data[2]
+        assert_eq!(ppu.read_oamdata(6), 0x02 & 0xE3);
+    }
+
+    #[test]
+    fn test_rendering_clears_oamaddr() {
+        let mut ppu = setup_ppu(Region::Ntsc);
+        ppu.write_ppumask(0b0001_0000);
+        ppu.write_oamaddr(0x20);
+
+        ppu.tick(255);
+        assert_eq!(ppu.read_oamaddr(), 0x20);
+        ppu.tick(86);
+        assert_eq!(ppu.read_oamaddr(), 0);
+    }
+
+    #[test]
+    fn test_visible_sprites_skip_hidden_entries() {
+        let mut ppu = setup_ppu(Region::Ntsc);
+        let mut oam = [0xFF; 256];
+        oam[8..12].copy_from_slice(&[0x10, 0x21, 0b0100_0001, 0x30]);
+        ppu.write_oamdma(&oam);
+
+        let sprites: Vec<Sprite> = ppu.visible_sprites().collect();
+
+        assert_eq!(
+            sprites,
+            vec![Sprite {
+                index: 2,
+                x: 0x30,
+                y: 0x10,
+                tile: 0x21,
+                palette: 1,
+                flip_horizontal: true,
+                flip_vertical: false,
+                behind_background: false,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_more_than_8_sprites_set_overflow() {
+        let mut ppu = setup_ppu(Region::Ntsc);
+        ppu.write_oamaddr(0);
+        for i in 0..64 {
+            // Nine sprites on scanlines 20-27, the rest below the screen
+            let y = if i < 9 { 20 } else { 0xF0 };
+            for value in [y, 0, 0, 0] {
+                ppu.write_oamdata(value);
+            }
+        }
+        ppu.write_ppumask(0b0001_0000);
+
+        assert_eq!(ppu.sprites_on_scanline(20).count(), 9);
+        assert_eq!(ppu.sprites_on_scanline(28).count(), 0);
+        for _ in 0..20 {
+            ppu.tick(255);
+            ppu.tick(86);
+        }
+        assert!(!ppu.sprite_overflow());
+        ppu.tick(255);
+        ppu.tick(86);
+        assert!(ppu.sprite_overflow());
+    }
+
+    #[test]
+    fn test_nametable_byte_has_no_side_effects() {
+        let mut ppu = setup_ppu(Region::Ntsc);
+        ppu.write_ppuaddr(0x24);
+        ppu.write_ppuaddr(0x05);
+        ppu.write_ppudata(0x42);
+        ppu.write_ppuaddr(0x20);
+        ppu.write_ppuaddr(0x00);
+
+        // Horizontal mirroring: $2400 mirrors $2000
+        assert_eq!(ppu.nametable_byte(1, 5), 0x42);
+        assert_eq!(ppu.nametable_byte(0, 5), 0x42);
+        assert_eq!(ppu.nametable_byte(2, 5), 0x00);
+        assert_eq!(ppu.loopy.vram_address(), 0x2000);
+    }
+
+    #[test]
+    fn test_power_up_memory() {
+        let mut ppu = setup_ppu(Region::Ntsc);
+        assert_eq!(ppu.read_palette_table(0x01), 0x00);
+
+        ppu.load_power_up_memory();
+
+        let palette: Vec<u8> = (0x00..0x10).map(|i| ppu.read_palette_table(i)).collect();
+        assert_eq!(
+            palette,
+            [
+                0x09, 0x01, 0x00, 0x01, 0x00, 0x02, 0x02, 0x0D, 0x08, 0x10, 0x08, 0x24, 0x00, 0x00,
+                0x04, 0x2C
+            ]
+        );
+        assert_eq!(ppu.read_palette_table(0x12), 0x34);
+        assert_eq!(ppu.read_palette_table(0x1F), 0x08);
+        assert!((0..256).all(|i| ppu.read_oamdata(i) == 0xFF));
+        assert_eq!(ppu.visible_sprites().count(), 0);
+    }
+
+    #[test]
+    fn test_tiles_past_chr_end_do_not_panic() {
+        let small_ppu = |size: usize| {
+            let mut chr = vec![0xBB; size];
+            chr[..16].fill(0xAA);
+            let mapper = create_mapper(0, 0x4000, size, Mirroring::Horizontal).unwrap();
+            PPU::new(chr, Mirroring::Horizontal, false, Region::Ntsc, mapper)
+        };
+
+        // The second pattern table mirrors the first one
+        let ppu = small_ppu(0x1000);
+        assert_eq!(ppu.pattern_tile(1, 0), &[0xAA; 16]);
+        assert_eq!(ppu.pattern_tile(1, 1), &[0xBB; 16]);
+
+        // Only 8 bytes are left for tile $100
+        let ppu = small_ppu(0x1008);
+        assert_eq!(ppu.pattern_tile(0, 0x100), &[0; 16]);
+        assert_eq!(ppu.read_tile(0x400, &ppu.name_table_range(0)), &[0; 16]);
+    }
+
+    #[test]
+    fn test_unused_space_mirrors_nametables() {
+        let mut ppu = setup_ppu(Region::Ntsc);
+        ppu.write_ppuaddr(0x30);
+        ppu.write_ppuaddr(0x00);
+        ppu.write_ppudata(0x42);
+        ppu.write_ppuaddr(0x2E);
+        ppu.write_ppuaddr(0xFE);
+        ppu.write_ppudata(0x24);
+
+        ppu.write_ppuaddr(0x20);
+        ppu.write_ppuaddr(0x00);
+        // Nametable reads are delayed by the read buffer
+        ppu.read_ppudata();
+        assert_eq!(ppu.read_ppudata(), 0x42);
+        ppu.write_ppuaddr(0x3E);
+        ppu.write_ppuaddr(0xFE);
+        ppu.read_ppudata();
+        assert_eq!(ppu.read_ppudata(), 0x24);
+    }
+
+    #[test]
+    fn test_sprite_backdrop_entries_mirror_background() {
+        let mut ppu = setup_ppu(Region::Ntsc);
+        ppu.write_ppuaddr(0x3F);
+        ppu.write_ppuaddr(0x10);
+        ppu.write_ppudata(0x2A);
+
+        ppu.write_ppuaddr(0x3F);
+        ppu.write_ppuaddr(0x00);
+        assert_eq!(ppu.read_ppudata(), 0x2A);
+        assert_eq!(ppu.read_palette_table(0x10), 0x2A);
+
+        // Mirrors of the palette RAM across $3F20-$3FFF
+        ppu.write_ppuaddr(0x3F);
+        ppu.write_ppuaddr(0xE0);
+        assert_eq!(ppu.read_ppudata(), 0x2A);
+    }
+
+    #[test]
+    fn test_palette_reads_are_not_buffered() {
+        let mut ppu = setup_ppu(Region::Ntsc);
+        ppu.write_ppuaddr(0x2F);
+        ppu.write_ppuaddr(0x01);
+        ppu.write_ppudata(0x11);
+        ppu.write_ppuaddr(0x3F);
+        ppu.write_ppuaddr(0x01);
+        ppu.write_ppudata(0x22);
+
+        ppu.write_ppuaddr(0x3F);
+        ppu.write_ppuaddr(0x01);
+        assert_eq!(ppu.read_ppudata(), 0x22);
+        // The buffer now holds the nametable byte at $2F01
+        ppu.write_ppuaddr(0x20);
+        ppu.write_ppuaddr(0x00);
+        assert_eq!(ppu.read_ppudata(), 0x11);
+    }
+
+    fn nametable_banks(mirroring: Mirroring) -> [usize; 4] {
+        let mut ppu = setup_ppu(Region::Ntsc);
+        ppu.set_mirroring(mirroring);
+        core::array::from_fn(|nametable| {
+            ppu.name_table_range(nametable).start / PPU::VRAM_NAMETABLE_SIZE as usize
+        })
+    }
+
+    #[test]
+    fn test_nametable_mapping_for_each_mirroring() {
+        assert_eq!(nametable_banks(Mirroring::Horizontal), [0, 0, 1, 1]);
+        assert_eq!(nametable_banks(Mirroring::Vertical), [0, 1, 0, 1]);
+        assert_eq!(nametable_banks(Mirroring::SingleScreenA), [0, 0, 0, 0]);
+        assert_eq!(nametable_banks(Mirroring::SingleScreenB), [1, 1, 1, 1]);
+        assert_eq!(nametable_banks(Mirroring::FourScreen), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_name_table_ranges_follow_runtime_mirroring() {
+        let mut ppu = setup_ppu(Region::Ntsc);
+        ppu.write_ppuaddr(0x28);
+        ppu.write_ppuaddr(0x00);
+        assert_eq!(ppu.get_name_table_ranges(), (0x400..0x800, 0..0x400));
+
+        ppu.set_mirroring(Mirroring::Vertical);
+        assert_eq!(ppu.get_name_table_ranges(), (0..0x400, 0x400..0x800));
+
+        ppu.set_mirroring(Mirroring::SingleScreenB);
+        assert_eq!(ppu.get_name_table_ranges(), (0x400..0x800, 0x400..0x800));
+
+        ppu.set_mirroring(Mirroring::FourScreen);
+        assert_eq!(ppu.get_name_table_ranges(), (0x800..0xC00, 0xC00..0x1000));
+    }
+
+    #[test]
+    fn test_dump_nametable_lists_tiles_and_attributes() {
+        let mut ppu = setup_ppu(Region::Ntsc);
+        ppu.write_ppuaddr(0x24);
+        ppu.write_ppuaddr(0x01);
+        ppu.write_ppudata(0xAB);
+        ppu.write_ppuaddr(0x27);
+        ppu.write_ppuaddr(0xFF);
+        ppu.write_ppudata(0x1E);
+
+        let dump = ppu.dump_nametable(1);
+        let lines: Vec<&str> = dump.lines().collect();
+
+        assert_eq!(lines.len(), 38);
+        assert!(lines[0].starts_with("00 AB 00"));
+        assert_eq!(lines[0].split(' ').count(), 32);
+        assert_eq!(lines[37], "00 00 00 00 00 00 00 1E");
+        // Horizontal mirroring: nametable 1 is the same memory as nametable 0
+        assert_eq!(ppu.dump_nametable(0), dump);
+    }
+
+    #[test]
+    fn test_pattern_fetches_follow_mapper_chr_banks() {
+        let chr = (0..16u8).flat_map(|bank| [bank; 0x0400]).collect();
+        let mapper = create_mapper(4, 0x8000, 0x4000, Mirroring::Vertical).unwrap();
+        let mut ppu = PPU::new(
+            chr,
+            Mirroring::Vertical,
+            false,
+            Region::Ntsc,
+            mapper.clone(),
+        );
+        ppu.write_ppuctrl(PPUCTRL::SPRITE_ADDR.bits());
+        let name_table = ppu.name_table_range(0);
+
+        mapper.borrow_mut().write(0x8000, 0);
+        mapper.borrow_mut().write(0x8001, 6);
+        mapper.borrow_mut().write(0x8000, 2);
+        mapper.borrow_mut().write(0x8001, 5);
+        assert_eq!(ppu.read_tile(0, &name_table)[0], 6);
+        assert_eq!(ppu.read_sprite_tile(0)[0], 5);
+
+        // Switching banks mid-frame changes the very next fetch
+        mapper.borrow_mut().write(0x8001, 9);
+        assert_eq!(ppu.read_sprite_tile(0)[15], 9);
+        ppu.write_ppuaddr(0x10);
+        ppu.write_ppuaddr(0x00);
+        ppu.read_ppudata();
+        assert_eq!(ppu.read_ppudata(), 9);
+    }
+
+    #[test]
+    fn test_mapper_mirroring_wins_over_set_mirroring() {
+        let mapper = create_mapper(4, 0x8000, 0x4000, Mirroring::Vertical).unwrap();
+        let mut ppu = PPU::new(
+            vec![0; 0x4000],
+            Mirroring::Vertical,
+            false,
+            Region::Ntsc,
+            mapper.clone(),
+        );
+
+        ppu.set_mirroring(Mirroring::SingleScreenA);
+        assert_eq!(ppu.current_mirroring(), Mirroring::Vertical);
+
+        mapper.borrow_mut().write(0xA000, 1);
+        assert_eq!(ppu.current_mirroring(), Mirroring::Horizontal);
+        assert_eq!(ppu.name_table_range(1), 0..0x400);
+
+        // NROM leaves mirroring to the PPU
+        let mut ppu = setup_ppu(Region::Ntsc);
+        ppu.set_mirroring(Mirroring::SingleScreenB);
+        assert_eq!(ppu.current_mirroring(), Mirroring::SingleScreenB);
+    }
+
+    fn setup_ppu(region: Region) -> PPU {
+        let mapper = create_mapper(0, 0x4000, 0x2000, Mirroring::Horizontal).unwrap();
+        PPU::new(vec![0; 0x2000], Mirroring::Horizontal, true, region, mapper)
+    }
+
+    fn ppu_at_vblank_dot(dot: usize) -> PPU {
+        let mut ppu = setup_ppu(Region::Ntsc);
+        ppu.write_ppuctrl(PPUCTRL::NMI_ENABLE.bits());
+        while ppu.scanline != 241 || ppu.cycles != dot {
+            ppu.tick(1);
+        }
+        ppu
+    }
+
+    fn dots_per_frame(region: Region) -> usize {
+        let mut ppu = setup_ppu(region);
+        let mut dots = 1;
+        while !ppu.tick(1) {
+            dots += 1;
+        }
+        dots
+    }
+}
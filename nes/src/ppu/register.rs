@@ -1,9 +1,8 @@
+pub mod loopy;
 pub mod oamaddr;
 pub mod oamdata;
 pub mod oamdma;
-pub mod ppuaddr;
 pub mod ppuctrl;
 pub mod ppudata;
 pub mod ppumask;
-pub mod ppuscroll;
 pub mod ppustatus;
@@ -0,0 +1,115 @@
+// PPU internal registers
+// https://www.nesdev.org/wiki/PPU_scrolling#PPU_internal_registers
+//
+// v - Current VRAM address (15 bits)
+// t - Temporary VRAM address (15 bits), the address of the top left onscreen tile
+// x - Fine X scroll (3 bits)
+// w - First or second write toggle (1 bit), shared by PPUSCROLL and PPUADDR
+//
+// v and t layout:
+// yyy NN YYYYY XXXXX
+// ||| || ||||| +++++-- coarse X scroll
+// ||| || +++++-------- coarse Y scroll
+// ||| ++-------------- nametable select
+// +++----------------- fine Y scroll
+#[derive(Clone)]
+pub struct LoopyRegisters {
+    v: u16,
+    t: u16,
+    x: u8,
+    w: bool,
+}
+
+impl LoopyRegisters {
+    const COARSE_X: u16 = 0x001F;
+    const COARSE_Y: u16 = 0x03E0;
+    const NAMETABLE: u16 = 0x0C00;
+    const NAMETABLE_X: u16 = 0x0400;
+    const FINE_Y: u16 = 0x7000;
+    const HORIZONTAL_BITS: u16 = LoopyRegisters::COARSE_X | LoopyRegisters::NAMETABLE_X;
+    const VRAM_ADDRESS_MIRRORING: u16 = 0b0011_1111_1111_1111;
+
+    pub fn new() -> Self {
+        LoopyRegisters {
+            v: 0,
+            t: 0,
+            x: 0,
+            w: false,
+        }
+    }
+
+    // $2000 write: t: ...GH.. ........ <- d: ......GH
+    pub fn write_ppuctrl(&mut self, value: u8) {
+        self.t = (self.t & !LoopyRegisters::NAMETABLE) | ((value as u16 & 0b11) << 10);
+    }
+
+    // $2005 first write:  t: ....... ...ABCDE <- d: ABCDE...
+    //                     x:              FGH <- d: .....FGH
+    // $2005 second write: t: FGH..AB CDE..... <- d: ABCDEFGH
+    pub fn write_ppuscroll(&mut self, value: u8) {
+        if !self.w {
+            self.t = (self.t & !LoopyRegisters::COARSE_X) | (value as u16 >> 3);
+            self.x = value & 0b111;
+        } else {
+            self.t = (self.t & !(LoopyRegisters::COARSE_Y | LoopyRegisters::FINE_Y))
+                | ((value as u16 & 0b1111_1000) << 2)
+                | ((value as u16 & 0b111) << 12);
+        }
+        self.w = !self.w;
+    }
+
+    // $2006 first write:  t: .CDEFGH ........ <- d: ..CDEFGH
+    //                     t: Z...... ........ <- 0
+    // $2006 second write: t: ....... ABCDEFGH <- d: ABCDEFGH
+    //                     v: <...all bits...> <- t: <...all bits...>
+    pub fn write_ppuaddr(&mut self, value: u8) {
+        if !self.w {
+            self.t = (self.t & 0x00FF) | ((value as u16 & 0b0011_1111) << 8);
+        } else {
+            self.t = (self.t & 0xFF00) | value as u16;
+            self.v = self.t;
+        }
+        self.w = !self.w;
+    }
+
+    // $2002 read: w <- 0
+    pub fn reset_w(&mut self) {
+        self.w = false;
+    }
+
+    // Address used by $2007 accesses
+    pub fn vram_address(&self) -> u16 {
+        self.v & LoopyRegisters::VRAM_ADDRESS_MIRRORING
+    }
+
+    // $2007 access increments v by 1 or 32
+    pub fn increment(&mut self, value: u8) {
+        self.v = self.v.wrapping_add(value as u16) & 0x7FFF;
+    }
+
+    // Pre-render scanline dots 280-304 (vertical bits) and dot 257 (horizontal bits)
+    // copy t into v when rendering is enabled.
+    pub fn copy_t_to_v(&mut self) {
+        self.v = self.t;
+    }
+
+    pub fn copy_horizontal_t_to_v(&mut self) {
+        self.v = (self.v & !LoopyRegisters::HORIZONTAL_BITS)
+            | (self.t & LoopyRegisters::HORIZONTAL_BITS);
+    }
+
+    // Pixel offset of v within its nametable
+    pub fn scroll_x(&self) -> u8 {
+        ((self.v & LoopyRegisters::COARSE_X) << 3) as u8 | self.x
+    }
+
+    pub fn scroll_y(&self) -> u8 {
+        (((self.v & LoopyRegisters::COARSE_Y) >> 2) | ((self.v & LoopyRegisters::FINE_Y) >> 12))
+            as u8
+    }
+
+    // Base nametable address selected by v
+    pub fn nametable_address(&self) -> u16 {
+        0x2000 | (self.v & LoopyRegisters::NAMETABLE)
+    }
+}
@@ -6,6 +6,7 @@
 // AAAA AAAA
 // |||| ||||
 // ++++-++++- OAM address
+#[derive(Clone)]
 pub struct OAMADDR {
     data: u8,
 }
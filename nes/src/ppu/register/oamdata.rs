@@ -6,6 +6,7 @@
 // DDDD DDDD
 // |||| ||||
 // ++++-++++- OAM data
+#[derive(Clone)]
 pub struct OAMDATA {
     data: u8,
 }
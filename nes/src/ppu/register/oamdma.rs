@@ -6,6 +6,7 @@
 // AAAA AAAA
 // |||| ||||
 // ++++-++++- Source page (high byte of source address)
+#[derive(Clone)]
 pub struct OAMDMA {
     value: u8,
 }
@@ -19,6 +19,7 @@ use bitflags::bitflags;
 // |          (0: read backdrop from EXT pins; 1: output color on EXT pins)
 // +--------- Vblank NMI enable (0: off, 1: on)
 bitflags! {
+    #[derive(Clone, Copy)]
     pub struct PPUCTRL: u8 {
         const NAMETABLE_ADDR_1 = 0b0000_0001;
         const NAMETABLE_ADDR_2 = 0b0000_0010;
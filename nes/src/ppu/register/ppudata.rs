@@ -6,6 +6,7 @@
 // DDDD DDDD
 // |||| ||||
 // ++++-++++- VRAM data
+#[derive(Clone)]
 pub struct PPUDATA {
     read_buffer: u8,
 }
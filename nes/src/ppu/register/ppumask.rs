@@ -16,6 +16,7 @@ use bitflags::bitflags;
 // |+-------- Emphasize green (red on PAL/Dendy)
 // +--------- Emphasize blue
 bitflags! {
+    #[derive(Clone, Copy)]
     pub struct PPUMASK: u8 {
         const GREYSCALE = 0b00000001;
         const SHOW_BG_LEFT_8_PX = 0b00000010;
@@ -12,6 +12,7 @@ use bitflags::bitflags;
 // |+-------- Sprite 0 hit flag
 // +--------- Vblank flag, cleared on read. Unreliable;
 bitflags! {
+    #[derive(Clone, Copy)]
     pub struct PPUSTATUS: u8 {
         const SPRITE_OVERFLOW = 0b0010_0000;
         const SPRITE_ZERO_HIT_FLAG =  0b0100_0000;
@@ -0,0 +1,357 @@
+use crate::ppu::palette::{get_bg_palette, sprite_palette};
+use crate::ppu::ppu::PPU;
+use crate::ppu::register::ppumask::PPUMASK;
+use alloc::vec;
+use alloc::vec::Vec;
+
+// Layers drawn when PPUMASK enables them. Turning one off helps to tell
+// which layer a glitch comes from, dropping the sprite limit removes the
+// flicker games use to work around it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RenderLayers {
+    pub background: bool,
+    pub sprites: bool,
+    pub sprite_limit: bool,
+}
+
+impl Default for RenderLayers {
+    fn default() -> Self {
+        RenderLayers {
+            background: true,
+            sprites: true,
+            sprite_limit: true,
+        }
+    }
+}
+
+// The last rendered frame as NES palette indices (0-63), row by row, plus the
+// scratch buffers drawing it needs. They are allocated once and reused for
+// every frame.
+#[derive(Default)]
+pub(crate) struct FrameBuffer {
+    pub(crate) indices: Vec<u8>,
+    // Background pixels with a non-zero color, sprites behind the background
+    // only show through the other ones
+    background_opaque: Vec<bool>,
+    // Pixels an opaque sprite pixel was already drawn to
+    claimed: Vec<bool>,
+}
+
+impl FrameBuffer {
+    pub(crate) fn new() -> FrameBuffer {
+        let size = PPU::FRAME_WIDTH * PPU::FRAME_HEIGHT;
+        FrameBuffer {
+            indices: vec![0; size],
+            background_opaque: vec![false; size],
+            claimed: vec![false; size],
+        }
+    }
+}
+
+pub(crate) fn render_indices(ppu: &PPU, frame: &mut FrameBuffer) {
+    let mask = ppu.mask_flags();
+    let layers = ppu.render_layers();
+    frame.background_opaque.fill(false);
+    // Without the background layer the screen shows the backdrop color, so a
+    // frame with rendering disabled is blank rather than stale VRAM contents.
+    if mask.contains(PPUMASK::ENABLE_BG_RENDERING) && layers.background {
+        render_background(ppu, frame);
+    } else {
+        frame
+            .indices
+            .fill(greyscale(ppu.read_palette_table(0), mask));
+    }
+    if mask.contains(PPUMASK::ENABLE_SPRITE_RENDERING) && layers.sprites {
+        render_sprites(ppu, frame);
+    }
+}
+
+// Every scanline uses the scroll, nametable and mirroring it was drawn with,
+// so a scroll change in the middle of a frame (e.g. below a status bar) or a
+// mapper switching mirroring for a split is kept.
+fn render_background(ppu: &PPU, frame: &mut FrameBuffer) {
+    let mask = ppu.mask_flags();
+    for y in 0..PPU::FRAME_HEIGHT {
+        let (scroll_x, scroll_y) = ppu.scroll_at_scanline(y);
+        let nametable = ppu.nametable_at_scanline(y);
+        let mirroring = ppu.mirroring_at_scanline(y);
+        let world_y = y + scroll_y as usize;
+
+        let mut tile: &[u8] = &[0; 16];
+        let mut palette = [0; 4];
+        for x in 0..PPU::FRAME_WIDTH {
+            let world_x = x + scroll_x as usize;
+            // The screen shows a 256x240 window of the 512x480 area formed by
+            // the four logical nametables, past an edge it reads the neighbour.
+            if x == 0 || world_x.is_multiple_of(8) {
+                let neighbour = (world_x >= 256) as usize | ((world_y >= 240) as usize) << 1;
+                let range = ppu.name_table_range_with(nametable ^ neighbour, mirroring);
+                let (column, row) = (world_x % 256 / 8, world_y % 240 / 8);
+                tile = ppu.read_tile(row * 32 + column, &range);
+                palette = get_bg_palette(ppu, &range, column, row);
+            }
+
+            let value = tile_pixel(tile, world_x % 8, world_y % 8);
+            // Hidden left column shows the backdrop color instead of the tile
+            // https://www.nesdev.org/wiki/PPU_mask
+            let hidden = x < 8 && !mask.contains(PPUMASK::SHOW_BG_LEFT_8_PX);
+            let color_idx = if hidden {
+                palette[0]
+            } else {
+                palette[value as usize]
+            };
+            let pixel = y * PPU::FRAME_WIDTH + x;
+            frame.background_opaque[pixel] = !hidden && value != 0;
+            frame.indices[pixel] = greyscale(color_idx, mask);
+        }
+    }
+}
+
+fn render_sprites(ppu: &PPU, frame: &mut FrameBuffer) {
+    let mask = ppu.mask_flags();
+    let visible = visible_sprites_per_scanline(ppu);
+
+    // The first sprite with an opaque pixel owns it, even when it is behind an
+    // opaque background pixel and so hides sprites with a higher index there.
+    frame.claimed.fill(false);
+
+    for sprite in ppu.visible_sprites() {
+        let tile_x = sprite.x as usize;
+        let tile_y = sprite.y as usize;
+        let sprite_palette = sprite_palette(ppu, sprite.palette);
+        let sprite_tile = ppu.read_sprite_tile(sprite.tile as usize);
+
+        for y in 0..=7 {
+            for x in 0..=7 {
+                let value = tile_pixel(sprite_tile, x, y);
+                if value == 0 {
+                    continue;
+                }
+                let pixel_x = tile_x + if sprite.flip_horizontal { 7 - x } else { x };
+                let pixel_y = tile_y + if sprite.flip_vertical { 7 - y } else { y };
+                if pixel_x < 8 && !mask.contains(PPUMASK::SHOW_SPRITES_LEFT_8_PX) {
+                    continue;
+                }
+                if visible
+                    .get(pixel_y)
+                    .is_some_and(|line| line & (1 << sprite.index) == 0)
+                {
+                    continue;
+                }
+                if pixel_x >= PPU::FRAME_WIDTH || pixel_y >= PPU::FRAME_HEIGHT {
+                    continue;
+                }
+                let pixel = pixel_y * PPU::FRAME_WIDTH + pixel_x;
+                if frame.claimed[pixel] {
+                    continue;
+                }
+                frame.claimed[pixel] = true;
+                if sprite.behind_background && frame.background_opaque[pixel] {
+                    continue;
+                }
+                frame.indices[pixel] = greyscale(sprite_palette[value as usize], mask);
+            }
+        }
+    }
+}
+
+// Bit i of a scanline is set when sprite i is drawn on it. Sprites past the
+// 8th are dropped like the hardware does unless the layers lift the limit.
+fn visible_sprites_per_scanline(ppu: &PPU) -> [u64; PPU::FRAME_HEIGHT] {
+    let mut visible = [u64::MAX; PPU::FRAME_HEIGHT];
+    if ppu.render_layers().sprite_limit {
+        for (scanline, line) in visible.iter_mut().enumerate() {
+            *line = ppu
+                .sprites_on_scanline(scanline)
+                .take(PPU::SPRITES_PER_SCANLINE)
+                .fold(0, |line, i| line | (1 << i));
+        }
+    }
+    visible
+}
+
+// 2-bit value of a tile pixel, the low bit comes from the first bit plane and
+// the high bit from the second one, 8 bytes later. x = 0 is the leftmost pixel.
+// https://www.nesdev.org/wiki/PPU_pattern_tables
+pub fn tile_pixel(tile: &[u8], x: usize, y: usize) -> u8 {
+    let bit = 7 - x;
+    (tile[y] >> bit) & 1 | ((tile[y + 8] >> bit) & 1) << 1
+}
+
+// Greyscale keeps only the brightness column of the palette
+pub fn greyscale(color_idx: u8, mask: PPUMASK) -> u8 {
+    if mask.contains(PPUMASK::GREYSCALE) {
+        color_idx & 0x30
+    } else {
+        color_idx
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapper::mapper::create_mapper;
+    use crate::ppu::mirroring::Mirroring;
+    use crate::region::Region;
+
+    fn setup_ppu(chr: Vec<u8>) -> PPU {
+        let mapper = create_mapper(0, 0x4000, 0x2000, Mirroring::Horizontal).unwrap();
+        PPU::new(chr, Mirroring::Horizontal, false, Region::Ntsc, mapper)
+    }
+
+    fn write_palette(ppu: &mut PPU, address: u8, colors: &[u8]) {
+        ppu.write_ppuaddr(0x3F);
+        ppu.write_ppuaddr(address);
+        for &color in colors {
+            ppu.write_ppudata(color);
+        }
+    }
+
+    #[test]
+    fn test_disabled_rendering_shows_backdrop() {
+        let mut ppu = setup_ppu(vec![0xFF; 0x2000]);
+        write_palette(&mut ppu, 0x00, &[0x21]);
+        ppu.write_ppumask(0);
+        while !ppu.tick(1) {}
+
+        assert_eq!(
+            ppu.index_buffer().len(),
+            PPU::FRAME_WIDTH * PPU::FRAME_HEIGHT
+        );
+        assert!(ppu.index_buffer().iter().all(|&index| index == 0x21));
+    }
+
+    #[test]
+    fn test_sprite_limit_and_layers_follow_render_layers() {
+        let mut ppu = setup_ppu(vec![0xFF; 0x2000]);
+        ppu.write_oamaddr(0);
+        for i in 0..64u8 {
+            // Nine solid sprites side by side on the same row
+            let (y, x) = if i < 9 { (50, 16 + i * 8) } else { (0xF0, 0) };
+            for value in [y, 0, 0, x] {
+                ppu.write_oamdata(value);
+            }
+        }
+        write_palette(&mut ppu, 0x00, &[0x0F, 0x00, 0x00, 0x16]);
+        write_palette(&mut ppu, 0x13, &[0x21]);
+        ppu.write_ppuctrl(0);
+        ppu.write_ppuscroll(0);
+        ppu.write_ppuscroll(0);
+        ppu.write_ppumask(0b0001_1110);
+        let pixel = |ppu: &PPU, x: usize, y: usize| ppu.index_buffer()[y * PPU::FRAME_WIDTH + x];
+        let ninth_sprite_x = 16 + 8 * 8;
+
+        while !ppu.tick(1) {}
+        assert_eq!(pixel(&ppu, 16, 50), 0x21);
+        assert_eq!(pixel(&ppu, ninth_sprite_x, 50), 0x16);
+
+        ppu.set_render_layers(RenderLayers {
+            sprite_limit: false,
+            ..RenderLayers::default()
+        });
+        while !ppu.tick(1) {}
+        assert_eq!(pixel(&ppu, ninth_sprite_x, 50), 0x21);
+
+        ppu.set_render_layers(RenderLayers {
+            background: false,
+            ..RenderLayers::default()
+        });
+        while !ppu.tick(1) {}
+        assert_eq!(pixel(&ppu, 16, 50), 0x21);
+        assert_eq!(pixel(&ppu, 16, 100), 0x0F);
+    }
+
+    const PATTERN_COLORS: [u8; 4] = [0x0F, 0x16, 0x2A, 0x12];
+
+    // Pixel value of every pixel in a tile of the given nametable
+    fn tile_pattern(table: usize, row: usize, column: usize) -> usize {
+        (row + column * 3 + table) % 4
+    }
+
+    // Vertical mirroring with both nametables filled with `tile_pattern`
+    fn patterned_ppu(nametable: u8, scroll_x: u8, scroll_y: u8) -> PPU {
+        // Tile n is a solid block of pixel value n
+        let mut chr = vec![0; 0x2000];
+        for tile in 0..4 {
+            let start = tile * 16;
+            chr[start..start + 8].fill(if tile & 1 != 0 { 0xFF } else { 0 });
+            chr[start + 8..start + 16].fill(if tile & 2 != 0 { 0xFF } else { 0 });
+        }
+        let mut ppu = setup_ppu(chr);
+        ppu.set_mirroring(Mirroring::Vertical);
+
+        for table in 0..2 {
+            ppu.write_ppuaddr(0x20 + table as u8 * 4);
+            ppu.write_ppuaddr(0x00);
+            for i in 0..0x3C0 {
+                ppu.write_ppudata(tile_pattern(table, i / 32, i % 32) as u8);
+            }
+        }
+        write_palette(&mut ppu, 0x00, &PATTERN_COLORS);
+
+        ppu.write_ppuctrl(nametable);
+        ppu.write_ppuscroll(scroll_x);
+        ppu.write_ppuscroll(scroll_y);
+        ppu.write_ppumask(0b0000_1010);
+        while !ppu.tick(1) {}
+        while !ppu.tick(1) {}
+        ppu
+    }
+
+    fn pattern_color(table: usize, world_x: usize, world_y: usize) -> u8 {
+        PATTERN_COLORS[tile_pattern(table, world_y % 240 / 8, world_x % 256 / 8)]
+    }
+
+    fn pixel(ppu: &PPU, x: usize, y: usize) -> u8 {
+        ppu.index_buffer()[y * PPU::FRAME_WIDTH + x]
+    }
+
+    #[test]
+    fn test_horizontal_scroll_seam() {
+        // Not a multiple of 8, so tiles on both sides of the seam are cut
+        let scroll_x = 13;
+        let ppu = patterned_ppu(0, scroll_x as u8, 0);
+
+        let seam = 256 - scroll_x;
+        for y in [0, 7, 8, 239] {
+            assert_eq!(pixel(&ppu, 0, y), pattern_color(0, scroll_x, y));
+            assert_eq!(pixel(&ppu, seam - 1, y), pattern_color(0, 255, y));
+            assert_eq!(pixel(&ppu, seam, y), pattern_color(1, 0, y));
+            assert_eq!(pixel(&ppu, 255, y), pattern_color(1, scroll_x - 1, y));
+        }
+    }
+
+    #[test]
+    fn test_scroll_past_nametable_edges_reads_neighbours() {
+        // The first nametable only covers the top left 156x190 pixels
+        let ppu = patterned_ppu(0, 100, 50);
+
+        assert_eq!(pixel(&ppu, 0, 0), pattern_color(0, 100, 50));
+        assert_eq!(pixel(&ppu, 155, 189), pattern_color(0, 255, 239));
+        assert_eq!(pixel(&ppu, 156, 0), pattern_color(1, 0, 50));
+        // Below it is the first nametable again with vertical mirroring
+        assert_eq!(pixel(&ppu, 0, 190), pattern_color(0, 100, 0));
+        assert_eq!(pixel(&ppu, 255, 239), pattern_color(1, 99, 49));
+    }
+
+    #[test]
+    fn test_diagonal_scroll_matches_reference_frame() {
+        let (scroll_x, scroll_y) = (100, 50);
+        // Scroll starts in the bottom right nametable ($2C00)
+        let ppu = patterned_ppu(0b11, scroll_x as u8, scroll_y as u8);
+
+        for y in 0..PPU::FRAME_HEIGHT {
+            for x in 0..PPU::FRAME_WIDTH {
+                let world_x = (x + scroll_x + 256) % 512;
+                let world_y = (y + scroll_y + 240) % 480;
+                // Vertical mirroring: left tables are the first one, right tables the second
+                let table = world_x / 256;
+                assert_eq!(
+                    pixel(&ppu, x, y),
+                    pattern_color(table, world_x, world_y),
+                    "pixel ({x}, {y})"
+                );
+            }
+        }
+    }
+}
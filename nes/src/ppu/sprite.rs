@@ -0,0 +1,73 @@
+// Sprite attributes stored in OAM, 4 bytes per sprite
+// https://www.nesdev.org/wiki/PPU_OAM
+//
+// Byte 2 - attributes
+// 76543210
+// ||||||||
+// ||||||++- Palette (4 to 7) of sprite
+// |||+++--- Unimplemented (read 0)
+// ||+------ Priority (0: in front of background; 1: behind background)
+// |+------- Flip sprite horizontally
+// +-------- Flip sprite vertically
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpriteEntry {
+    pub y: u8,
+    pub tile: u8,
+    pub attributes: u8,
+    pub x: u8,
+}
+
+impl SpriteEntry {
+    pub fn new(bytes: &[u8]) -> Self {
+        SpriteEntry {
+            y: bytes[0],
+            tile: bytes[1],
+            attributes: bytes[2],
+            x: bytes[3],
+        }
+    }
+
+    pub fn palette(&self) -> u8 {
+        self.attributes & 0b11
+    }
+
+    pub fn is_behind_background(&self) -> bool {
+        self.attributes & 0b0010_0000 != 0
+    }
+
+    pub fn flip_horizontal(&self) -> bool {
+        self.attributes & 0b0100_0000 != 0
+    }
+
+    pub fn flip_vertical(&self) -> bool {
+        self.attributes & 0b1000_0000 != 0
+    }
+}
+
+// A decoded OAM entry together with its index, index 0 has the highest priority.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sprite {
+    pub index: usize,
+    pub x: u8,
+    pub y: u8,
+    pub tile: u8,
+    pub palette: u8,
+    pub flip_horizontal: bool,
+    pub flip_vertical: bool,
+    pub behind_background: bool,
+}
+
+impl Sprite {
+    pub fn new(index: usize, entry: SpriteEntry) -> Self {
+        Sprite {
+            index,
+            x: entry.x,
+            y: entry.y,
+            tile: entry.tile,
+            palette: entry.palette(),
+            flip_horizontal: entry.flip_horizontal(),
+            flip_vertical: entry.flip_vertical(),
+            behind_background: entry.is_behind_background(),
+        }
+    }
+}
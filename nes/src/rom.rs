@@ -1,3 +1,5 @@
 mod control_bytes;
 mod error;
+pub mod fingerprint;
+pub mod header;
 pub mod rom;
@@ -1,10 +1,5 @@
 use crate::ppu::mirroring::Mirroring;
-
-#[derive(PartialEq)]
-pub enum NESFormat {
-    NES1,
-    NES2,
-}
+use crate::rom::header::NESFormat;
 
 pub struct ControlBytes {
     byte1: u8,
@@ -27,6 +22,10 @@ impl ControlBytes {
         }
     }
 
+    pub fn has_battery(&self) -> bool {
+        self.byte1 & 0b0000_0010 != 0
+    }
+
     pub fn trainer_size(&self) -> usize {
         if self.byte1 & 0b0000_0100 != 0 {
             512
@@ -36,7 +35,7 @@ impl ControlBytes {
     }
 
     pub fn mapper(&self) -> u8 {
-        self.byte2 & 0b1111_0000 + ((self.byte1 & 0b1111_0000) >> 4)
+        (self.byte2 & 0b1111_0000) | ((self.byte1 & 0b1111_0000) >> 4)
     }
 
     pub fn nes_format(&self) -> NESFormat {
@@ -47,3 +46,26 @@ impl ControlBytes {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mapper_from_lower_nibble() {
+        let control_bytes = ControlBytes::new(0x30, 0x00);
+        assert_eq!(control_bytes.mapper(), 3);
+    }
+
+    #[test]
+    fn test_mapper_zero() {
+        let control_bytes = ControlBytes::new(0x01, 0x00);
+        assert_eq!(control_bytes.mapper(), 0);
+    }
+
+    #[test]
+    fn test_mapper_from_both_nibbles() {
+        let control_bytes = ControlBytes::new(0x40, 0x10);
+        assert_eq!(control_bytes.mapper(), 0x14);
+    }
+}
@@ -1,5 +1,5 @@
-use std::error::Error;
-use std::fmt::{Display, Formatter};
+use core::error::Error;
+use core::fmt::{Display, Formatter};
 
 #[derive(Debug)]
 pub enum InvalidINESFile<'a> {
@@ -10,10 +10,14 @@ pub enum InvalidINESFile<'a> {
     ControlByte2Absent,
     FailedToReadPRGROM,
     FailedToReadCHRROM,
+    FailedToReadTrainer,
+    FileTruncated { expected: usize, actual: usize },
+    UnsupportedNES2,
+    EmptyPRGROM,
 }
 
 impl Display for InvalidINESFile<'_> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
         match self {
             InvalidINESFile::IncorrectNESTag(actual, expected) => {
                 write!(
@@ -32,6 +36,14 @@ impl Display for InvalidINESFile<'_> {
             }
             InvalidINESFile::FailedToReadPRGROM => write!(f, "Failed to read PRGROM data"),
             InvalidINESFile::FailedToReadCHRROM => write!(f, "Failed to read CHRROM data"),
+            InvalidINESFile::FailedToReadTrainer => write!(f, "Failed to read trainer data"),
+            InvalidINESFile::FileTruncated { expected, actual } => write!(
+                f,
+                "File is truncated, header declares {} bytes, actual {}",
+                expected, actual
+            ),
+            InvalidINESFile::UnsupportedNES2 => write!(f, "NES2.0 isn't supported"),
+            InvalidINESFile::EmptyPRGROM => write!(f, "Header declares 0 PRGROM banks"),
         }
     }
 }
@@ -0,0 +1,192 @@
+use crate::rom::header::NesHeader;
+use crate::rom::rom::Rom;
+use alloc::string::String;
+use core::fmt::Write;
+
+// Identifies a dump for matching against ROM databases (No-Intro, GoodNES).
+// Hashes cover PRG and CHR ROM without the iNES header, which differs
+// between dumps of the same cartridge.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RomFingerprint {
+    pub header: NesHeader,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_crc32"))]
+    pub crc32: u32,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_sha1"))]
+    pub sha1: [u8; 20],
+}
+
+// Hashes are written in upper case hex, the way ROM databases list them
+impl RomFingerprint {
+    pub fn crc32_hex(&self) -> String {
+        hex(&self.crc32.to_be_bytes())
+    }
+
+    pub fn sha1_hex(&self) -> String {
+        hex(&self.sha1)
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        write!(hex, "{:02X}", byte).unwrap();
+        hex
+    })
+}
+
+#[cfg(feature = "serde")]
+fn serialize_crc32<S: serde::Serializer>(crc32: &u32, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&hex(&crc32.to_be_bytes()))
+}
+
+#[cfg(feature = "serde")]
+fn serialize_sha1<S: serde::Serializer>(sha1: &[u8; 20], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&hex(sha1))
+}
+
+impl Rom {
+    pub fn fingerprint(&self) -> RomFingerprint {
+        // CHR RAM isn't part of the dump
+        let chr: &[u8] = if self.uses_chr_ram() {
+            &[]
+        } else {
+            &self.chr_rom
+        };
+        let data = || self.prg_rom.iter().chain(chr).copied();
+        RomFingerprint {
+            header: self.header(),
+            crc32: crc32(data()),
+            sha1: sha1(data()),
+        }
+    }
+}
+
+// CRC-32/ISO-HDLC, the one used by zip
+fn crc32(data: impl Iterator<Item = u8>) -> u32 {
+    !data.fold(!0u32, |crc, byte| {
+        (0..8).fold(crc ^ byte as u32, |crc, _| {
+            if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            }
+        })
+    })
+}
+
+// https://datatracker.ietf.org/doc/html/rfc3174
+fn sha1(data: impl Iterator<Item = u8>) -> [u8; 20] {
+    let mut state: [u32; 5] = [
+        0x6745_2301,
+        0xEFCD_AB89,
+        0x98BA_DCFE,
+        0x1032_5476,
+        0xC3D2_E1F0,
+    ];
+    let mut block = [0u8; 64];
+    let mut length: u64 = 0;
+
+    for byte in data {
+        block[(length % 64) as usize] = byte;
+        length += 1;
+        if length.is_multiple_of(64) {
+            sha1_block(&mut state, &block);
+        }
+    }
+
+    // Padding: a 1 bit, zeros, then the message length in bits
+    let mut filled = (length % 64) as usize;
+    block[filled] = 0x80;
+    block[filled + 1..].fill(0);
+    filled += 1;
+    if filled > 56 {
+        sha1_block(&mut state, &block);
+        block = [0; 64];
+    }
+    block[56..].copy_from_slice(&(length * 8).to_be_bytes());
+    sha1_block(&mut state, &block);
+
+    let mut digest = [0u8; 20];
+    for (chunk, word) in digest.chunks_exact_mut(4).zip(state) {
+        chunk.copy_from_slice(&word.to_be_bytes());
+    }
+    digest
+}
+
+fn sha1_block(state: &mut [u32; 5], block: &[u8; 64]) {
+    let mut w = [0u32; 80];
+    for (i, chunk) in block.chunks_exact(4).enumerate() {
+        w[i] = u32::from_be_bytes(chunk.try_into().unwrap());
+    }
+    for i in 16..80 {
+        w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e] = *state;
+    for (i, word) in w.iter().enumerate() {
+        let (f, k) = match i {
+            0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+            20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+            40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+            _ => (b ^ c ^ d, 0xCA62_C1D6),
+        };
+        let temp = a
+            .rotate_left(5)
+            .wrapping_add(f)
+            .wrapping_add(e)
+            .wrapping_add(k)
+            .wrapping_add(*word);
+        e = d;
+        d = c;
+        c = b.rotate_left(30);
+        b = a;
+        a = temp;
+    }
+
+    for (value, new) in state.iter_mut().zip([a, b, c, d, e]) {
+        *value = value.wrapping_add(new);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ppu::mirroring::Mirroring;
+    use crate::rom::rom::ines_image;
+
+    #[test]
+    fn test_hashes_match_reference_values() {
+        let abc = "abc".bytes();
+        assert_eq!(crc32(abc.clone()), 0x3524_41C2);
+        assert_eq!(hex(&sha1(abc)), "A9993E364706816ABA3E25717850C26C9CD0D89D");
+        // Two blocks of padding
+        let long = "abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq".bytes();
+        assert_eq!(hex(&sha1(long)), "84983E441C3BD26EBAAE4AA1F95129E5E54670F1");
+        assert_eq!(
+            hex(&sha1("".bytes())),
+            "DA39A3EE5E6B4B0D3255BFEF95601890AFD80709"
+        );
+    }
+
+    #[test]
+    fn test_fingerprint_covers_prg_and_chr_only() {
+        let mut content = ines_image(0, 1, 1);
+        for (i, byte) in content[16..].iter_mut().enumerate() {
+            *byte = i as u8;
+        }
+        let fingerprint = Rom::new(&content).unwrap().fingerprint();
+
+        // Same data with a different header byte
+        content[7] = 0x00;
+        content[9] = 0x01;
+        assert_eq!(
+            Rom::new(&content).unwrap().fingerprint().sha1,
+            fingerprint.sha1
+        );
+
+        assert_eq!(fingerprint.crc32, crc32(content[16..].iter().copied()));
+        assert_eq!(fingerprint.header.mirroring, Mirroring::Horizontal);
+        assert_eq!(fingerprint.crc32_hex().len(), 8);
+        assert_eq!(fingerprint.sha1_hex().len(), 40);
+    }
+}
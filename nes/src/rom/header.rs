@@ -0,0 +1,23 @@
+use crate::ppu::mirroring::Mirroring;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum NESFormat {
+    NES1,
+    NES2,
+}
+
+// Cartridge information from the iNES header, e.g. for a ROM info screen.
+// Bank counts are in 16 KiB (PRG) and 8 KiB (CHR) units, 0 CHR banks means
+// the cartridge uses CHR RAM.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct NesHeader {
+    pub prg_banks: usize,
+    pub chr_banks: usize,
+    pub mapper: u8,
+    pub mirroring: Mirroring,
+    pub battery: bool,
+    pub trainer: bool,
+    pub format: NESFormat,
+}
@@ -1,18 +1,29 @@
 use crate::ppu::mirroring::Mirroring;
-use crate::rom::control_bytes::{ControlBytes, NESFormat};
+use crate::region::Region;
+use crate::rom::control_bytes::ControlBytes;
 use crate::rom::error::InvalidINESFile;
+use crate::rom::header::{NESFormat, NesHeader};
+use alloc::vec;
+use alloc::vec::Vec;
 
 pub struct Rom {
     pub prg_rom: Vec<u8>,
     pub chr_rom: Vec<u8>,
     mapper: u8,
     pub mirroring: Mirroring,
+    pub region: Region,
+    // 512 bytes the cartridge expects to find at $7000-$71FF
+    pub trainer: Option<Vec<u8>>,
+    chr_ram: bool,
+    battery: bool,
+    format: NESFormat,
 }
 
 impl Rom {
     const NES_TAG: [u8; 4] = [0x4E, 0x45, 0x53, 0x1A];
     const PRG_ROM_SIZE: usize = 16384;
     const CHRROM_SIZE: usize = 8192;
+    const CHRRAM_SIZE: usize = 8192;
 
     pub fn new(content: &[u8]) -> Result<Self, InvalidINESFile> {
         let nes_tag = content
@@ -32,25 +43,272 @@ impl Rom {
         );
 
         if control_bytes.nes_format() == NESFormat::NES2 {
-            panic!("NES2.0 isn't supported")
+            return Err(InvalidINESFile::UnsupportedNES2);
         }
+        if prg_rom_size == 0 {
+            return Err(InvalidINESFile::EmptyPRGROM);
+        }
+
+        let trainer_start = 16;
+        let trainer_size = control_bytes.trainer_size();
+        let trainer = if trainer_size > 0 {
+            Some(
+                content
+                    .get(trainer_start..(trainer_start + trainer_size))
+                    .ok_or(InvalidINESFile::FailedToReadTrainer)?
+                    .to_vec(),
+            )
+        } else {
+            None
+        };
 
-        let prg_rom_start = 16 + control_bytes.trainer_size();
+        let prg_rom_start = trainer_start + trainer_size;
         let chr_rom_start = prg_rom_start + prg_rom_size;
+        let expected = chr_rom_start + chr_rom_size;
+        if content.len() < expected {
+            return Err(InvalidINESFile::FileTruncated {
+                expected,
+                actual: content.len(),
+            });
+        }
+
+        // A cartridge without CHR ROM has 8 KiB of CHR RAM instead.
+        let chr_ram = chr_rom_size == 0;
+        let chr_rom = if chr_ram {
+            vec![0; Rom::CHRRAM_SIZE]
+        } else {
+            content
+                .get(chr_rom_start..(chr_rom_start + chr_rom_size))
+                .ok_or(InvalidINESFile::FailedToReadCHRROM)?
+                .to_vec()
+        };
 
         Ok(Rom {
             prg_rom: content
                 .get(prg_rom_start..(prg_rom_start + prg_rom_size))
                 .ok_or(InvalidINESFile::FailedToReadPRGROM)?
-                .try_into()
-                .unwrap(),
-            chr_rom: content
-                .get(chr_rom_start..(chr_rom_start + chr_rom_size))
-                .ok_or(InvalidINESFile::FailedToReadCHRROM)?
-                .try_into()
-                .unwrap(),
+                .to_vec(),
+            chr_rom,
             mapper: control_bytes.mapper(),
             mirroring: control_bytes.mirroring(),
+            region: Rom::tv_system(content),
+            trainer,
+            chr_ram,
+            battery: control_bytes.has_battery(),
+            format: control_bytes.nes_format(),
         })
     }
+
+    // Builds a NROM cartridge from raw PRG and CHR data without an iNES header,
+    // e.g. assembler output or test fixtures. 16 KiB of PRG is mirrored into
+    // $C000-$FFFF, so the vectors live at the end of the data. Empty CHR means
+    // the cartridge uses 8 KiB of CHR RAM.
+    pub fn from_prg(prg: &[u8], chr: &[u8], mirroring: Mirroring) -> Self {
+        let chr_ram = chr.is_empty();
+        Rom {
+            prg_rom: prg.to_vec(),
+            chr_rom: if chr_ram {
+                vec![0; Rom::CHRRAM_SIZE]
+            } else {
+                chr.to_vec()
+            },
+            mapper: 0,
+            mirroring,
+            region: Region::Ntsc,
+            trainer: None,
+            chr_ram,
+            battery: false,
+            format: NESFormat::NES1,
+        }
+    }
+
+    // Byte 9 bit 0 is the TV system flag (0: NTSC, 1: PAL). Few dumps set it,
+    // so NTSC is used when it is absent.
+    fn tv_system(content: &[u8]) -> Region {
+        match content.get(9) {
+            Some(flags) if flags & 1 == 1 => Region::Pal,
+            _ => Region::Ntsc,
+        }
+    }
+
+    pub fn header(&self) -> NesHeader {
+        NesHeader {
+            prg_banks: self.prg_rom.len() / Rom::PRG_ROM_SIZE,
+            chr_banks: if self.chr_ram {
+                0
+            } else {
+                self.chr_rom.len() / Rom::CHRROM_SIZE
+            },
+            mapper: self.mapper,
+            mirroring: self.mirroring,
+            battery: self.battery,
+            trainer: self.trainer.is_some(),
+            format: self.format,
+        }
+    }
+
+    pub fn mapper(&self) -> u8 {
+        self.mapper
+    }
+
+    pub fn uses_chr_ram(&self) -> bool {
+        self.chr_ram
+    }
+
+    // PRG RAM is battery backed, so frontends should persist it between runs
+    pub fn has_battery(&self) -> bool {
+        self.battery
+    }
+}
+
+// iNES image with zeroed PRG and CHR ROM, shared by tests that need a
+// cartridge file rather than a `Rom`
+#[cfg(test)]
+pub(crate) fn ines_image(mapper: u8, prg_banks: u8, chr_banks: u8) -> Vec<u8> {
+    let mut content = Rom::NES_TAG.to_vec();
+    content.extend([prg_banks, chr_banks, mapper << 4, mapper & 0xF0]);
+    content.resize(
+        16 + prg_banks as usize * Rom::PRG_ROM_SIZE + chr_banks as usize * Rom::CHRROM_SIZE,
+        0,
+    );
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trainer_is_skipped_before_prg_rom() {
+        let mut content = vec![0x4E, 0x45, 0x53, 0x1A, 0x01, 0x01, 0b0000_0100, 0x00];
+        content.resize(16, 0);
+        content.extend(vec![0xAA; 512]);
+        content.extend(vec![0xBB; 0x4000]);
+        content.extend(vec![0xCC; 0x2000]);
+
+        let rom = Rom::new(&content).unwrap();
+
+        assert_eq!(rom.trainer, Some(vec![0xAA; 512]));
+        assert_eq!(rom.prg_rom, vec![0xBB; 0x4000]);
+        assert_eq!(rom.chr_rom, vec![0xCC; 0x2000]);
+    }
+
+    #[test]
+    fn test_header_describes_cartridge() {
+        // 2 PRG banks, CHR RAM, mapper 4, vertical mirroring, battery
+        let mut content = vec![0x4E, 0x45, 0x53, 0x1A, 0x02, 0x00, 0b0100_0011, 0x00];
+        content.resize(16, 0);
+        content.extend(vec![0; 0x8000]);
+
+        let header = Rom::new(&content).unwrap().header();
+
+        assert_eq!(
+            header,
+            NesHeader {
+                prg_banks: 2,
+                chr_banks: 0,
+                mapper: 4,
+                mirroring: Mirroring::Vertical,
+                battery: true,
+                trainer: false,
+                format: NESFormat::NES1,
+            }
+        );
+    }
+
+    #[test]
+    fn test_rom_without_trainer() {
+        let mut content = vec![0x4E, 0x45, 0x53, 0x1A, 0x01, 0x01, 0x00, 0x00];
+        content.resize(16, 0);
+        content.extend(vec![0xBB; 0x4000]);
+        content.extend(vec![0xCC; 0x2000]);
+
+        let rom = Rom::new(&content).unwrap();
+
+        assert_eq!(rom.trainer, None);
+        assert_eq!(rom.prg_rom, vec![0xBB; 0x4000]);
+    }
+
+    #[test]
+    fn test_truncated_prg_rom_is_rejected() {
+        // Header claims 2 PRG banks and CHR RAM, only one bank is present
+        let mut content = vec![0x4E, 0x45, 0x53, 0x1A, 0x02, 0x00, 0x00, 0x00];
+        content.resize(16, 0);
+        content.extend(vec![0xBB; 0x4000]);
+
+        match Rom::new(&content) {
+            Err(InvalidINESFile::FileTruncated { expected, actual }) => {
+                assert_eq!(expected, 16 + 0x8000);
+                assert_eq!(actual, 16 + 0x4000);
+            }
+            _ => panic!("Expected FileTruncated error"),
+        }
+    }
+
+    #[test]
+    fn test_truncated_chr_rom_is_rejected() {
+        let mut content = vec![0x4E, 0x45, 0x53, 0x1A, 0x01, 0x01, 0x00, 0x00];
+        content.resize(16, 0);
+        content.extend(vec![0xBB; 0x4000]);
+        content.extend(vec![0xCC; 0x1FFF]);
+
+        match Rom::new(&content) {
+            Err(InvalidINESFile::FileTruncated { expected, actual }) => {
+                assert_eq!(expected, 16 + 0x6000);
+                assert_eq!(actual, 16 + 0x5FFF);
+            }
+            _ => panic!("Expected FileTruncated error"),
+        }
+    }
+
+    #[test]
+    fn test_nes2_header_is_an_error() {
+        let mut content = vec![0x4E, 0x45, 0x53, 0x1A, 0x01, 0x00, 0x00, 0b0000_1000];
+        content.resize(16 + 0x4000, 0);
+
+        assert!(matches!(
+            Rom::new(&content),
+            Err(InvalidINESFile::UnsupportedNES2)
+        ));
+    }
+
+    // Loading arbitrary files must never panic, whatever the header claims
+    #[test]
+    fn test_malformed_input_never_panics() {
+        let mut state: u32 = 0x2545_F491;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state
+        };
+
+        for _ in 0..1000 {
+            let len = next() as usize % 0x9000;
+            let mut content: Vec<u8> = (0..len).map(|_| next() as u8).collect();
+            // Most buffers get a valid tag so the rest of the header is exercised
+            if content.len() >= 4 && next() % 4 != 0 {
+                content[..4].copy_from_slice(&Rom::NES_TAG);
+                if content.len() > 5 {
+                    content[4] %= 3;
+                    content[5] %= 3;
+                }
+            }
+
+            if let Ok(rom) = Rom::new(&content) {
+                assert!(!rom.prg_rom.is_empty());
+                assert!(rom.chr_rom.len() >= Rom::CHRRAM_SIZE);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rom_from_prg_without_chr_uses_chr_ram() {
+        let rom = Rom::from_prg(&[0xEA; 0x4000], &[], Mirroring::Vertical);
+
+        assert_eq!(rom.mapper(), 0);
+        assert!(rom.uses_chr_ram());
+        assert_eq!(rom.chr_rom, vec![0; 0x2000]);
+        assert_eq!(rom.prg_rom, vec![0xEA; 0x4000]);
+    }
 }
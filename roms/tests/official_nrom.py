@@ -0,0 +1,414 @@
+#!/usr/bin/env python3
+"""Builds official_nrom.nes, a self-checking NROM test ROM.
+
+The ROM exercises official 6502 instructions and reports through PRG RAM the
+same way blargg's test ROMs do: signature DE B0 61 at $6001, status at $6000
+($80 running, $81 reset requested, then the result code) and a zero
+terminated message at $6004. On the first boot it requests a reset so the
+reset handshake is covered as well.
+
+    python3 roms/tests/official_nrom.py
+"""
+
+import os
+
+MODES = {
+    "imp": 0, "acc": 0, "imm": 1, "zp": 1, "zpx": 1, "zpy": 1, "indx": 1,
+    "indy": 1, "rel": 1, "abs": 2, "absx": 2, "absy": 2, "ind": 2,
+}
+
+OPCODES = {
+    ("ADC", "imm"): 0x69, ("ADC", "zp"): 0x65, ("ADC", "abs"): 0x6D,
+    ("AND", "imm"): 0x29, ("ASL", "acc"): 0x0A, ("ASL", "zp"): 0x06,
+    ("BCC", "rel"): 0x90, ("BCS", "rel"): 0xB0, ("BEQ", "rel"): 0xF0,
+    ("BIT", "zp"): 0x24, ("BMI", "rel"): 0x30, ("BNE", "rel"): 0xD0,
+    ("BPL", "rel"): 0x10, ("BRK", "imp"): 0x00, ("BVC", "rel"): 0x50,
+    ("BVS", "rel"): 0x70, ("CLC", "imp"): 0x18, ("CLD", "imp"): 0xD8,
+    ("CLV", "imp"): 0xB8, ("CMP", "imm"): 0xC9, ("CMP", "zp"): 0xC5,
+    ("CPX", "imm"): 0xE0, ("CPY", "imm"): 0xC0, ("DEC", "zp"): 0xC6,
+    ("DEX", "imp"): 0xCA, ("DEY", "imp"): 0x88, ("EOR", "imm"): 0x49,
+    ("INC", "zp"): 0xE6, ("INX", "imp"): 0xE8, ("INY", "imp"): 0xC8,
+    ("JMP", "abs"): 0x4C, ("JMP", "ind"): 0x6C, ("JSR", "abs"): 0x20,
+    ("LDA", "imm"): 0xA9, ("LDA", "zp"): 0xA5, ("LDA", "zpx"): 0xB5,
+    ("LDA", "abs"): 0xAD, ("LDA", "absx"): 0xBD, ("LDA", "absy"): 0xB9,
+    ("LDA", "indx"): 0xA1, ("LDA", "indy"): 0xB1, ("LDX", "imm"): 0xA2,
+    ("LDX", "zpy"): 0xB6, ("LDY", "imm"): 0xA0, ("LSR", "acc"): 0x4A,
+    ("ORA", "imm"): 0x09, ("PHA", "imp"): 0x48, ("PHP", "imp"): 0x08,
+    ("PLA", "imp"): 0x68, ("PLP", "imp"): 0x28, ("ROL", "acc"): 0x2A,
+    ("ROR", "acc"): 0x6A, ("ROR", "zp"): 0x66, ("RTI", "imp"): 0x40,
+    ("RTS", "imp"): 0x60, ("SBC", "imm"): 0xE9, ("SEC", "imp"): 0x38,
+    ("SED", "imp"): 0xF8, ("SEI", "imp"): 0x78, ("STA", "zp"): 0x85,
+    ("STA", "zpx"): 0x95, ("STA", "abs"): 0x8D, ("STA", "absx"): 0x9D,
+    ("STA", "absy"): 0x99, ("STA", "indy"): 0x91, ("STX", "zp"): 0x86,
+    ("STY", "zp"): 0x84, ("TAX", "imp"): 0xAA, ("TAY", "imp"): 0xA8,
+    ("TSX", "imp"): 0xBA, ("TXA", "imp"): 0x8A, ("TXS", "imp"): 0x9A,
+    ("TYA", "imp"): 0x98,
+}
+
+ORIGIN = 0xC000
+
+# Zero page and PRG RAM used by the ROM
+MESSAGE_POINTER = 0x00
+TEST_NUMBER = 0x10
+RESET_MARKER = 0x6100
+
+
+class Assembler:
+    def __init__(self, origin):
+        self.origin = origin
+        self.code = bytearray()
+        self.labels = {}
+        self.fixups = []
+
+    def pc(self):
+        return self.origin + len(self.code)
+
+    def label(self, name):
+        assert name not in self.labels, name
+        self.labels[name] = self.pc()
+
+    def byte(self, *values):
+        self.code.extend(values)
+
+    def text(self, string):
+        self.code.extend(string.encode("ascii") + b"\0")
+
+    def op(self, mnemonic, mode="imp", operand=0):
+        self.code.append(OPCODES[(mnemonic, mode)])
+        size = MODES[mode]
+        if isinstance(operand, str):
+            self.fixups.append((len(self.code), mode, operand))
+            operand = 0
+        if size == 1:
+            self.code.append(operand & 0xFF)
+        elif size == 2:
+            self.code.extend([operand & 0xFF, operand >> 8])
+
+    def resolve(self):
+        for offset, mode, name in self.fixups:
+            # "<label" and ">label" are the low and high byte of an address
+            target = self.labels[name.lstrip("<>")]
+            if mode == "rel":
+                delta = target - (self.origin + offset + 1)
+                assert -128 <= delta <= 127, name
+                self.code[offset] = delta & 0xFF
+            elif name.startswith("<"):
+                self.code[offset] = target & 0xFF
+            elif name.startswith(">"):
+                self.code[offset] = target >> 8
+            else:
+                self.code[offset:offset + 2] = bytes([target & 0xFF, target >> 8])
+        return bytes(self.code)
+
+
+def build():
+    a = Assembler(ORIGIN)
+    tests = []
+
+    def store(address, value):
+        a.op("LDA", "imm", value)
+        a.op("STA", "abs", address)
+
+    def begin(name):
+        number = len(tests) + 2
+        tests.append(name)
+        a.op("LDA", "imm", number)
+        a.op("STA", "zp", TEST_NUMBER)
+        a.op("LDA", "imm", f"<message{number}")
+        a.op("STA", "zp", MESSAGE_POINTER)
+        a.op("LDA", "imm", f">message{number}")
+        a.op("STA", "zp", MESSAGE_POINTER + 1)
+
+    def expect(mode, value):
+        a.op("CMP", mode, value)
+        a.op("BEQ", "rel", 3)
+        a.op("JMP", "abs", "fail")
+
+    # Keeps the N, V, Z and C flags of the status register
+    def expect_flags(value):
+        a.op("PHP")
+        a.op("PLA")
+        a.op("AND", "imm", 0xC3)
+        expect("imm", value)
+
+    a.label("reset")
+    a.op("SEI")
+    a.op("CLD")
+    a.op("LDX", "imm", 0xFF)
+    a.op("TXS")
+    for offset, value in enumerate([0xDE, 0xB0, 0x61]):
+        store(0x6001 + offset, value)
+    a.op("LDA", "abs", RESET_MARKER)
+    a.op("CMP", "imm", 0xA5)
+    a.op("BEQ", "rel", "after_reset")
+    store(RESET_MARKER, 0xA5)
+    store(0x6000, 0x81)
+    a.label("wait_for_reset")
+    a.op("JMP", "abs", "wait_for_reset")
+
+    a.label("after_reset")
+    store(0x6000, 0x80)
+
+    # Every check leaves Z and C set, tests clear the flags they look at
+    begin("LDA flags")
+    a.op("CLC")
+    a.op("CLV")
+    a.op("LDA", "imm", 0x80)
+    expect_flags(0x80)
+    a.op("CLC")
+    a.op("LDA", "imm", 0x00)
+    expect_flags(0x02)
+
+    begin("ADC")
+    a.op("CLC")
+    a.op("LDA", "imm", 0x50)
+    a.op("ADC", "imm", 0x50)
+    expect_flags(0xC0)
+    a.op("SEC")
+    a.op("LDA", "imm", 0xFF)
+    a.op("ADC", "imm", 0x00)
+    expect_flags(0x03)
+
+    begin("SBC")
+    a.op("SEC")
+    a.op("LDA", "imm", 0x50)
+    a.op("SBC", "imm", 0xB0)
+    a.op("STA", "zp", 0x20)
+    expect_flags(0xC0)
+    a.op("LDA", "zp", 0x20)
+    expect("imm", 0xA0)
+
+    begin("Shifts and rotates")
+    a.op("LDA", "imm", 0x81)
+    a.op("SEC")
+    a.op("ROL", "acc")
+    expect("imm", 0x03)
+    a.op("LSR", "acc")
+    a.op("ROR", "acc")
+    expect("imm", 0x80)
+    a.op("CLV")
+    a.op("ASL", "acc")
+    expect_flags(0x03)
+
+    begin("Read-modify-write")
+    a.op("LDA", "imm", 0xFF)
+    a.op("STA", "zp", 0x20)
+    a.op("INC", "zp", 0x20)
+    a.op("LDA", "zp", 0x20)
+    expect("imm", 0x00)
+    a.op("DEC", "zp", 0x20)
+    a.op("ASL", "zp", 0x20)
+    a.op("LDA", "zp", 0x20)
+    expect("imm", 0xFE)
+    a.op("SEC")
+    a.op("ROR", "zp", 0x20)
+    a.op("LDA", "zp", 0x20)
+    expect("imm", 0xFF)
+
+    begin("Indexed addressing")
+    a.op("LDX", "imm", 0x00)
+    a.label("fill")
+    a.op("TXA")
+    a.op("EOR", "imm", 0x5A)
+    a.op("STA", "absx", 0x0300)
+    a.op("INX")
+    a.op("BNE", "rel", "fill")
+    a.op("LDX", "imm", 0x10)
+    a.op("LDA", "absx", 0x0300)
+    expect("imm", 0x10 ^ 0x5A)
+    # Crosses into the next page
+    a.op("LDY", "imm", 0x01)
+    a.op("LDA", "absy", 0x02FF)
+    expect("imm", 0x00 ^ 0x5A)
+    # Zero page indexing wraps within page zero
+    a.op("LDA", "imm", 0x77)
+    a.op("STA", "zp", 0x0F)
+    a.op("LDX", "imm", 0xFF)
+    a.op("LDA", "zpx", 0x10)
+    expect("imm", 0x77)
+    a.op("LDY", "imm", 0x01)
+    a.op("LDX", "zpy", 0x0E)
+    a.op("TXA")
+    expect("imm", 0x77)
+
+    begin("Indirect addressing")
+    a.op("LDA", "imm", 0x00)
+    a.op("STA", "zp", 0x30)
+    a.op("LDA", "imm", 0x03)
+    a.op("STA", "zp", 0x31)
+    a.op("LDY", "imm", 0x42)
+    a.op("LDA", "indy", 0x30)
+    expect("imm", 0x42 ^ 0x5A)
+    a.op("LDX", "imm", 0x04)
+    a.op("LDA", "imm", 0x80)
+    a.op("STA", "zp", 0x34)
+    a.op("LDA", "imm", 0x03)
+    a.op("STA", "zp", 0x35)
+    a.op("LDA", "indx", 0x30)
+    expect("imm", 0x80 ^ 0x5A)
+    a.op("LDA", "imm", 0x99)
+    a.op("LDY", "imm", 0x01)
+    a.op("STA", "indy", 0x30)
+    a.op("LDA", "abs", 0x0301)
+    expect("imm", 0x99)
+
+    begin("Compare and BIT")
+    a.op("CLV")
+    a.op("LDA", "imm", 0x40)
+    a.op("CMP", "imm", 0x40)
+    expect_flags(0x03)
+    a.op("LDX", "imm", 0x10)
+    a.op("CPX", "imm", 0x20)
+    expect_flags(0x80)
+    a.op("LDY", "imm", 0x20)
+    a.op("CPY", "imm", 0x10)
+    expect_flags(0x01)
+    a.op("LDA", "imm", 0xC0)
+    a.op("STA", "zp", 0x20)
+    a.op("LDA", "imm", 0x01)
+    a.op("CLC")
+    a.op("BIT", "zp", 0x20)
+    expect_flags(0xC2)
+
+    begin("Branches")
+    a.op("CLC")
+    a.op("BCS", "rel", "fail_near")
+    a.op("SEC")
+    a.op("BCC", "rel", "fail_near")
+    a.op("LDA", "imm", 0x01)
+    a.op("BEQ", "rel", "fail_near")
+    a.op("BMI", "rel", "fail_near")
+    a.op("CLV")
+    a.op("BVS", "rel", "fail_near")
+    a.op("LDA", "imm", 0x00)
+    a.op("BNE", "rel", "fail_near")
+    a.op("BPL", "rel", "branches_taken")
+    a.label("fail_near")
+    a.op("JMP", "abs", "fail")
+    a.label("branches_taken")
+    a.op("LDA", "imm", 0x80)
+    a.op("BPL", "rel", "fail_near")
+    a.op("BVC", "rel", "branches_done")
+    a.op("JMP", "abs", "fail")
+    a.label("branches_done")
+
+    begin("Stack and subroutines")
+    a.op("TSX")
+    a.op("STX", "zp", 0x20)
+    a.op("LDA", "imm", 0x12)
+    a.op("PHA")
+    # Interrupts stay disabled
+    a.op("LDA", "imm", 0xC7)
+    a.op("PHA")
+    a.op("PLP")
+    expect_flags(0xC3)
+    a.op("PLA")
+    expect("imm", 0x12)
+    a.op("JSR", "abs", "subroutine")
+    a.op("TYA")
+    expect("imm", 0x34)
+    a.op("TSX")
+    a.op("TXA")
+    expect("zp", 0x20)
+
+    begin("JMP indirect page wrap")
+    # Without the wrap the high byte comes from $0300 and the jump lands on
+    # a page of KIL opcodes, the status byte then never leaves $80
+    a.op("LDX", "imm", 0x00)
+    a.op("LDA", "imm", 0x02)
+    a.label("fill_kil")
+    a.op("STA", "absx", 0x0400)
+    a.op("INX")
+    a.op("BNE", "rel", "fill_kil")
+    a.op("LDA", "imm", 0x04)
+    a.op("STA", "abs", 0x0300)
+    a.op("LDA", "imm", "<jmp_target")
+    a.op("STA", "abs", 0x02FF)
+    a.op("LDA", "imm", ">jmp_target")
+    a.op("STA", "abs", 0x0200)
+    a.op("JMP", "ind", 0x02FF)
+    a.label("jmp_target")
+
+    begin("Decimal mode")
+    a.op("SED")
+    a.op("CLC")
+    a.op("LDA", "imm", 0x09)
+    a.op("ADC", "imm", 0x01)
+    a.op("CLD")
+    expect("imm", 0x0A)
+
+    begin("BRK and RTI")
+    a.op("LDA", "imm", 0x00)
+    a.op("STA", "zp", 0x40)
+    a.op("STA", "zp", 0x41)
+    a.op("BRK")
+    a.byte(0xEA)
+    a.op("LDA", "zp", 0x40)
+    expect("imm", 0x01)
+    a.op("LDA", "zp", 0x41)
+    expect("imm", 0x10)
+
+    a.op("LDA", "imm", "<passed")
+    a.op("STA", "zp", MESSAGE_POINTER)
+    a.op("LDA", "imm", ">passed")
+    a.op("STA", "zp", MESSAGE_POINTER + 1)
+    a.op("JSR", "abs", "print")
+    store(0x6000, 0x00)
+    a.label("done")
+    a.op("JMP", "abs", "done")
+
+    a.label("fail")
+    a.op("JSR", "abs", "print")
+    a.op("LDA", "zp", TEST_NUMBER)
+    a.op("STA", "abs", 0x6000)
+    a.label("failed")
+    a.op("JMP", "abs", "failed")
+
+    # Copies the message at the pointer to $6004
+    a.label("print")
+    a.op("LDY", "imm", 0x00)
+    a.label("print_loop")
+    a.op("LDA", "indy", MESSAGE_POINTER)
+    a.op("STA", "absy", 0x6004)
+    a.op("BEQ", "rel", "print_done")
+    a.op("INY")
+    a.op("BNE", "rel", "print_loop")
+    a.label("print_done")
+    a.op("RTS")
+
+    a.label("subroutine")
+    a.op("LDY", "imm", 0x34)
+    a.op("RTS")
+
+    # BRK handler, records the B flag pushed with the status register
+    a.label("irq")
+    a.op("PLA")
+    a.op("PHA")
+    a.op("AND", "imm", 0x10)
+    a.op("STA", "zp", 0x41)
+    a.op("INC", "zp", 0x40)
+    a.label("nmi")
+    a.op("RTI")
+
+    a.label("passed")
+    a.text("official_nrom\n\nPassed\n")
+    for number, name in enumerate(tests, start=2):
+        a.label(f"message{number}")
+        a.text(f"official_nrom\n\n{name}\nFailed #{number}\n")
+
+    code = a.resolve()
+    prg = bytearray(0x4000)
+    prg[: len(code)] = code
+    for offset, name in [(0x3FFA, "nmi"), (0x3FFC, "reset"), (0x3FFE, "irq")]:
+        address = a.labels[name]
+        prg[offset:offset + 2] = bytes([address & 0xFF, address >> 8])
+
+    # iNES header: one 16 KiB PRG bank, one 8 KiB CHR bank, mapper 0
+    header = bytes([0x4E, 0x45, 0x53, 0x1A, 0x01, 0x01, 0x00, 0x00]) + bytes(8)
+    return header + bytes(prg) + bytes(0x2000)
+
+
+if __name__ == "__main__":
+    path = os.path.join(os.path.dirname(os.path.abspath(__file__)), "official_nrom.nes")
+    with open(path, "wb") as rom:
+        rom.write(build())